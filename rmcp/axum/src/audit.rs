@@ -0,0 +1,611 @@
+//! Audit logging for MCP requests.
+//!
+//! [`AuditLayer`] inspects the JSON-RPC requests flowing to the rmcp service
+//! and the responses coming back, whether as a JSON body or an SSE stream,
+//! and correlates them by id. Each completed request produces one
+//! [`AuditRecord`] with the caller's [`Principal`], the method, the tool,
+//! prompt or resource it targeted, redacted arguments, the duration and the
+//! outcome.
+//!
+//! Records go to an [`AuditSink`]: [`TracingSink`] emits `tracing` events,
+//! [`JsonlSink`] appends JSON lines to a file, and any
+//! `Fn(AuditRecord)` closure works as a custom sink.
+//!
+//! ```rust,ignore
+//! use rmcp_axum::audit::{AuditLayer, JsonlSink};
+//! use rmcp_axum::auth::{AuthLayer, BearerAuth, oauth::OAuthClaims};
+//!
+//! let audit = AuditLayer::<OAuthClaims>::new(JsonlSink::open("audit.jsonl")?)
+//!     .redact_keys(["ssn"]);
+//!
+//! // Layers run outside-in: authenticate first so records carry the principal.
+//! let app = axum::Router::new()
+//!     .nest_service("/mcp", mcp_service)
+//!     .layer(audit)
+//!     .layer(AuthLayer::new(BearerAuth::new(validator)));
+//! ```
+
+use crate::{
+    auth::Principal,
+    jsonrpc::{self, Message},
+};
+use axum::body::{Body, Bytes};
+use futures::{StreamExt, future::BoxFuture};
+use http::{Request, Response, header};
+use serde::Serialize;
+use serde_json::Value;
+use std::{
+    fs::File,
+    io::{self, Write},
+    marker::PhantomData,
+    path::Path,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// Replacement for redacted argument values.
+const REDACTED: &str = "[REDACTED]";
+
+/// Argument keys redacted by default, matched against the words of a key.
+const DEFAULT_REDACT_KEYS: &[&str] = &[
+    "password",
+    "secret",
+    "token",
+    "authorization",
+    "api_key",
+    "apikey",
+];
+
+/// One audited JSON-RPC request.
+#[derive(Clone, Debug, Serialize)]
+pub struct AuditRecord {
+    /// When the request was received (milliseconds since epoch).
+    pub timestamp_ms: u64,
+    /// The authenticated caller, if any.
+    pub principal: Option<String>,
+    /// The JSON-RPC request id.
+    pub request_id: Value,
+    /// The JSON-RPC method, e.g. `tools/call`.
+    pub method: String,
+    /// Tool or prompt name, or resource URI.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    /// Redacted `arguments` of `tools/call` and `prompts/get`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<Value>,
+    /// Time until the response was seen (milliseconds).
+    pub duration_ms: u64,
+    /// Whether the call failed: a JSON-RPC error, a tool result with
+    /// `isError`, or no response at all.
+    pub is_error: bool,
+    /// JSON-RPC error message, or why no response was seen.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Destination for [`AuditRecord`]s.
+///
+/// Called synchronously as responses stream through; implementations that
+/// do slow I/O should hand records off to a background task.
+pub trait AuditSink: Send + Sync + 'static {
+    /// Record one completed request.
+    fn record(&self, record: AuditRecord);
+}
+
+impl<F> AuditSink for F
+where
+    F: Fn(AuditRecord) + Send + Sync + 'static,
+{
+    fn record(&self, record: AuditRecord) {
+        self(record)
+    }
+}
+
+/// Emits each record as an `INFO` event with target `rmcp_axum::audit`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TracingSink;
+
+impl AuditSink for TracingSink {
+    fn record(&self, record: AuditRecord) {
+        tracing::info!(
+            target: "rmcp_axum::audit",
+            principal = record.principal.as_deref(),
+            request_id = %record.request_id,
+            method = %record.method,
+            target = record.target.as_deref(),
+            arguments = record.arguments.as_ref().map(tracing::field::display),
+            duration_ms = record.duration_ms,
+            is_error = record.is_error,
+            error = record.error.as_deref(),
+            "mcp request",
+        );
+    }
+}
+
+/// Appends each record as a JSON line to a file.
+pub struct JsonlSink {
+    file: Mutex<File>,
+}
+
+impl JsonlSink {
+    /// Open `path` for appending, creating it if needed.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = File::options().create(true).append(true).open(path)?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+}
+
+impl AuditSink for JsonlSink {
+    fn record(&self, record: AuditRecord) {
+        let mut line = serde_json::to_vec(&record).expect("audit record serializes");
+        line.push(b'\n');
+        let mut file = self.file.lock().expect("audit file poisoned");
+        if let Err(e) = file.write_all(&line) {
+            tracing::error!("failed to write audit record: {e}");
+        }
+    }
+}
+
+/// Tower [`Layer`](tower::Layer) that applies [`AuditService`].
+///
+/// `C` is the claims type inserted by the authenticator; requests without
+/// claims are recorded with no principal.
+pub struct AuditLayer<C> {
+    sink: Arc<dyn AuditSink>,
+    /// Redacted keys, as words.
+    redact_keys: Arc<Vec<Vec<String>>>,
+    omit_arguments: bool,
+    _claims: PhantomData<fn() -> C>,
+}
+
+impl<C> Clone for AuditLayer<C> {
+    fn clone(&self) -> Self {
+        Self {
+            sink: self.sink.clone(),
+            redact_keys: self.redact_keys.clone(),
+            omit_arguments: self.omit_arguments,
+            _claims: PhantomData,
+        }
+    }
+}
+
+impl<C> AuditLayer<C> {
+    /// Send records to `sink`.
+    ///
+    /// Argument values are redacted whose key contains the word `password`,
+    /// `secret`, `token`, `authorization`, `api_key` or `apikey`. Keys are
+    /// split into words at separators and camelCase humps, so `accessToken`
+    /// and `X-Api-Key` are redacted but `max_tokens` isn't.
+    pub fn new(sink: impl AuditSink) -> Self {
+        Self {
+            sink: Arc::new(sink),
+            redact_keys: Arc::new(DEFAULT_REDACT_KEYS.iter().map(|k| words(k)).collect()),
+            omit_arguments: false,
+            _claims: PhantomData,
+        }
+    }
+
+    /// Also redact argument values whose key contains the words of any of
+    /// `keys` (case-insensitive), at any depth.
+    pub fn redact_keys<I, K>(mut self, keys: I) -> Self
+    where
+        I: IntoIterator<Item = K>,
+        K: Into<String>,
+    {
+        Arc::make_mut(&mut self.redact_keys).extend(
+            keys.into_iter()
+                .map(|k| words(&k.into()))
+                .filter(|words| !words.is_empty()),
+        );
+        self
+    }
+
+    /// Leave arguments out of records entirely.
+    pub fn omit_arguments(mut self) -> Self {
+        self.omit_arguments = true;
+        self
+    }
+
+    fn arguments(&self, message: &Message) -> Option<Value> {
+        if self.omit_arguments {
+            return None;
+        }
+        let mut arguments = message.params.as_ref()?.get("arguments")?.clone();
+        redact(&mut arguments, &self.redact_keys);
+        Some(arguments)
+    }
+}
+
+impl<C, S> tower::Layer<S> for AuditLayer<C> {
+    type Service = AuditService<C, S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AuditService {
+            layer: self.clone(),
+            inner,
+        }
+    }
+}
+
+/// Tower service that records audited requests.
+pub struct AuditService<C, S> {
+    layer: AuditLayer<C>,
+    inner: S,
+}
+
+impl<C, S: Clone> Clone for AuditService<C, S> {
+    fn clone(&self) -> Self {
+        Self {
+            layer: self.layer.clone(),
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<C, S> tower::Service<Request<Body>> for AuditService<C, S>
+where
+    C: Principal + Send + Sync + 'static,
+    S: tower::Service<Request<Body>, Response = Response<Body>> + Clone + Send + 'static,
+    S::Future: Send,
+    S::Error: Send,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let layer = self.layer.clone();
+        let mut inner = self.inner.clone();
+        // swap to ensure poll_ready state is preserved
+        std::mem::swap(&mut self.inner, &mut inner);
+
+        Box::pin(async move {
+            let principal = req
+                .extensions()
+                .get::<C>()
+                .map(|c| c.principal().to_owned());
+            let (parts, bytes) = match jsonrpc::buffer(req).await {
+                Ok(buffered) => buffered,
+                Err(response) => return Ok(response),
+            };
+
+            let timestamp_ms = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64;
+            let pending: Vec<AuditRecord> = jsonrpc::parse(&bytes)
+                .into_iter()
+                .filter(Message::is_request)
+                .map(|message| AuditRecord {
+                    timestamp_ms,
+                    principal: principal.clone(),
+                    method: message.method.clone().unwrap_or_default(),
+                    target: message.target().map(str::to_owned),
+                    arguments: layer.arguments(&message),
+                    request_id: message.id.clone().unwrap_or_default(),
+                    duration_ms: 0,
+                    is_error: false,
+                    error: None,
+                })
+                .collect();
+
+            let req = Request::from_parts(parts, Body::from(bytes));
+            if pending.is_empty() {
+                return inner.call(req).await;
+            }
+            let mut recorder = Recorder {
+                sink: layer.sink.clone(),
+                started: Instant::now(),
+                pending,
+                buffer: Vec::new(),
+                unanswered: None,
+            };
+
+            let response = inner.call(req).await?;
+            if !response.status().is_success() {
+                recorder.unanswered = Some(format!("HTTP {}", response.status()));
+                return Ok(response);
+            }
+            let content_type = response
+                .headers()
+                .get(header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or_default()
+                .to_owned();
+
+            let (parts, body) = response.into_parts();
+            if content_type.starts_with("text/event-stream") {
+                let stream = body.into_data_stream().map(move |chunk| {
+                    if let Ok(ref bytes) = chunk {
+                        recorder.feed_sse(bytes);
+                    }
+                    chunk
+                });
+                Ok(Response::from_parts(parts, Body::from_stream(stream)))
+            } else {
+                // JSON responses are complete messages; buffer and forward.
+                let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+                    Ok(bytes) => bytes,
+                    Err(e) => {
+                        recorder.unanswered = Some(format!("response body error: {e}"));
+                        Bytes::new()
+                    }
+                };
+                for message in jsonrpc::parse(&bytes) {
+                    recorder.complete(&message);
+                }
+                Ok(Response::from_parts(parts, Body::from(bytes)))
+            }
+        })
+    }
+}
+
+/// Requests awaiting a response. Anything still pending when dropped is
+/// recorded as failed.
+struct Recorder {
+    sink: Arc<dyn AuditSink>,
+    started: Instant,
+    /// In request order, so a batch reusing an id gets a record per request.
+    pending: Vec<AuditRecord>,
+    /// Partial SSE event.
+    buffer: Vec<u8>,
+    /// Why pending requests got no response, if known.
+    unanswered: Option<String>,
+}
+
+impl Recorder {
+    fn complete(&mut self, message: &Message) {
+        if !message.is_response() {
+            return;
+        }
+        let id = message.id.clone().unwrap_or_default();
+        let Some(position) = self.pending.iter().position(|r| r.request_id == id) else {
+            return;
+        };
+        let mut record = self.pending.remove(position);
+        record.duration_ms = self.started.elapsed().as_millis() as u64;
+        if let Some(ref error) = message.error {
+            record.is_error = true;
+            record.error = error
+                .get("message")
+                .and_then(Value::as_str)
+                .map(str::to_owned);
+        } else {
+            record.is_error = message
+                .result
+                .as_ref()
+                .and_then(|r| r.get("isError"))
+                .and_then(Value::as_bool)
+                .unwrap_or(false);
+        }
+        self.sink.record(record);
+    }
+
+    /// Feed a chunk of an SSE stream, completing requests whose responses
+    /// appear in finished events.
+    fn feed_sse(&mut self, chunk: &[u8]) {
+        if self.pending.is_empty() {
+            return;
+        }
+        self.buffer.extend(chunk.iter().filter(|&&b| b != b'\r'));
+        while let Some(end) = self.buffer.windows(2).position(|w| w == b"\n\n") {
+            let event: Vec<u8> = self.buffer.drain(..end + 2).collect();
+            let data = event
+                .split(|&b| b == b'\n')
+                .filter_map(|line| line.strip_prefix(b"data:"))
+                .map(|data| data.strip_prefix(b" ").unwrap_or(data))
+                .collect::<Vec<_>>()
+                .join(&b'\n');
+            for message in jsonrpc::parse(&data) {
+                self.complete(&message);
+            }
+        }
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        let duration_ms = self.started.elapsed().as_millis() as u64;
+        let error = self
+            .unanswered
+            .take()
+            .unwrap_or_else(|| "no response".to_owned());
+        for mut record in self.pending.drain(..) {
+            record.duration_ms = duration_ms;
+            record.is_error = true;
+            record.error = Some(error.clone());
+            self.sink.record(record);
+        }
+    }
+}
+
+/// Replace values under sensitive keys, recursively. A key is sensitive if
+/// its words contain the words of one of `keys`.
+fn redact(value: &mut Value, keys: &[Vec<String>]) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                let key = words(key);
+                if keys
+                    .iter()
+                    .any(|k| key.windows(k.len()).any(|w| w == k.as_slice()))
+                {
+                    *value = Value::String(REDACTED.to_owned());
+                } else {
+                    redact(value, keys);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|v| redact(v, keys)),
+        _ => {}
+    }
+}
+
+/// The lowercase words of `key`, split at non-alphanumeric characters and
+/// before an uppercase letter following a lowercase one or digit.
+fn words(key: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut hump = false;
+    for c in key.chars() {
+        if (!c.is_alphanumeric() || (hump && c.is_uppercase())) && !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+        hump = c.is_lowercase() || c.is_numeric();
+        if c.is_alphanumeric() {
+            word.extend(c.to_lowercase());
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AuditLayer, AuditRecord, words};
+    use axum::{Extension, Router, body::Body, routing::post};
+    use http::{Request, StatusCode, header};
+    use std::sync::{Arc, Mutex};
+    use tower::ServiceExt;
+
+    type Records = Arc<Mutex<Vec<AuditRecord>>>;
+
+    fn app(response: &'static str, content_type: &'static str) -> (Router, Records) {
+        let records = Records::default();
+        let sink = records.clone();
+        let app = Router::new()
+            .route(
+                "/mcp",
+                post(move || async move { ([(header::CONTENT_TYPE, content_type)], response) }),
+            )
+            .layer(AuditLayer::<String>::new(move |r| {
+                sink.lock().unwrap().push(r)
+            }))
+            .layer(Extension("alice".to_owned()));
+        (app, records)
+    }
+
+    fn tool_call(arguments: serde_json::Value) -> Request<Body> {
+        let body = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "tools/call",
+            "params": { "name": "login", "arguments": arguments },
+        });
+        Request::post("/mcp")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    async fn drain(resp: http::Response<Body>) {
+        axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn records_json_response_with_redacted_arguments() {
+        let (app, records) = app(
+            r#"{"jsonrpc":"2.0","id":1,"result":{"content":[],"isError":true}}"#,
+            "application/json",
+        );
+        let args = serde_json::json!({
+            "user": "alice",
+            "Password": "hunter2",
+            "nested": { "api_token": "x", "accessToken": "y", "X-Api-Key": "z" },
+            "max_tokens": 100,
+        });
+        let resp = app.oneshot(tool_call(args)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        drain(resp).await;
+
+        let records = records.lock().unwrap();
+        assert_eq!(records.len(), 1);
+        let record = &records[0];
+        assert_eq!(record.principal.as_deref(), Some("alice"));
+        assert_eq!(record.method, "tools/call");
+        assert_eq!(record.target.as_deref(), Some("login"));
+        assert!(record.is_error);
+        let args = record.arguments.as_ref().unwrap();
+        assert_eq!(args["user"], "alice");
+        assert_eq!(args["Password"], "[REDACTED]");
+        assert_eq!(args["nested"]["api_token"], "[REDACTED]");
+        assert_eq!(args["nested"]["accessToken"], "[REDACTED]");
+        assert_eq!(args["nested"]["X-Api-Key"], "[REDACTED]");
+        assert_eq!(args["max_tokens"], 100);
+    }
+
+    #[test]
+    fn splits_keys_into_words() {
+        assert_eq!(words("accessToken"), ["access", "token"]);
+        assert_eq!(words("X-API-Key"), ["x", "api", "key"]);
+        assert_eq!(words("APIKey"), ["apikey"]);
+        assert_eq!(words("client_secret2"), ["client", "secret2"]);
+        assert!(words("--").is_empty());
+    }
+
+    #[tokio::test]
+    async fn records_every_request_of_a_batch_reusing_an_id() {
+        let (app, records) = app(
+            r#"[{"jsonrpc":"2.0","id":1,"result":{"content":[]}},
+                {"jsonrpc":"2.0","id":1,"error":{"code":-32602,"message":"bad args"}}]"#,
+            "application/json",
+        );
+        let call = |name: &str| {
+            serde_json::json!({
+                "jsonrpc": "2.0", "id": 1, "method": "tools/call",
+                "params": { "name": name, "arguments": {} },
+            })
+        };
+        let body = serde_json::json!([call("first"), call("second")]);
+        let req = Request::post("/mcp")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        drain(app.oneshot(req).await.unwrap()).await;
+
+        let records = records.lock().unwrap();
+        let outcomes: Vec<_> = records
+            .iter()
+            .map(|r| (r.target.as_deref(), r.is_error))
+            .collect();
+        assert_eq!(outcomes, [(Some("first"), false), (Some("second"), true)]);
+    }
+
+    #[tokio::test]
+    async fn correlates_responses_in_sse_stream() {
+        let (app, records) = app(
+            "data: {\"jsonrpc\":\"2.0\",\"method\":\"notifications/progress\",\"params\":{}}\n\n\
+             id: 0\ndata: {\"jsonrpc\":\"2.0\",\"id\":1,\"error\":{\"code\":-32602,\"message\":\"bad args\"}}\n\n",
+            "text/event-stream",
+        );
+        let resp = app.oneshot(tool_call(serde_json::json!({}))).await.unwrap();
+        drain(resp).await;
+
+        let records = records.lock().unwrap();
+        assert_eq!(records.len(), 1);
+        assert!(records[0].is_error);
+        assert_eq!(records[0].error.as_deref(), Some("bad args"));
+    }
+
+    #[tokio::test]
+    async fn records_unanswered_requests() {
+        let (app, records) = app("", "text/event-stream");
+        let resp = app.oneshot(tool_call(serde_json::json!({}))).await.unwrap();
+        drain(resp).await;
+
+        let records = records.lock().unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].error.as_deref(), Some("no response"));
+    }
+}
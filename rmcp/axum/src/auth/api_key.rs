@@ -0,0 +1,338 @@
+//! Static API key authentication plugin.
+//!
+//! Reads a key from the `X-API-Key` header (or, if enabled, a query
+//! parameter) and looks up its SHA-256 digest in an [`ApiKeyStore`]. Only
+//! digests are ever stored, so a leaked store does not leak usable keys.
+//!
+//! Requires the `api-key` feature.
+//!
+//! ```rust,ignore
+//! use rmcp_axum::auth::AuthLayer;
+//! use rmcp_axum::auth::api_key::{ApiKeyAuth, ApiKeyClaims, ApiKeyHash, HashedKeyStore};
+//!
+//! // Digest produced offline with `ApiKeyHash::of("...")`.
+//! let hash: ApiKeyHash = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08"
+//!     .parse()
+//!     .unwrap();
+//! let keys = HashedKeyStore::new().with_key(
+//!     hash,
+//!     ApiKeyClaims {
+//!         key_id: "ci".into(),
+//!         subject: "svc-ci".into(),
+//!         scope: vec!["mcp:tools".into()],
+//!     },
+//! );
+//!
+//! let app = axum::Router::new()
+//!     .nest_service("/mcp", service)
+//!     .layer(AuthLayer::new(ApiKeyAuth::new(keys)));
+//! ```
+
+use crate::auth::{AuthError, Authenticator};
+use sha2::{Digest, Sha256};
+use std::{collections::HashMap, fmt, str::FromStr, sync::Arc};
+
+/// Default header carrying the API key.
+const DEFAULT_HEADER: &str = "x-api-key";
+
+/// SHA-256 digest of an API key.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ApiKeyHash([u8; 32]);
+
+impl ApiKeyHash {
+    /// Hash a plaintext API key.
+    pub fn of(key: &str) -> Self {
+        Self(Sha256::digest(key.as_bytes()).into())
+    }
+}
+
+impl fmt::Display for ApiKeyHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in self.0 {
+            write!(f, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for ApiKeyHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ApiKeyHash({self})")
+    }
+}
+
+impl FromStr for ApiKeyHash {
+    type Err = String;
+
+    /// Parse a lowercase or uppercase hex-encoded SHA-256 digest.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() != 64 || !s.is_ascii() {
+            return Err("expected 64 hex characters".into());
+        }
+        let mut out = [0u8; 32];
+        for (i, byte) in out.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&s[i * 2..i * 2 + 2], 16)
+                .map_err(|_| format!("invalid hex digest: {s}"))?;
+        }
+        Ok(Self(out))
+    }
+}
+
+/// Claims attached to an API key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ApiKeyClaims {
+    /// Identifier of the key (for logging and rotation; never the key itself).
+    pub key_id: String,
+    /// The principal the key acts as.
+    pub subject: String,
+    /// Scopes granted to the key.
+    pub scope: Vec<String>,
+}
+
+/// Lookup of API key digests to claims.
+///
+/// Implement this to back keys with a database or secret manager.
+pub trait ApiKeyStore: Clone + Send + Sync + 'static {
+    /// The claims type produced for a known key.
+    type Claims: Clone + Send + Sync + 'static;
+
+    /// Return the claims for the key with the given digest, if known.
+    fn lookup(&self, hash: &ApiKeyHash) -> impl Future<Output = Option<Self::Claims>> + Send;
+}
+
+/// In-memory [`ApiKeyStore`] of key digests.
+#[derive(Clone, Debug)]
+pub struct HashedKeyStore<C = ApiKeyClaims> {
+    keys: Arc<HashMap<ApiKeyHash, C>>,
+}
+
+impl<C> HashedKeyStore<C> {
+    pub fn new() -> Self {
+        Self {
+            keys: Arc::new(HashMap::new()),
+        }
+    }
+}
+
+impl<C> Default for HashedKeyStore<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: Clone> HashedKeyStore<C> {
+    /// Register a key digest with its claims.
+    pub fn with_key(mut self, hash: ApiKeyHash, claims: C) -> Self {
+        Arc::make_mut(&mut self.keys).insert(hash, claims);
+        self
+    }
+}
+
+impl<C> ApiKeyStore for HashedKeyStore<C>
+where
+    C: Clone + Send + Sync + 'static,
+{
+    type Claims = C;
+
+    async fn lookup(&self, hash: &ApiKeyHash) -> Option<C> {
+        self.keys.get(hash).cloned()
+    }
+}
+
+/// API key authenticator.
+///
+/// Extracts the key from the `X-API-Key` header, or from a query parameter
+/// when [`query_param`](Self::query_param) is set, and resolves it through
+/// the inner [`ApiKeyStore`].
+#[derive(Clone)]
+pub struct ApiKeyAuth<S> {
+    store: S,
+    header: http::HeaderName,
+    query_param: Option<String>,
+}
+
+impl<S> ApiKeyAuth<S> {
+    pub fn new(store: S) -> Self {
+        Self {
+            store,
+            header: http::HeaderName::from_static(DEFAULT_HEADER),
+            query_param: None,
+        }
+    }
+
+    /// Read the key from this header instead of `X-API-Key`.
+    pub fn header(mut self, header: http::HeaderName) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// Also accept the key from this query parameter.
+    ///
+    /// Off by default: query strings tend to end up in access logs.
+    pub fn query_param(mut self, name: impl Into<String>) -> Self {
+        self.query_param = Some(name.into());
+        self
+    }
+
+    fn extract(&self, parts: &http::request::Parts) -> Result<Option<String>, AuthError> {
+        if let Some(key) = parts.headers.get(&self.header) {
+            return key.to_str().map(|k| Some(k.to_owned())).map_err(|_| {
+                AuthError::InvalidRequest(format!("malformed {} header", self.header))
+            });
+        }
+        let Some(name) = self.query_param.as_deref() else {
+            return Ok(None);
+        };
+        let axum::extract::Query(query) =
+            axum::extract::Query::<HashMap<String, String>>::try_from_uri(&parts.uri)
+                .map_err(|_| AuthError::InvalidRequest("malformed query string".into()))?;
+        Ok(query.get(name).cloned())
+    }
+}
+
+impl<S> Authenticator for ApiKeyAuth<S>
+where
+    S: ApiKeyStore,
+{
+    type Claims = S::Claims;
+    type Error = AuthError;
+
+    async fn authenticate(
+        &self,
+        parts: &http::request::Parts,
+    ) -> Result<Self::Claims, Self::Error> {
+        let key = self
+            .extract(parts)?
+            .filter(|k| !k.is_empty())
+            .ok_or(AuthError::MissingCredentials)?;
+        self.store
+            .lookup(&ApiKeyHash::of(&key))
+            .await
+            .ok_or_else(|| AuthError::InvalidToken("invalid API key".into()))
+    }
+
+    fn reject(error: AuthError) -> AuthError {
+        error
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::auth::{
+        AuthError, AuthLayer, Authenticator, AuthenticatorExt, Either,
+        api_key::{ApiKeyAuth, ApiKeyClaims, ApiKeyHash, HashedKeyStore},
+    };
+    use axum::{Extension, Router, routing::get};
+    use tower::ServiceExt;
+
+    fn store() -> HashedKeyStore {
+        HashedKeyStore::new().with_key(
+            ApiKeyHash::of("k-123"),
+            ApiKeyClaims {
+                key_id: "ci".into(),
+                subject: "svc-ci".into(),
+                scope: vec!["mcp:tools".into()],
+            },
+        )
+    }
+
+    fn parts(uri: &str, headers: &[(&str, &str)]) -> http::request::Parts {
+        let mut builder = http::Request::builder().uri(uri);
+        for (k, v) in headers {
+            builder = builder.header(*k, *v);
+        }
+        builder.body(()).unwrap().into_parts().0
+    }
+
+    #[test]
+    fn hash_round_trips_through_hex() {
+        let hash = ApiKeyHash::of("test");
+        assert_eq!(
+            hash.to_string(),
+            "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08"
+        );
+        assert_eq!(hash.to_string().parse::<ApiKeyHash>(), Ok(hash));
+        assert!("abc".parse::<ApiKeyHash>().is_err());
+    }
+
+    #[tokio::test]
+    async fn accepts_header_key() {
+        let auth = ApiKeyAuth::new(store());
+        let claims = auth
+            .authenticate(&parts("/mcp", &[("x-api-key", "k-123")]))
+            .await
+            .expect("valid key");
+        assert_eq!(claims.subject, "svc-ci");
+    }
+
+    #[tokio::test]
+    async fn query_param_is_opt_in() {
+        let auth = ApiKeyAuth::new(store());
+        assert!(
+            auth.authenticate(&parts("/mcp?api_key=k-123", &[]))
+                .await
+                .is_err()
+        );
+
+        let auth = auth.query_param("api_key");
+        assert!(
+            auth.authenticate(&parts("/mcp?api_key=k-123", &[]))
+                .await
+                .is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_unknown_key() {
+        let auth = ApiKeyAuth::new(store());
+        let err = auth
+            .authenticate(&parts("/mcp", &[("x-api-key", "nope")]))
+            .await
+            .unwrap_err();
+        assert_eq!(err, AuthError::InvalidToken("invalid API key".into()));
+    }
+
+    #[tokio::test]
+    async fn composes_with_other_authenticators_in_one_layer() {
+        let user_keys = HashedKeyStore::new().with_key(ApiKeyHash::of("user"), "alice".to_string());
+        let auth = ApiKeyAuth::new(user_keys)
+            .header(http::HeaderName::from_static("x-user-key"))
+            .or(ApiKeyAuth::new(store()));
+
+        let app = Router::new()
+            .route(
+                "/mcp",
+                get(
+                    |Extension(claims): Extension<Either<String, ApiKeyClaims>>| async move {
+                        match claims {
+                            Either::Left(user) => user,
+                            Either::Right(key) => key.subject,
+                        }
+                    },
+                ),
+            )
+            .layer(AuthLayer::new(auth));
+
+        for (header, value, expected) in [
+            ("x-user-key", "user", "alice"),
+            ("x-api-key", "k-123", "svc-ci"),
+        ] {
+            let req = http::Request::get("/mcp")
+                .header(header, value)
+                .body(axum::body::Body::empty())
+                .unwrap();
+            let resp = app.clone().oneshot(req).await.unwrap();
+            assert_eq!(resp.status(), http::StatusCode::OK);
+            let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            assert_eq!(body, expected);
+        }
+
+        let req = http::Request::get("/mcp")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let resp = app.oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), http::StatusCode::UNAUTHORIZED);
+    }
+}
@@ -23,12 +23,17 @@
 //!     .layer(AuthLayer::new(BearerAuth::new(MyValidator)));
 //! ```
 
-use crate::auth::{Authenticator, Validator};
+use crate::auth::{AuthError, Authenticator, Validator};
 
 /// Bearer token authenticator.
 ///
 /// Extracts the token from `Authorization: Bearer <token>` and passes it
 /// to the inner [`Validator`].
+///
+/// A missing header or a different scheme yields
+/// [`AuthError::MissingCredentials`], a malformed header
+/// [`AuthError::InvalidRequest`], and a validator failure
+/// [`AuthError::InvalidToken`].
 #[derive(Clone)]
 pub struct BearerAuth<V> {
     validator: V,
@@ -38,6 +43,20 @@ impl<V> BearerAuth<V> {
     pub fn new(validator: V) -> Self {
         Self { validator }
     }
+
+    /// Also accept DPoP-bound tokens (`Authorization: DPoP <token>`),
+    /// verifying each request's proof with `verifier`.
+    ///
+    /// Tokens whose claims carry a `cnf.jkt` binding are only accepted with
+    /// a matching proof.
+    #[cfg(feature = "dpop")]
+    pub fn with_dpop(self, verifier: super::dpop::DpopVerifier) -> super::dpop::DpopAuth<V>
+    where
+        V: Validator,
+        V::Claims: super::dpop::ConfirmationClaims,
+    {
+        super::dpop::DpopAuth::new(self.validator, verifier)
+    }
 }
 
 impl<V> Authenticator for BearerAuth<V>
@@ -45,22 +64,150 @@ where
     V: Validator,
 {
     type Claims = V::Claims;
-    type Error = String;
+    type Error = AuthError;
 
     async fn authenticate(
         &self,
         parts: &http::request::Parts,
     ) -> Result<Self::Claims, Self::Error> {
-        let token = parts
-            .headers
-            .get(http::header::AUTHORIZATION)
-            .and_then(|v| v.to_str().ok())
-            .and_then(|v| v.strip_prefix("Bearer "))
-            .ok_or_else(|| "missing or malformed Authorization header".to_string())?;
-
+        let token = bearer_token(parts)?;
         self.validator
             .validate(token)
             .await
-            .map_err(|e| e.to_string())
+            .map_err(|e| AuthError::InvalidToken(format!("{e:#}")))
+    }
+
+    fn reject(error: AuthError) -> AuthError {
+        error
+    }
+}
+
+/// Extract the token from an `Authorization: Bearer <token>` header.
+pub(crate) fn bearer_token(parts: &http::request::Parts) -> Result<&str, AuthError> {
+    authorization(parts, &["Bearer"]).map(|(_, token)| token)
+}
+
+/// Extract `<scheme> <token>` from the `Authorization` header, accepting
+/// only the given `schemes`. Returns the matched entry of `schemes`.
+///
+/// The scheme is matched case-insensitively per RFC 7235 §2.1. Other
+/// schemes count as missing credentials, so the client gets a plain
+/// challenge per RFC 6750 §3.1 rather than an error.
+pub(crate) fn authorization<'a>(
+    parts: &'a http::request::Parts,
+    schemes: &[&'static str],
+) -> Result<(&'static str, &'a str), AuthError> {
+    let header = parts
+        .headers
+        .get(http::header::AUTHORIZATION)
+        .ok_or(AuthError::MissingCredentials)?;
+    let value = header
+        .to_str()
+        .map_err(|_| AuthError::InvalidRequest("malformed Authorization header".into()))?;
+    let (scheme, token) = value.split_once(' ').unwrap_or((value, ""));
+    let scheme = schemes
+        .iter()
+        .find(|s| scheme.eq_ignore_ascii_case(s))
+        .ok_or(AuthError::MissingCredentials)?;
+    let token = token.trim();
+    if token.is_empty() || token.contains(' ') {
+        return Err(AuthError::InvalidRequest(format!(
+            "malformed {scheme} token"
+        )));
+    }
+    Ok((scheme, token))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::auth::{AuthError, AuthLayer, BearerAuth, Validator};
+    use tower::ServiceExt;
+
+    #[derive(Clone)]
+    struct Secret;
+
+    impl Validator for Secret {
+        type Claims = String;
+        type Error = String;
+
+        async fn validate(&self, token: &str) -> Result<String, String> {
+            match token {
+                "secret" => Ok("user".into()),
+                "old" => Err("token expired".into()),
+                _ => Err("bad signature".into()),
+            }
+        }
+    }
+
+    async fn call(authorization: Option<&str>) -> (http::StatusCode, String, serde_json::Value) {
+        let app = axum::Router::new()
+            .route("/mcp", axum::routing::post(|| async { "ok" }))
+            .layer(AuthLayer::new(BearerAuth::new(Secret)));
+        let mut req = http::Request::post("/mcp");
+        if let Some(value) = authorization {
+            req = req.header(http::header::AUTHORIZATION, value);
+        }
+        let resp = app
+            .oneshot(req.body(axum::body::Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = resp.status();
+        let challenge = resp
+            .headers()
+            .get(http::header::WWW_AUTHENTICATE)
+            .map(|v| v.to_str().unwrap().to_owned())
+            .unwrap_or_default();
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json = serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null);
+        (status, challenge, json)
+    }
+
+    #[tokio::test]
+    async fn accepts_valid_token_with_any_scheme_case() {
+        let (status, _, _) = call(Some("bearer secret")).await;
+        assert_eq!(status, http::StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn missing_header_has_bare_challenge() {
+        let (status, challenge, json) = call(None).await;
+        assert_eq!(status, http::StatusCode::UNAUTHORIZED);
+        assert_eq!(challenge, "Bearer");
+        assert_eq!(json["error"]["code"], -32001);
+    }
+
+    #[tokio::test]
+    async fn malformed_header_is_invalid_request() {
+        for header in ["Bearer", "Bearer a b"] {
+            let (status, challenge, json) = call(Some(header)).await;
+            assert_eq!(status, http::StatusCode::BAD_REQUEST, "{header}");
+            assert!(challenge.starts_with("Bearer error=\"invalid_request\""));
+            assert_eq!(json["error"]["data"]["error"], "invalid_request");
+        }
+    }
+
+    #[tokio::test]
+    async fn other_scheme_is_plain_challenge() {
+        let (status, challenge, json) = call(Some("Basic dXNlcjpwYXNz")).await;
+        assert_eq!(status, http::StatusCode::UNAUTHORIZED);
+        assert_eq!(challenge, "Bearer");
+        assert_eq!(json["error"]["data"]["error"], "unauthorized");
+    }
+
+    #[tokio::test]
+    async fn rejected_token_is_invalid_token_with_description() {
+        let (status, challenge, json) = call(Some("Bearer old")).await;
+        assert_eq!(status, http::StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            challenge,
+            "Bearer error=\"invalid_token\", error_description=\"token expired\""
+        );
+        assert_eq!(json["error"]["message"], "token expired");
+        assert_eq!(
+            AuthError::from("bad signature").error_code(),
+            Some("invalid_token")
+        );
     }
 }
@@ -0,0 +1,254 @@
+//! Authenticator combinators.
+//!
+//! [`FirstOf`] tries two authenticators in order and accepts the request if
+//! either succeeds, so a single [`AuthLayer`](super::AuthLayer) can accept
+//! e.g. OAuth JWTs from interactive users and API keys from service accounts.
+//! [`MapClaims`] converts the claims of an authenticator into another type,
+//! which is handy for collapsing [`Either`] claims into one application type.
+//!
+//! ```rust,ignore
+//! use rmcp_axum::auth::{AuthLayer, AuthenticatorExt, BearerAuth, Either};
+//! use rmcp_axum::auth::api_key::{ApiKeyAuth, HashedKeyStore};
+//!
+//! let auth = BearerAuth::new(jwt).or(ApiKeyAuth::new(keys));
+//!
+//! // Handlers receive `Either<OAuthClaims, ApiKeyClaims>`.
+//! let app = axum::Router::new()
+//!     .nest_service("/mcp", service)
+//!     .layer(AuthLayer::new(auth));
+//! ```
+
+use crate::auth::{AuthError, Authenticator};
+
+/// Claims produced by one of two authenticators.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Either<L, R> {
+    /// Claims from the first authenticator.
+    Left(L),
+    /// Claims from the second authenticator.
+    Right(R),
+}
+
+impl<L, R> Either<L, R> {
+    /// Returns the left claims, if any.
+    pub fn left(&self) -> Option<&L> {
+        match self {
+            Either::Left(l) => Some(l),
+            Either::Right(_) => None,
+        }
+    }
+
+    /// Returns the right claims, if any.
+    pub fn right(&self) -> Option<&R> {
+        match self {
+            Either::Left(_) => None,
+            Either::Right(r) => Some(r),
+        }
+    }
+}
+
+impl<T> Either<T, T> {
+    /// Unwrap claims when both authenticators produce the same type.
+    pub fn into_inner(self) -> T {
+        match self {
+            Either::Left(t) | Either::Right(t) => t,
+        }
+    }
+}
+
+/// Authenticator that tries `A`, then `B`, returning the first success.
+///
+/// If both fail, the more specific error is reported: a presented but
+/// invalid credential wins over a missing one, and `A` wins over `B`.
+#[derive(Clone)]
+pub struct FirstOf<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A, B> FirstOf<A, B> {
+    pub fn new(first: A, second: B) -> Self {
+        Self { first, second }
+    }
+}
+
+impl<A, B> Authenticator for FirstOf<A, B>
+where
+    A: Authenticator,
+    B: Authenticator,
+{
+    type Claims = Either<A::Claims, B::Claims>;
+    type Error = AuthError;
+
+    async fn authenticate(
+        &self,
+        parts: &http::request::Parts,
+    ) -> Result<Self::Claims, Self::Error> {
+        let first = match self.first.authenticate(parts).await {
+            Ok(claims) => return Ok(Either::Left(claims)),
+            Err(e) => A::reject(e),
+        };
+        match self.second.authenticate(parts).await {
+            Ok(claims) => Ok(Either::Right(claims)),
+            Err(e) => Err(match first {
+                AuthError::MissingCredentials => B::reject(e),
+                first => first,
+            }),
+        }
+    }
+
+    fn reject(error: AuthError) -> AuthError {
+        error
+    }
+}
+
+/// Authenticator that maps the claims of `A` with a function.
+#[derive(Clone)]
+pub struct MapClaims<A, F> {
+    authenticator: A,
+    f: F,
+}
+
+impl<A, F> MapClaims<A, F> {
+    pub fn new(authenticator: A, f: F) -> Self {
+        Self { authenticator, f }
+    }
+}
+
+impl<A, F, C> Authenticator for MapClaims<A, F>
+where
+    A: Authenticator,
+    F: Fn(A::Claims) -> C + Clone + Send + Sync + 'static,
+    C: Clone + Send + Sync + 'static,
+{
+    type Claims = C;
+    type Error = A::Error;
+
+    async fn authenticate(
+        &self,
+        parts: &http::request::Parts,
+    ) -> Result<Self::Claims, Self::Error> {
+        self.authenticator.authenticate(parts).await.map(&self.f)
+    }
+
+    fn reject(error: A::Error) -> AuthError {
+        A::reject(error)
+    }
+}
+
+/// Combinator methods for [`Authenticator`]s.
+pub trait AuthenticatorExt: Authenticator + Sized {
+    /// Try `self` first, falling back to `other`.
+    fn or<B: Authenticator>(self, other: B) -> FirstOf<Self, B> {
+        FirstOf::new(self, other)
+    }
+
+    /// Convert the produced claims with `f`.
+    fn map_claims<F, C>(self, f: F) -> MapClaims<Self, F>
+    where
+        F: Fn(Self::Claims) -> C + Clone + Send + Sync + 'static,
+        C: Clone + Send + Sync + 'static,
+    {
+        MapClaims::new(self, f)
+    }
+}
+
+impl<A: Authenticator> AuthenticatorExt for A {}
+
+#[cfg(test)]
+mod tests {
+    use crate::auth::{AuthError, Authenticator, AuthenticatorExt, Either};
+
+    /// Accepts requests carrying the given header with a non-empty value.
+    #[derive(Clone)]
+    struct HeaderAuth(&'static str);
+
+    impl Authenticator for HeaderAuth {
+        type Claims = String;
+        type Error = AuthError;
+
+        async fn authenticate(&self, parts: &http::request::Parts) -> Result<String, AuthError> {
+            let value = parts
+                .headers
+                .get(self.0)
+                .and_then(|v| v.to_str().ok())
+                .ok_or(AuthError::MissingCredentials)?;
+            if value.is_empty() {
+                return Err(AuthError::InvalidToken(format!("empty {}", self.0)));
+            }
+            Ok(value.into())
+        }
+
+        fn reject(error: AuthError) -> AuthError {
+            error
+        }
+    }
+
+    /// Rejects every request with a plain `Display` error.
+    #[derive(Clone)]
+    struct Deny;
+
+    impl Authenticator for Deny {
+        type Claims = String;
+        type Error = std::fmt::Error;
+
+        async fn authenticate(&self, _: &http::request::Parts) -> Result<String, Self::Error> {
+            Err(std::fmt::Error)
+        }
+    }
+
+    fn parts(headers: &[(&str, &str)]) -> http::request::Parts {
+        let mut builder = http::Request::builder();
+        for (k, v) in headers {
+            builder = builder.header(*k, *v);
+        }
+        builder.body(()).unwrap().into_parts().0
+    }
+
+    #[tokio::test]
+    async fn first_of_prefers_first() {
+        let auth = HeaderAuth("a").or(HeaderAuth("b"));
+        let claims = auth.authenticate(&parts(&[("a", "1"), ("b", "2")])).await;
+        assert_eq!(claims, Ok(Either::Left("1".into())));
+    }
+
+    #[tokio::test]
+    async fn first_of_falls_back() {
+        let auth = HeaderAuth("a").or(HeaderAuth("b"));
+        let claims = auth.authenticate(&parts(&[("b", "2")])).await;
+        assert_eq!(claims, Ok(Either::Right("2".into())));
+    }
+
+    #[tokio::test]
+    async fn first_of_reports_most_specific_error() {
+        let auth = HeaderAuth("a").or(HeaderAuth("b"));
+        let err = auth.authenticate(&parts(&[])).await.unwrap_err();
+        assert_eq!(err, AuthError::MissingCredentials);
+
+        let err = auth.authenticate(&parts(&[("b", "")])).await.unwrap_err();
+        assert_eq!(err, AuthError::InvalidToken("empty b".into()));
+
+        let err = auth
+            .authenticate(&parts(&[("a", ""), ("b", "")]))
+            .await
+            .unwrap_err();
+        assert_eq!(err, AuthError::InvalidToken("empty a".into()));
+    }
+
+    #[tokio::test]
+    async fn first_of_reports_display_errors_as_invalid_token() {
+        let auth = HeaderAuth("a").or(Deny);
+        let err = auth.authenticate(&parts(&[])).await.unwrap_err();
+        assert_eq!(err, AuthError::other(std::fmt::Error));
+        assert_eq!(err.error_code(), Some("invalid_token"));
+    }
+
+    #[tokio::test]
+    async fn map_claims_unifies_types() {
+        let auth = HeaderAuth("a")
+            .or(HeaderAuth("b"))
+            .map_claims(Either::into_inner);
+        let claims = auth.authenticate(&parts(&[("b", "2")])).await;
+        assert_eq!(claims, Ok("2".to_string()));
+    }
+}
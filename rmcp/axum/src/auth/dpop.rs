@@ -0,0 +1,654 @@
+//! DPoP sender-constrained token verification
+//! ([RFC 9449](https://datatracker.ietf.org/doc/html/rfc9449)).
+//!
+//! A DPoP-bound access token carries the thumbprint of the client's key in
+//! its `cnf.jkt` claim. Each request presents the token as
+//! `Authorization: DPoP <token>` together with a `DPoP` header holding a
+//! proof JWT signed by that key and bound to the request method, URI and
+//! token. [`DpopAuth`] checks the proof, and a replay cache rejects reused
+//! proof `jti`s.
+//!
+//! Requires the `dpop` feature.
+//!
+//! ```rust,ignore
+//! use rmcp_axum::auth::{AuthLayer, BearerAuth, dpop::DpopVerifier};
+//! use rmcp_axum::auth::oauth::ProtectedResource;
+//!
+//! let dpop = DpopVerifier::new().origin("https://mcp.example.com")?;
+//! let resource = ProtectedResource::new(dpop.advertise(metadata))?;
+//!
+//! let app = axum::Router::new()
+//!     .nest_service("/mcp", mcp_service)
+//!     .layer(
+//!         AuthLayer::new(BearerAuth::new(validator).with_dpop(dpop))
+//!             .with_protected_resource(resource),
+//!     );
+//! ```
+
+use crate::auth::{
+    AuthError, Authenticator, Validator,
+    bearer::authorization,
+    oauth::{OAuthClaims, ProtectedResourceMetadata},
+};
+use axum::extract::OriginalUri;
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use http::request::Parts;
+use jsonwebtoken::{
+    Algorithm, DecodingKey, Validation,
+    jwk::{AlgorithmParameters, Jwk},
+};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// Default maximum age of a proof.
+const DEFAULT_MAX_AGE: Duration = Duration::from_secs(300);
+
+/// Default tolerance for proofs issued slightly in the future.
+const DEFAULT_LEEWAY: Duration = Duration::from_secs(60);
+
+/// Asymmetric algorithms accepted by default.
+const DEFAULT_ALGORITHMS: &[Algorithm] = &[
+    Algorithm::ES256,
+    Algorithm::ES384,
+    Algorithm::RS256,
+    Algorithm::PS256,
+    Algorithm::EdDSA,
+];
+
+/// Error returned for an origin or scheme [`DpopVerifier`] can't use.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidOrigin(String);
+
+impl fmt::Display for InvalidOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid DPoP origin: {}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidOrigin {}
+
+/// Claims whose token may be bound to a DPoP key.
+pub trait ConfirmationClaims {
+    /// The `cnf.jkt` thumbprint, if the token is DPoP-bound.
+    fn dpop_jkt(&self) -> Option<&str>;
+}
+
+impl ConfirmationClaims for OAuthClaims {
+    fn dpop_jkt(&self) -> Option<&str> {
+        self.cnf.as_ref()?.jkt.as_deref()
+    }
+}
+
+/// Proof JWT claims (RFC 9449 §4.2).
+#[derive(Deserialize)]
+struct ProofClaims {
+    jti: String,
+    htm: String,
+    htu: String,
+    iat: u64,
+    ath: Option<String>,
+}
+
+/// Verifies DPoP proofs and remembers their `jti`s.
+#[derive(Clone)]
+pub struct DpopVerifier {
+    origin: Option<url::Url>,
+    scheme: &'static str,
+    algorithms: Vec<Algorithm>,
+    max_age: Duration,
+    leeway: Duration,
+    required: bool,
+    /// Seen `jti`s and when they can be forgotten.
+    replay: Arc<Mutex<HashMap<String, Instant>>>,
+}
+
+impl Default for DpopVerifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DpopVerifier {
+    /// Accept ES256, ES384, RS256, PS256 and EdDSA proofs up to five
+    /// minutes old.
+    pub fn new() -> Self {
+        Self {
+            origin: None,
+            scheme: "https",
+            algorithms: DEFAULT_ALGORITHMS.to_vec(),
+            max_age: DEFAULT_MAX_AGE,
+            leeway: DEFAULT_LEEWAY,
+            required: false,
+            replay: Arc::default(),
+        }
+    }
+
+    /// The externally visible origin (`https://host[:port]`) proofs must
+    /// name in `htu`.
+    ///
+    /// Without it, `htu` is compared against the request's `Host` header
+    /// and the configured [`scheme`](Self::scheme).
+    ///
+    /// Fails unless `origin` is an `http(s)` URL with a host and nothing
+    /// after it but an optional `/`.
+    pub fn origin(mut self, origin: &str) -> Result<Self, InvalidOrigin> {
+        let url = url::Url::parse(origin).map_err(|e| InvalidOrigin(format!("{origin}: {e}")))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(InvalidOrigin(format!("{origin}: scheme must be http(s)")));
+        }
+        if url.host_str().is_none() {
+            return Err(InvalidOrigin(format!("{origin}: missing host")));
+        }
+        if url.path() != "/"
+            || url.query().is_some()
+            || url.fragment().is_some()
+            || !url.username().is_empty()
+            || url.password().is_some()
+        {
+            return Err(InvalidOrigin(format!(
+                "{origin}: must be scheme, host and port only"
+            )));
+        }
+        self.origin = Some(url);
+        Ok(self)
+    }
+
+    /// Scheme `htu` must use when no [`origin`](Self::origin) is set.
+    /// Defaults to `https`; use `http` only for servers reached without TLS.
+    pub fn scheme(mut self, scheme: &str) -> Result<Self, InvalidOrigin> {
+        self.scheme = match scheme {
+            "https" => "https",
+            "http" => "http",
+            _ => return Err(InvalidOrigin(format!("{scheme}: scheme must be http(s)"))),
+        };
+        Ok(self)
+    }
+
+    /// Accepted proof signing algorithms. Symmetric algorithms are always
+    /// rejected.
+    pub fn algorithms(mut self, algorithms: impl IntoIterator<Item = Algorithm>) -> Self {
+        self.algorithms = algorithms
+            .into_iter()
+            .filter(|a| !is_symmetric(*a))
+            .collect();
+        self
+    }
+
+    /// Maximum proof age (default 5 minutes); also how long `jti`s are
+    /// remembered.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Reject tokens that are not DPoP-bound.
+    pub fn require_dpop(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    /// Add the DPoP fields to Protected Resource Metadata.
+    pub fn advertise(&self, mut metadata: ProtectedResourceMetadata) -> ProtectedResourceMetadata {
+        metadata.dpop_signing_alg_values_supported =
+            Some(self.algorithms.iter().map(|a| format!("{a:?}")).collect());
+        metadata.dpop_bound_access_tokens_required = Some(self.required);
+        metadata
+    }
+
+    /// Verify the request's proof for `token`, bound to `jkt`.
+    pub fn verify(&self, parts: &Parts, token: &str, jkt: &str) -> Result<(), AuthError> {
+        let invalid = |msg: &str| AuthError::InvalidDpopProof(msg.into());
+
+        let mut proofs = parts.headers.get_all("dpop").iter();
+        let (Some(proof), None) = (proofs.next(), proofs.next()) else {
+            return Err(invalid("exactly one DPoP proof is required"));
+        };
+        let proof = proof
+            .to_str()
+            .map_err(|_| invalid("malformed DPoP proof"))?;
+
+        let header =
+            jsonwebtoken::decode_header(proof).map_err(|_| invalid("malformed DPoP proof"))?;
+        if header.typ.as_deref() != Some("dpop+jwt") {
+            return Err(invalid("DPoP proof must have typ dpop+jwt"));
+        }
+        if !self.algorithms.contains(&header.alg) {
+            return Err(invalid("unsupported DPoP proof algorithm"));
+        }
+        let jwk = header
+            .jwk
+            .as_ref()
+            .ok_or_else(|| invalid("DPoP proof has no jwk header"))?;
+        if has_private_key(proof) {
+            return Err(invalid("DPoP proof jwk must not contain a private key"));
+        }
+        if jwk_thumbprint(jwk).as_deref() != Some(jkt) {
+            return Err(invalid("DPoP proof key does not match the token binding"));
+        }
+
+        let key = DecodingKey::from_jwk(jwk).map_err(|_| invalid("invalid DPoP proof jwk"))?;
+        let mut validation = Validation::new(header.alg);
+        validation.validate_exp = false;
+        validation.validate_aud = false;
+        validation.required_spec_claims = HashSet::new();
+        let claims = jsonwebtoken::decode::<ProofClaims>(proof, &key, &validation)
+            .map_err(|_| invalid("invalid DPoP proof signature"))?
+            .claims;
+
+        if claims.htm != parts.method.as_str() {
+            return Err(invalid("DPoP proof htm does not match the request method"));
+        }
+        if !self.htu_matches(&claims.htu, parts) {
+            return Err(invalid("DPoP proof htu does not match the request URI"));
+        }
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        if claims.iat > now + self.leeway.as_secs() || claims.iat + self.max_age.as_secs() < now {
+            return Err(invalid("DPoP proof is expired or issued in the future"));
+        }
+        let ath = URL_SAFE_NO_PAD.encode(Sha256::digest(token.as_bytes()));
+        if claims.ath.as_deref() != Some(ath.as_str()) {
+            return Err(invalid("DPoP proof ath does not match the access token"));
+        }
+
+        self.remember(&claims.jti)
+            .then_some(())
+            .ok_or_else(|| invalid("DPoP proof has been used before"))
+    }
+
+    /// Compare `htu` with the request URI, ignoring query and fragment
+    /// (RFC 9449 §4.3).
+    fn htu_matches(&self, htu: &str, parts: &Parts) -> bool {
+        let Ok(mut htu) = url::Url::parse(htu) else {
+            return false;
+        };
+        htu.set_query(None);
+        htu.set_fragment(None);
+        let path = parts
+            .extensions
+            .get::<OriginalUri>()
+            .map_or(parts.uri.path(), |OriginalUri(uri)| uri.path());
+
+        let base = match self.origin {
+            Some(ref origin) => origin.clone(),
+            None => {
+                let Some(host) = parts
+                    .headers
+                    .get(http::header::HOST)
+                    .and_then(|h| h.to_str().ok())
+                    .or_else(|| parts.uri.authority().map(|a| a.as_str()))
+                else {
+                    return false;
+                };
+                match url::Url::parse(&format!("{}://{host}", self.scheme)) {
+                    Ok(base) => base,
+                    Err(_) => return false,
+                }
+            }
+        };
+        base.join(path).is_ok_and(|expected| expected == htu)
+    }
+
+    /// Record `jti`, returning `false` if it was already seen.
+    fn remember(&self, jti: &str) -> bool {
+        let now = Instant::now();
+        let mut seen = self.replay.lock().expect("DPoP replay cache poisoned");
+        seen.retain(|_, expires| *expires > now);
+        seen.insert(jti.to_owned(), now + self.max_age + self.leeway)
+            .is_none()
+    }
+}
+
+/// [`Authenticator`] accepting Bearer and DPoP-bound tokens; see
+/// [`BearerAuth::with_dpop`](super::BearerAuth::with_dpop).
+#[derive(Clone)]
+pub struct DpopAuth<V> {
+    validator: V,
+    verifier: DpopVerifier,
+}
+
+impl<V> DpopAuth<V> {
+    pub(crate) fn new(validator: V, verifier: DpopVerifier) -> Self {
+        Self {
+            validator,
+            verifier,
+        }
+    }
+}
+
+impl<V> Authenticator for DpopAuth<V>
+where
+    V: Validator,
+    V::Claims: ConfirmationClaims,
+{
+    type Claims = V::Claims;
+    type Error = AuthError;
+
+    async fn authenticate(&self, parts: &Parts) -> Result<Self::Claims, Self::Error> {
+        let (scheme, token) = authorization(parts, &["Bearer", "DPoP"])?;
+        let claims = self
+            .validator
+            .validate(token)
+            .await
+            .map_err(|e| AuthError::InvalidToken(format!("{e:#}")))?;
+
+        match (scheme, claims.dpop_jkt()) {
+            ("DPoP", Some(jkt)) => self.verifier.verify(parts, token, jkt)?,
+            ("DPoP", None) => {
+                return Err(AuthError::InvalidToken("token is not DPoP-bound".into()));
+            }
+            (_, Some(_)) => {
+                return Err(AuthError::InvalidToken(
+                    "DPoP-bound token must use the DPoP scheme".into(),
+                ));
+            }
+            (_, None) if self.verifier.required => {
+                return Err(AuthError::InvalidDpopProof(
+                    "a DPoP-bound token is required".into(),
+                ));
+            }
+            (_, None) => {}
+        }
+        Ok(claims)
+    }
+
+    fn reject(error: AuthError) -> AuthError {
+        error
+    }
+}
+
+/// JWK SHA-256 thumbprint ([RFC 7638](https://datatracker.ietf.org/doc/html/rfc7638)),
+/// as used in `cnf.jkt`. `None` for symmetric keys.
+pub fn jwk_thumbprint(jwk: &Jwk) -> Option<String> {
+    let quote = |s: &str| serde_json::to_string(s).expect("string serializes");
+    let curve = |c| serde_json::to_string(c).expect("curve serializes");
+    // Required members only, in lexicographic order, without whitespace.
+    let canonical = match &jwk.algorithm {
+        AlgorithmParameters::EllipticCurve(p) => format!(
+            r#"{{"crv":{},"kty":"EC","x":{},"y":{}}}"#,
+            curve(&p.curve),
+            quote(&p.x),
+            quote(&p.y)
+        ),
+        AlgorithmParameters::RSA(p) => {
+            format!(r#"{{"e":{},"kty":"RSA","n":{}}}"#, quote(&p.e), quote(&p.n))
+        }
+        AlgorithmParameters::OctetKeyPair(p) => format!(
+            r#"{{"crv":{},"kty":"OKP","x":{}}}"#,
+            curve(&p.curve),
+            quote(&p.x)
+        ),
+        AlgorithmParameters::OctetKey(_) => return None,
+    };
+    Some(URL_SAFE_NO_PAD.encode(Sha256::digest(canonical.as_bytes())))
+}
+
+fn is_symmetric(alg: Algorithm) -> bool {
+    matches!(alg, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512)
+}
+
+/// Whether the proof's `jwk` header includes the private `d` member.
+fn has_private_key(proof: &str) -> bool {
+    let header = proof.split('.').next().unwrap_or_default();
+    URL_SAFE_NO_PAD
+        .decode(header)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice::<serde_json::Value>(&bytes).ok())
+        .is_some_and(|h| h["jwk"].get("d").is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DpopVerifier, jwk_thumbprint};
+    use crate::auth::{
+        AuthError, Authenticator, BearerAuth, Validator,
+        oauth::{Confirmation, OAuthClaims, ProtectedResourceMetadata},
+    };
+    use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+    use jsonwebtoken::{
+        Algorithm, EncodingKey, Header,
+        jwk::{
+            AlgorithmParameters, CommonParameters, EllipticCurve, EllipticCurveKeyParameters,
+            EllipticCurveKeyType, Jwk,
+        },
+    };
+    use sha2::{Digest, Sha256};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    struct ClientKey {
+        encoding: EncodingKey,
+        jwk: Jwk,
+    }
+
+    fn client_key() -> ClientKey {
+        let key = rcgen::KeyPair::generate().unwrap();
+        // Uncompressed P-256 point: 0x04 || x || y.
+        let point = key.public_key_raw();
+        let jwk = Jwk {
+            common: CommonParameters::default(),
+            algorithm: AlgorithmParameters::EllipticCurve(EllipticCurveKeyParameters {
+                key_type: EllipticCurveKeyType::EC,
+                curve: EllipticCurve::P256,
+                x: URL_SAFE_NO_PAD.encode(&point[1..33]),
+                y: URL_SAFE_NO_PAD.encode(&point[33..]),
+            }),
+        };
+        ClientKey {
+            encoding: EncodingKey::from_ec_der(&key.serialize_der()),
+            jwk,
+        }
+    }
+
+    /// Accepts `bound-*` tokens as bound to the thumbprint after the prefix.
+    #[derive(Clone)]
+    struct Tokens;
+
+    impl Validator for Tokens {
+        type Claims = OAuthClaims;
+        type Error = String;
+
+        async fn validate(&self, token: &str) -> Result<OAuthClaims, String> {
+            Ok(OAuthClaims {
+                sub: "alice".into(),
+                cnf: token.strip_prefix("bound-").map(|jkt| Confirmation {
+                    jkt: Some(jkt.into()),
+                }),
+                ..Default::default()
+            })
+        }
+    }
+
+    fn proof(key: &ClientKey, htm: &str, htu: &str, token: &str, jti: &str) -> String {
+        let mut header = Header::new(Algorithm::ES256);
+        header.typ = Some("dpop+jwt".into());
+        header.jwk = Some(key.jwk.clone());
+        let iat = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let claims = serde_json::json!({
+            "jti": jti,
+            "htm": htm,
+            "htu": htu,
+            "iat": iat,
+            "ath": URL_SAFE_NO_PAD.encode(Sha256::digest(token.as_bytes())),
+        });
+        jsonwebtoken::encode(&header, &claims, &key.encoding).unwrap()
+    }
+
+    fn request(authorization: &str, proof: Option<&str>) -> http::request::Parts {
+        let mut req = http::Request::post("/mcp?session=1")
+            .header(http::header::HOST, "mcp.example.com")
+            .header(http::header::AUTHORIZATION, authorization);
+        if let Some(proof) = proof {
+            req = req.header("dpop", proof);
+        }
+        req.body(()).unwrap().into_parts().0
+    }
+
+    #[tokio::test]
+    async fn accepts_valid_proof_and_rejects_replay() {
+        let key = client_key();
+        let token = format!("bound-{}", jwk_thumbprint(&key.jwk).unwrap());
+        let auth = BearerAuth::new(Tokens).with_dpop(DpopVerifier::new());
+        let proof = proof(&key, "POST", "https://mcp.example.com/mcp", &token, "j1");
+
+        let parts = request(&format!("DPoP {token}"), Some(&proof));
+        assert_eq!(auth.authenticate(&parts).await.unwrap().sub, "alice");
+
+        let err = auth.authenticate(&parts).await.unwrap_err();
+        assert_eq!(
+            err,
+            AuthError::InvalidDpopProof("DPoP proof has been used before".into())
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_mismatched_proofs() {
+        let key = client_key();
+        let other = client_key();
+        let token = format!("bound-{}", jwk_thumbprint(&key.jwk).unwrap());
+        let auth = BearerAuth::new(Tokens).with_dpop(DpopVerifier::new());
+        let authorization = format!("DPoP {token}");
+
+        for (proof, reason) in [
+            (
+                proof(&key, "GET", "https://mcp.example.com/mcp", &token, "a"),
+                "htm",
+            ),
+            (
+                proof(&key, "POST", "https://evil.example.com/mcp", &token, "b"),
+                "htu",
+            ),
+            (
+                proof(&key, "POST", "https://mcp.example.com/mcp", "other", "c"),
+                "ath",
+            ),
+            (
+                proof(&other, "POST", "https://mcp.example.com/mcp", &token, "d"),
+                "token binding",
+            ),
+        ] {
+            let err = auth
+                .authenticate(&request(&authorization, Some(&proof)))
+                .await
+                .unwrap_err();
+            assert!(
+                matches!(&err, AuthError::InvalidDpopProof(d) if d.contains(reason)),
+                "{reason}: {err:?}"
+            );
+        }
+
+        let err = auth
+            .authenticate(&request(&authorization, None))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AuthError::InvalidDpopProof(_)));
+    }
+
+    #[tokio::test]
+    async fn enforces_scheme_binding() {
+        let auth = BearerAuth::new(Tokens).with_dpop(DpopVerifier::new());
+        assert!(
+            auth.authenticate(&request("Bearer plain", None))
+                .await
+                .is_ok()
+        );
+        assert!(matches!(
+            auth.authenticate(&request("Bearer bound-abc", None)).await,
+            Err(AuthError::InvalidToken(_))
+        ));
+
+        let strict = BearerAuth::new(Tokens).with_dpop(DpopVerifier::new().require_dpop());
+        assert!(matches!(
+            strict.authenticate(&request("Bearer plain", None)).await,
+            Err(AuthError::InvalidDpopProof(_))
+        ));
+    }
+
+    #[test]
+    fn thumbprint_matches_rfc7638_example() {
+        let jwk: Jwk = serde_json::from_value(serde_json::json!({
+            "kty": "RSA",
+            "n": "0vx7agoebGcQSuuPiLJXZptN9nndrQmbXEps2aiAFbWhM78LhWx4cbbfAAtVT86zwu1RK7aPFFxuhDR1L6tSoc_BJECPebWKRXjBZCiFV4n3oknjhMstn64tZ_2W-5JsGY4Hc5n9yBXArwl93lqt7_RN5w6Cf0h4QyQ5v-65YGjQR0_FDW2QvzqY368QQMicAtaSqzs8KJZgnYb9c7d0zgdAZHzu6qMQvRL5hajrn1n91CbOpbISD08qNLyrdkt-bFTWhAI4vMQFh6WeZu0fM4lFd2NcRwr3XPksINHaQ-G_xBniIqbw0Ls1jF44-csFCur-kEgU8awapJzKnqDKgw",
+            "e": "AQAB",
+            "alg": "RS256",
+            "kid": "2011-04-29",
+        }))
+        .unwrap();
+        assert_eq!(
+            jwk_thumbprint(&jwk).unwrap(),
+            "NzbLsXh8uDCcd-6MNwXF4W_7noWXFZAfHkxZsRGC9Xs"
+        );
+    }
+
+    #[tokio::test]
+    async fn binds_htu_to_configured_origin_or_scheme() {
+        let key = client_key();
+        let token = format!("bound-{}", jwk_thumbprint(&key.jwk).unwrap());
+        let authorization = format!("DPoP {token}");
+        let check = |verifier: DpopVerifier, htu: &str, jti: &str| {
+            let parts = request(&authorization, Some(&proof(&key, "POST", htu, &token, jti)));
+            async move {
+                BearerAuth::new(Tokens)
+                    .with_dpop(verifier)
+                    .authenticate(&parts)
+                    .await
+                    .is_ok()
+            }
+        };
+
+        assert!(!check(DpopVerifier::new(), "http://mcp.example.com/mcp", "a").await);
+        let http = DpopVerifier::new().scheme("http").unwrap();
+        assert!(check(http, "http://mcp.example.com/mcp", "b").await);
+
+        let origin = DpopVerifier::new()
+            .origin("https://public.example.com:8443")
+            .unwrap();
+        assert!(!check(origin.clone(), "https://mcp.example.com/mcp", "c").await);
+        assert!(check(origin, "https://public.example.com:8443/mcp", "d").await);
+    }
+
+    #[test]
+    fn rejects_invalid_origins() {
+        for origin in [
+            "mcp.example.com",
+            "ftp://mcp.example.com",
+            "https://mcp.example.com/mcp",
+            "https://mcp.example.com?x=1",
+            "https://user@mcp.example.com",
+        ] {
+            assert!(DpopVerifier::new().origin(origin).is_err(), "{origin}");
+        }
+        assert!(
+            DpopVerifier::new()
+                .origin("https://mcp.example.com/")
+                .is_ok()
+        );
+        assert!(DpopVerifier::new().scheme("ws").is_err());
+    }
+
+    #[test]
+    fn advertises_metadata() {
+        let metadata = DpopVerifier::new()
+            .algorithms([
+                jsonwebtoken::Algorithm::ES256,
+                jsonwebtoken::Algorithm::HS256,
+            ])
+            .require_dpop()
+            .advertise(ProtectedResourceMetadata::default());
+        assert_eq!(
+            metadata.dpop_signing_alg_values_supported,
+            Some(vec!["ES256".to_string()])
+        );
+        assert_eq!(metadata.dpop_bound_access_tokens_required, Some(true));
+    }
+}
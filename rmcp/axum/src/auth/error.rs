@@ -0,0 +1,128 @@
+//! Structured authentication errors.
+//!
+//! [`AuthError`] carries enough information for [`AuthLayer`](super::AuthLayer)
+//! to pick the right status code, the RFC 6750 `error` parameter of the
+//! `WWW-Authenticate` challenge, and a JSON-RPC error body.
+
+use std::fmt;
+
+/// JSON-RPC error code for malformed requests.
+const INVALID_REQUEST_CODE: i64 = -32600;
+
+/// JSON-RPC error code used for authentication and authorization failures.
+const UNAUTHORIZED_CODE: i64 = -32001;
+
+/// Reason an [`Authenticator`](super::Authenticator) rejected a request.
+///
+/// Authenticators with other error types are reported as
+/// [`AuthError::InvalidToken`] via [`AuthError::other`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AuthError {
+    /// No credentials were presented.
+    ///
+    /// Per [RFC 6750 §3.1](https://datatracker.ietf.org/doc/html/rfc6750#section-3.1)
+    /// the challenge carries no error code in this case.
+    MissingCredentials,
+    /// The request is malformed, e.g. an unparsable `Authorization` header
+    /// (`invalid_request`, 400).
+    InvalidRequest(String),
+    /// The token is expired, revoked, malformed, or invalid for other
+    /// reasons (`invalid_token`, 401).
+    InvalidToken(String),
+    /// The DPoP proof is missing, malformed, or doesn't match the request or
+    /// token (`invalid_dpop_proof`, 401). Challenged with the `DPoP` scheme
+    /// per [RFC 9449 §7.1](https://datatracker.ietf.org/doc/html/rfc9449#section-7.1).
+    InvalidDpopProof(String),
+    /// The token is valid but lacks the required scope
+    /// (`insufficient_scope`, 403).
+    InsufficientScope {
+        /// The scope required for the request.
+        scope: String,
+    },
+}
+
+impl AuthError {
+    /// Report any other failure as [`AuthError::InvalidToken`] with the
+    /// error's message as description.
+    pub fn other(error: impl fmt::Display) -> Self {
+        AuthError::InvalidToken(error.to_string())
+    }
+
+    /// HTTP status code for this error.
+    pub fn status(&self) -> http::StatusCode {
+        match self {
+            AuthError::MissingCredentials
+            | AuthError::InvalidToken(_)
+            | AuthError::InvalidDpopProof(_) => http::StatusCode::UNAUTHORIZED,
+            AuthError::InvalidRequest(_) => http::StatusCode::BAD_REQUEST,
+            AuthError::InsufficientScope { .. } => http::StatusCode::FORBIDDEN,
+        }
+    }
+
+    /// RFC 6750 `error` code, if any.
+    pub fn error_code(&self) -> Option<&'static str> {
+        match self {
+            AuthError::MissingCredentials => None,
+            AuthError::InvalidRequest(_) => Some("invalid_request"),
+            AuthError::InvalidToken(_) => Some("invalid_token"),
+            AuthError::InvalidDpopProof(_) => Some("invalid_dpop_proof"),
+            AuthError::InsufficientScope { .. } => Some("insufficient_scope"),
+        }
+    }
+
+    /// Human-readable description, used as `error_description`.
+    pub fn description(&self) -> String {
+        match self {
+            AuthError::MissingCredentials => "missing credentials".into(),
+            AuthError::InvalidRequest(d)
+            | AuthError::InvalidToken(d)
+            | AuthError::InvalidDpopProof(d) => d.clone(),
+            AuthError::InsufficientScope { scope } => format!("requires scope: {scope}"),
+        }
+    }
+
+    /// JSON-RPC style error body.
+    ///
+    /// The `id` is `null` as the middleware rejects the request before the
+    /// JSON-RPC payload is read.
+    pub fn to_json(&self) -> serde_json::Value {
+        let code = match self {
+            AuthError::InvalidRequest(_) => INVALID_REQUEST_CODE,
+            _ => UNAUTHORIZED_CODE,
+        };
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": null,
+            "error": {
+                "code": code,
+                "message": self.description(),
+                "data": {
+                    "error": self.error_code().unwrap_or("unauthorized"),
+                },
+            },
+        })
+    }
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.error_code() {
+            Some(code) => write!(f, "{code}: {}", self.description()),
+            None => f.write_str(&self.description()),
+        }
+    }
+}
+
+impl std::error::Error for AuthError {}
+
+impl From<String> for AuthError {
+    fn from(description: String) -> Self {
+        AuthError::InvalidToken(description)
+    }
+}
+
+impl From<&str> for AuthError {
+    fn from(description: &str) -> Self {
+        AuthError::InvalidToken(description.into())
+    }
+}
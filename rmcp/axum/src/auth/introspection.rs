@@ -0,0 +1,407 @@
+//! Token introspection plugin for opaque OAuth access tokens.
+//!
+//! Validates tokens by POSTing them to an
+//! [RFC 7662](https://datatracker.ietf.org/doc/html/rfc7662) introspection
+//! endpoint, authenticating with client credentials. Implements
+//! [`Validator`](super::Validator) producing [`OAuthClaims`].
+//!
+//! Active tokens are cached until their `exp`, inactive tokens for a short
+//! negative TTL, so a busy client does not hit the authorization server on
+//! every request.
+//!
+//! Requires the `introspection` feature.
+//!
+//! ```rust,ignore
+//! use rmcp_axum::auth::{AuthLayer, BearerAuth, introspection::IntrospectionValidator};
+//!
+//! let validator = IntrospectionValidator::from_endpoint("https://auth.example.com/oauth/introspect")
+//!     .client_credentials("mcp-server", "s3cret")
+//!     .build();
+//!
+//! let app = axum::Router::new()
+//!     .nest_service("/mcp", service)
+//!     .layer(AuthLayer::new(BearerAuth::new(validator)));
+//! ```
+
+use crate::auth::{
+    Validator,
+    oauth::{Audience, Confirmation, OAuthClaims},
+};
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// Default time an inactive-token result is cached.
+const DEFAULT_NEGATIVE_TTL: Duration = Duration::from_secs(10);
+
+/// Default upper bound on the number of cached introspection results.
+const DEFAULT_MAX_ENTRIES: usize = 10_000;
+
+/// Introspection response ([RFC 7662 §2.2](https://datatracker.ietf.org/doc/html/rfc7662#section-2.2)).
+#[derive(Debug, Deserialize)]
+struct IntrospectionResponse {
+    active: bool,
+    scope: Option<String>,
+    client_id: Option<String>,
+    username: Option<String>,
+    sub: Option<String>,
+    aud: Option<Audience>,
+    iss: Option<String>,
+    exp: Option<u64>,
+    jti: Option<String>,
+    sid: Option<String>,
+    cnf: Option<Confirmation>,
+}
+
+impl IntrospectionResponse {
+    fn into_claims(self) -> Result<OAuthClaims> {
+        // Prefer `sub`, falling back to the resource owner or client.
+        let Some(sub) = self.sub.or(self.username).or(self.client_id) else {
+            bail!("active token has no subject");
+        };
+        Ok(OAuthClaims {
+            sub,
+            iss: self.iss,
+            aud: self.aud.map(Audience::into_vec),
+            scope: self
+                .scope
+                .map(|s| s.split_whitespace().map(String::from).collect())
+                .unwrap_or_default(),
+            exp: self.exp,
+            jti: self.jti,
+            sid: self.sid,
+            cnf: self.cnf,
+            ..Default::default()
+        })
+    }
+}
+
+/// A cached introspection result.
+enum CacheEntry {
+    Active {
+        claims: Box<OAuthClaims>,
+        expires_at: Instant,
+    },
+    Inactive {
+        expires_at: Instant,
+    },
+}
+
+impl CacheEntry {
+    fn expires_at(&self) -> Instant {
+        match self {
+            CacheEntry::Active { expires_at, .. } | CacheEntry::Inactive { expires_at } => {
+                *expires_at
+            }
+        }
+    }
+}
+
+/// Builder for [`IntrospectionValidator`].
+pub struct IntrospectionValidatorBuilder {
+    endpoint: String,
+    client_id: Option<String>,
+    client_secret: Option<String>,
+    negative_ttl: Duration,
+    max_entries: usize,
+    client: Option<reqwest::Client>,
+}
+
+impl IntrospectionValidatorBuilder {
+    /// Authenticate to the introspection endpoint with HTTP Basic client
+    /// credentials (`client_secret_basic`).
+    pub fn client_credentials(
+        mut self,
+        client_id: impl Into<String>,
+        client_secret: impl Into<String>,
+    ) -> Self {
+        self.client_id = Some(client_id.into());
+        self.client_secret = Some(client_secret.into());
+        self
+    }
+
+    /// How long an inactive-token result is cached (default 10 seconds).
+    ///
+    /// Set to [`Duration::ZERO`] to disable negative caching.
+    pub fn negative_ttl(mut self, ttl: Duration) -> Self {
+        self.negative_ttl = ttl;
+        self
+    }
+
+    /// Maximum number of cached results (default 10 000).
+    pub fn max_entries(mut self, max: usize) -> Self {
+        self.max_entries = max;
+        self
+    }
+
+    /// Use a preconfigured HTTP client (timeouts, proxies, TLS roots).
+    pub fn http_client(mut self, client: reqwest::Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Build the validator.
+    pub fn build(self) -> IntrospectionValidator {
+        IntrospectionValidator {
+            inner: Arc::new(IntrospectionValidatorInner {
+                client: self.client.unwrap_or_default(),
+                endpoint: self.endpoint,
+                client_id: self.client_id,
+                client_secret: self.client_secret,
+                negative_ttl: self.negative_ttl,
+                max_entries: self.max_entries,
+                cache: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+}
+
+struct IntrospectionValidatorInner {
+    client: reqwest::Client,
+    endpoint: String,
+    client_id: Option<String>,
+    client_secret: Option<String>,
+    negative_ttl: Duration,
+    max_entries: usize,
+    cache: Mutex<HashMap<String, CacheEntry>>,
+}
+
+/// Validator that checks opaque tokens against an RFC 7662 introspection
+/// endpoint.
+#[derive(Clone)]
+pub struct IntrospectionValidator {
+    inner: Arc<IntrospectionValidatorInner>,
+}
+
+impl IntrospectionValidator {
+    /// Start building an introspection validator for the given endpoint.
+    pub fn from_endpoint(endpoint: impl Into<String>) -> IntrospectionValidatorBuilder {
+        IntrospectionValidatorBuilder {
+            endpoint: endpoint.into(),
+            client_id: None,
+            client_secret: None,
+            negative_ttl: DEFAULT_NEGATIVE_TTL,
+            max_entries: DEFAULT_MAX_ENTRIES,
+            client: None,
+        }
+    }
+
+    /// Drop all cached introspection results.
+    pub fn clear_cache(&self) {
+        self.inner.cache.lock().expect("cache poisoned").clear();
+    }
+
+    fn cached(&self, token: &str) -> Option<Result<OAuthClaims>> {
+        let cache = self.inner.cache.lock().expect("cache poisoned");
+        let entry = cache.get(token)?;
+        if entry.expires_at() <= Instant::now() {
+            return None;
+        }
+        Some(match entry {
+            CacheEntry::Active { claims, .. } => Ok(OAuthClaims::clone(claims)),
+            CacheEntry::Inactive { .. } => Err(anyhow::anyhow!("token is not active")),
+        })
+    }
+
+    fn store(&self, token: &str, entry: CacheEntry) {
+        let mut cache = self.inner.cache.lock().expect("cache poisoned");
+        if cache.len() >= self.inner.max_entries {
+            let now = Instant::now();
+            cache.retain(|_, e| e.expires_at() > now);
+            if cache.len() >= self.inner.max_entries {
+                return;
+            }
+        }
+        cache.insert(token.to_owned(), entry);
+    }
+
+    async fn introspect(&self, token: &str) -> Result<IntrospectionResponse> {
+        let inner = &self.inner;
+        let mut request = inner
+            .client
+            .post(&inner.endpoint)
+            .header(http::header::ACCEPT, "application/json")
+            .form(&[("token", token), ("token_type_hint", "access_token")]);
+        if let Some(ref client_id) = inner.client_id {
+            request = request.basic_auth(client_id, inner.client_secret.as_deref());
+        }
+
+        let resp = request
+            .send()
+            .await
+            .context("introspection request failed")?;
+        let status = resp.status();
+        if !status.is_success() {
+            bail!("introspection endpoint returned {status}");
+        }
+        resp.json::<IntrospectionResponse>()
+            .await
+            .context("failed to parse introspection response")
+    }
+}
+
+impl Validator for IntrospectionValidator {
+    type Claims = OAuthClaims;
+    type Error = anyhow::Error;
+
+    async fn validate(&self, token: &str) -> Result<OAuthClaims> {
+        if let Some(result) = self.cached(token) {
+            return result;
+        }
+
+        let response = self.introspect(token).await?;
+        let now = Instant::now();
+        if !response.active {
+            if !self.inner.negative_ttl.is_zero() {
+                let expires_at = now + self.inner.negative_ttl;
+                self.store(token, CacheEntry::Inactive { expires_at });
+            }
+            bail!("token is not active");
+        }
+
+        let claims = response.into_claims()?;
+        match claims.exp.map(seconds_until) {
+            Some(Some(remaining)) => {
+                let expires_at = now + remaining;
+                self.store(
+                    token,
+                    CacheEntry::Active {
+                        claims: Box::new(claims.clone()),
+                        expires_at,
+                    },
+                );
+            }
+            // The server says the token is active, but it has already expired
+            // by our clock; reject it rather than trust the skewed response.
+            Some(None) => bail!("token has expired"),
+            // Without `exp` there is no safe bound on caching.
+            None => {}
+        }
+        Ok(claims)
+    }
+}
+
+/// Time remaining until `exp` (seconds since epoch), or `None` if passed.
+fn seconds_until(exp: u64) -> Option<Duration> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    exp.checked_sub(now)
+        .filter(|secs| *secs > 0)
+        .map(Duration::from_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::auth::{Validator, introspection::IntrospectionValidator};
+    use axum::{Form, Json, Router, routing::post};
+    use std::{
+        collections::HashMap,
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
+        time::{SystemTime, UNIX_EPOCH},
+    };
+
+    /// Spawn a mock introspection server; returns its URL and a hit counter.
+    async fn mock_server() -> (String, Arc<AtomicUsize>) {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        let app = Router::new().route(
+            "/introspect",
+            post(
+                move |headers: http::HeaderMap, Form(form): Form<HashMap<String, String>>| {
+                    let counter = counter.clone();
+                    async move {
+                        counter.fetch_add(1, Ordering::SeqCst);
+                        assert!(headers.contains_key(http::header::AUTHORIZATION));
+                        let exp = SystemTime::now()
+                            .duration_since(UNIX_EPOCH)
+                            .unwrap()
+                            .as_secs()
+                            + 300;
+                        let body = match form.get("token").map(String::as_str) {
+                            Some("good") => serde_json::json!({
+                                "active": true,
+                                "sub": "user-1",
+                                "scope": "mcp:tools files:read",
+                                "aud": "https://mcp.example.com",
+                                "iss": "https://auth.example.com",
+                                "exp": exp,
+                            }),
+                            Some("anonymous") => serde_json::json!({
+                                "active": true,
+                                "scope": "mcp:tools",
+                                "exp": exp,
+                            }),
+                            _ => serde_json::json!({ "active": false }),
+                        };
+                        Json(body)
+                    }
+                },
+            ),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (format!("http://{addr}/introspect"), hits)
+    }
+
+    #[tokio::test]
+    async fn maps_active_response_to_claims() {
+        let (url, _) = mock_server().await;
+        let validator = IntrospectionValidator::from_endpoint(url)
+            .client_credentials("rs", "secret")
+            .build();
+        let claims = validator.validate("good").await.expect("active token");
+        assert_eq!(claims.sub, "user-1");
+        assert_eq!(claims.scope, vec!["mcp:tools", "files:read"]);
+        assert_eq!(
+            claims.aud,
+            Some(vec!["https://mcp.example.com".to_string()])
+        );
+        assert!(claims.exp.is_some());
+    }
+
+    #[tokio::test]
+    async fn rejects_active_tokens_without_subject() {
+        let (url, _) = mock_server().await;
+        let validator = IntrospectionValidator::from_endpoint(url)
+            .client_credentials("rs", "secret")
+            .build();
+        let err = validator.validate("anonymous").await.unwrap_err();
+        assert!(err.to_string().contains("no subject"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn caches_positive_results() {
+        let (url, hits) = mock_server().await;
+        let validator = IntrospectionValidator::from_endpoint(url)
+            .client_credentials("rs", "secret")
+            .build();
+        validator.validate("good").await.unwrap();
+        validator.validate("good").await.unwrap();
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn caches_negative_results() {
+        let (url, hits) = mock_server().await;
+        let validator = IntrospectionValidator::from_endpoint(url)
+            .client_credentials("rs", "secret")
+            .build();
+        assert!(validator.validate("bad").await.is_err());
+        assert!(validator.validate("bad").await.is_err());
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+        validator.clear_cache();
+        assert!(validator.validate("bad").await.is_err());
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+    }
+}
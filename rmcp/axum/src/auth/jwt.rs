@@ -2,7 +2,8 @@
 //!
 //! Validates JWT Bearer tokens by verifying signatures against a JWKS
 //! endpoint. Implements [`Validator`](super::Validator) producing
-//! [`OAuthClaims`].
+//! [`OAuthClaims`]. [`MultiIssuerValidator`] serves several identity-provider
+//! tenants from one endpoint.
 //!
 //! Requires the `jwt` feature.
 //!
@@ -23,27 +24,26 @@
 //!     .layer(AuthLayer::new(BearerAuth::new(validator)));
 //! ```
 
-use crate::auth::Validator;
-use anyhow::{Context, Result, anyhow};
+pub use crate::auth::oauth::OAuthClaims;
+pub use jsonwebtoken::Algorithm;
+
+use crate::auth::{
+    Validator,
+    oauth::{Audience, Confirmation},
+};
+use anyhow::{Context, Result, anyhow, bail};
+use futures::future::BoxFuture;
 use jsonwebtoken::{DecodingKey, TokenData, Validation, decode, jwk::JwkSet};
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 use tokio::sync::RwLock;
 
-/// Standard OAuth 2.1 token claims.
-#[derive(Clone, Debug)]
-pub struct OAuthClaims {
-    /// The subject (user/client identifier).
-    pub sub: String,
-    /// The issuer.
-    pub iss: Option<String>,
-    /// The audience.
-    pub aud: Option<Vec<String>>,
-    /// Granted scopes (space-separated in the token, parsed here).
-    pub scope: Vec<String>,
-    /// Expiration time (seconds since epoch).
-    pub exp: Option<u64>,
-}
+/// Most unresolved issuers a [`MultiIssuerValidator`] remembers.
+const MAX_UNRESOLVED: usize = 10_000;
 
 /// Raw JWT claims deserialized from the token payload.
 #[derive(Debug, Serialize, Deserialize)]
@@ -53,33 +53,52 @@ struct RawClaims {
     aud: Option<Audience>,
     scope: Option<String>,
     exp: Option<u64>,
+    jti: Option<String>,
+    sid: Option<String>,
+    cnf: Option<Confirmation>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-#[serde(untagged)]
-enum Audience {
-    Single(String),
-    Multiple(Vec<String>),
+/// Where a [`JwtValidator`] gets its verification keys.
+///
+/// Implemented for JWKS URLs (via [`JwtValidator::from_jwks_url`]) and for a
+/// static [`JwkSet`]. Implement it to verify tokens from an issuer in the
+/// same process without an HTTP round-trip.
+pub trait JwksSource: Send + Sync + 'static {
+    /// Load the current key set. Called on build and whenever a token names
+    /// an unknown `kid`.
+    fn jwks(&self) -> BoxFuture<'_, Result<JwkSet>>;
 }
 
-impl Audience {
-    fn into_vec(self) -> Vec<String> {
-        match self {
-            Audience::Single(s) => vec![s],
-            Audience::Multiple(v) => v,
-        }
+impl JwksSource for JwkSet {
+    fn jwks(&self) -> BoxFuture<'_, Result<JwkSet>> {
+        Box::pin(async move { Ok(self.clone()) })
+    }
+}
+
+/// A JWKS endpoint.
+struct JwksUrl(String);
+
+impl JwksSource for JwksUrl {
+    fn jwks(&self) -> BoxFuture<'_, Result<JwkSet>> {
+        Box::pin(fetch_jwks(&self.0))
     }
 }
 
 /// Builder for [`JwtValidator`].
 pub struct JwtValidatorBuilder {
-    jwks_url: String,
+    source: Arc<dyn JwksSource>,
     audience: Option<String>,
     issuer: Option<String>,
+    algorithms: Vec<Algorithm>,
 }
 
 impl JwtValidatorBuilder {
     /// Require the `aud` claim to match this value.
+    ///
+    /// This is an exact string comparison. To bind tokens to the MCP server's
+    /// canonical resource URI, prefer
+    /// [`AuthLayer::with_protected_resource`](crate::auth::AuthLayer::with_protected_resource),
+    /// which normalizes both sides.
     pub fn audience(mut self, audience: impl Into<String>) -> Self {
         self.audience = Some(audience.into());
         self
@@ -91,37 +110,30 @@ impl JwtValidatorBuilder {
         self
     }
 
+    /// Algorithms accepted from keys published without an `alg`. Keys with
+    /// an `alg` only accept that one. Defaults to `HS256`.
+    pub fn algorithms(mut self, algorithms: impl IntoIterator<Item = Algorithm>) -> Self {
+        self.algorithms = algorithms.into_iter().collect();
+        self
+    }
+
     /// Fetch the JWKS and build the validator.
     pub async fn build(self) -> Result<JwtValidator> {
-        let jwks = fetch_jwks(&self.jwks_url).await?;
-
-        let mut validation = Validation::default();
-        if let Some(ref aud) = self.audience {
-            validation.set_audience(&[aud]);
-        } else {
-            validation.validate_aud = false;
-        }
-        if let Some(ref iss) = self.issuer {
-            validation.set_issuer(&[iss]);
-        }
-
+        let keys = KeySet::load(self.source).await?;
+        let mut validation = validation(self.audience.as_deref(), self.issuer.as_deref());
+        validation.algorithms = self.algorithms;
         Ok(JwtValidator {
-            inner: Arc::new(JwtValidatorInner {
-                jwks: RwLock::new(jwks),
-                jwks_url: self.jwks_url,
-                validation,
-            }),
+            inner: Arc::new(JwtValidatorInner { keys, validation }),
         })
     }
 }
 
 struct JwtValidatorInner {
-    jwks: RwLock<JwkSet>,
-    jwks_url: String,
+    keys: KeySet,
     validation: Validation,
 }
 
-/// JWT validator that verifies tokens against a JWKS endpoint.
+/// JWT validator that verifies tokens against a JWKS.
 #[derive(Clone)]
 pub struct JwtValidator {
     inner: Arc<JwtValidatorInner>,
@@ -130,44 +142,22 @@ pub struct JwtValidator {
 impl JwtValidator {
     /// Start building a JWT validator from a JWKS URL.
     pub fn from_jwks_url(url: impl Into<String>) -> JwtValidatorBuilder {
+        Self::from_jwks_source(JwksUrl(url.into()))
+    }
+
+    /// Start building a JWT validator from any [`JwksSource`].
+    pub fn from_jwks_source(source: impl JwksSource) -> JwtValidatorBuilder {
         JwtValidatorBuilder {
-            jwks_url: url.into(),
+            source: Arc::new(source),
             audience: None,
             issuer: None,
+            algorithms: Validation::default().algorithms,
         }
     }
 
-    /// Refresh the JWKS from the configured endpoint.
+    /// Reload the JWKS from the configured source.
     pub async fn refresh_jwks(&self) -> Result<()> {
-        let jwks = fetch_jwks(&self.inner.jwks_url).await?;
-        *self.inner.jwks.write().await = jwks;
-        Ok(())
-    }
-
-    fn decode_token(&self, token: &str, jwks: &JwkSet) -> Result<OAuthClaims> {
-        let header = jsonwebtoken::decode_header(token).context("invalid JWT header")?;
-        let kid = header
-            .kid
-            .as_deref()
-            .ok_or_else(|| anyhow!("JWT missing kid header"))?;
-        let jwk = jwks
-            .find(kid)
-            .ok_or_else(|| anyhow!("no matching key for kid: {kid}"))?;
-        let key = DecodingKey::from_jwk(jwk).context("invalid JWK")?;
-        let data: TokenData<RawClaims> =
-            decode(token, &key, &self.inner.validation).context("JWT validation failed")?;
-
-        let claims = data.claims;
-        Ok(OAuthClaims {
-            sub: claims.sub.unwrap_or_default(),
-            iss: claims.iss,
-            aud: claims.aud.map(Audience::into_vec),
-            scope: claims
-                .scope
-                .map(|s| s.split_whitespace().map(String::from).collect())
-                .unwrap_or_default(),
-            exp: claims.exp,
-        })
+        self.inner.keys.refresh().await
     }
 }
 
@@ -176,9 +166,49 @@ impl Validator for JwtValidator {
     type Error = anyhow::Error;
 
     async fn validate(&self, token: &str) -> Result<OAuthClaims> {
+        self.inner.keys.decode(token, &self.inner.validation).await
+    }
+}
+
+fn validation(audience: Option<&str>, issuer: Option<&str>) -> Validation {
+    let mut validation = Validation::default();
+    if let Some(aud) = audience {
+        validation.set_audience(&[aud]);
+    } else {
+        validation.validate_aud = false;
+    }
+    if let Some(iss) = issuer {
+        validation.set_issuer(&[iss]);
+    }
+    validation
+}
+
+/// A JWKS together with the source it is refreshed from.
+struct KeySet {
+    jwks: RwLock<JwkSet>,
+    source: Arc<dyn JwksSource>,
+}
+
+impl KeySet {
+    async fn load(source: Arc<dyn JwksSource>) -> Result<Self> {
+        Ok(Self {
+            jwks: RwLock::new(source.jwks().await?),
+            source,
+        })
+    }
+
+    async fn refresh(&self) -> Result<()> {
+        let jwks = self.source.jwks().await?;
+        *self.jwks.write().await = jwks;
+        Ok(())
+    }
+
+    /// Decode and verify `token`, refreshing the JWKS once if it names an
+    /// unknown `kid` (key rotation).
+    async fn decode(&self, token: &str, validation: &Validation) -> Result<OAuthClaims> {
         // Try with current JWKS.
-        let jwks = self.inner.jwks.read().await;
-        match self.decode_token(token, &jwks) {
+        let jwks = self.jwks.read().await;
+        match decode_token(token, &jwks, validation) {
             Ok(claims) => return Ok(claims),
             Err(e) => {
                 // If key not found, try refreshing JWKS (key rotation).
@@ -190,10 +220,325 @@ impl Validator for JwtValidator {
         drop(jwks);
 
         // Refresh and retry once.
-        self.refresh_jwks().await.context("JWKS refresh failed")?;
+        self.refresh().await.context("JWKS refresh failed")?;
+
+        let jwks = self.jwks.read().await;
+        decode_token(token, &jwks, validation)
+    }
+}
+
+fn decode_token(token: &str, jwks: &JwkSet, validation: &Validation) -> Result<OAuthClaims> {
+    let header = jsonwebtoken::decode_header(token).context("invalid JWT header")?;
+    let kid = header
+        .kid
+        .as_deref()
+        .ok_or_else(|| anyhow!("JWT missing kid header"))?;
+    let jwk = jwks
+        .find(kid)
+        .ok_or_else(|| anyhow!("no matching key for kid: {kid}"))?;
+    // Pin the algorithm to the one the key is published for, or else to the
+    // configured ones, so a token can't pick a weaker one.
+    let mut validation = validation.clone();
+    if let Some(alg) = jwk.common.key_algorithm {
+        let alg = alg
+            .to_string()
+            .parse::<Algorithm>()
+            .map_err(|_| anyhow!("key {kid} is not a signing key"))?;
+        if alg != header.alg {
+            bail!("JWT alg {:?} does not match key {kid}", header.alg);
+        }
+        validation.algorithms = vec![alg];
+    }
+    let key = DecodingKey::from_jwk(jwk).context("invalid JWK")?;
+    let data: TokenData<RawClaims> =
+        decode(token, &key, &validation).context("JWT validation failed")?;
+
+    let claims = data.claims;
+    Ok(OAuthClaims {
+        sub: claims.sub.unwrap_or_default(),
+        iss: claims.iss,
+        aud: claims.aud.map(Audience::into_vec),
+        scope: claims
+            .scope
+            .map(|s| s.split_whitespace().map(String::from).collect())
+            .unwrap_or_default(),
+        exp: claims.exp,
+        jti: claims.jti,
+        sid: claims.sid,
+        cnf: claims.cnf,
+        ..Default::default()
+    })
+}
+
+/// The unverified `iss` claim of `token`, used only to pick the keys that
+/// then verify it.
+fn unverified_issuer(token: &str) -> Result<String> {
+    #[derive(Deserialize)]
+    struct Issuer {
+        iss: Option<String>,
+    }
+
+    let mut validation = Validation::default();
+    validation.insecure_disable_signature_validation();
+    validation.validate_exp = false;
+    validation.validate_aud = false;
+    validation.required_spec_claims.clear();
+    let data: TokenData<Issuer> =
+        decode(token, &DecodingKey::from_secret(&[]), &validation).context("invalid JWT")?;
+    data.claims
+        .iss
+        .ok_or_else(|| anyhow!("JWT missing iss claim"))
+}
+
+/// An identity-provider tenant trusted by a [`MultiIssuerValidator`].
+#[derive(Clone)]
+pub struct Tenant {
+    id: String,
+    issuer: String,
+    source: Arc<dyn JwksSource>,
+    algorithms: Vec<Algorithm>,
+}
+
+impl Tenant {
+    /// A tenant whose tokens carry `issuer` and are signed by keys published
+    /// at `jwks_url`.
+    pub fn from_jwks_url(
+        id: impl Into<String>,
+        issuer: impl Into<String>,
+        jwks_url: impl Into<String>,
+    ) -> Self {
+        Self::from_jwks_source(id, issuer, JwksUrl(jwks_url.into()))
+    }
+
+    /// A tenant whose tokens carry `issuer` and are signed by keys from any
+    /// [`JwksSource`].
+    pub fn from_jwks_source(
+        id: impl Into<String>,
+        issuer: impl Into<String>,
+        source: impl JwksSource,
+    ) -> Self {
+        Self {
+            id: id.into(),
+            issuer: issuer.into(),
+            source: Arc::new(source),
+            algorithms: Validation::default().algorithms,
+        }
+    }
+
+    /// Algorithms accepted from the tenant's keys published without an
+    /// `alg`. Keys with an `alg` only accept that one. Defaults to `HS256`.
+    pub fn algorithms(mut self, algorithms: impl IntoIterator<Item = Algorithm>) -> Self {
+        self.algorithms = algorithms.into_iter().collect();
+        self
+    }
+
+    /// The tenant identifier reported in [`OAuthClaims::tenant`].
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The exact `iss` value of the tenant's tokens.
+    pub fn issuer(&self) -> &str {
+        &self.issuer
+    }
+}
+
+/// Looks up tenants by issuer for a [`MultiIssuerValidator`], e.g. from a
+/// database of onboarded customers.
+///
+/// Called for issuers that aren't configured statically and aren't cached
+/// yet. Returning `Ok(None)` rejects the token, and further tokens from the
+/// issuer until the
+/// [`unresolved_ttl`](MultiIssuerValidatorBuilder::unresolved_ttl) passes.
+pub trait IssuerResolver: Send + Sync + 'static {
+    /// Resolve the tenant whose tokens carry `issuer`.
+    fn resolve<'a>(&'a self, issuer: &'a str) -> BoxFuture<'a, Result<Option<Tenant>>>;
+}
+
+/// Builder for [`MultiIssuerValidator`].
+pub struct MultiIssuerValidatorBuilder {
+    tenants: HashMap<String, Tenant>,
+    resolver: Option<Box<dyn IssuerResolver>>,
+    audience: Option<String>,
+    unresolved_ttl: Duration,
+}
+
+impl MultiIssuerValidatorBuilder {
+    /// Trust tokens from `tenant`.
+    pub fn tenant(mut self, tenant: Tenant) -> Self {
+        self.tenants.insert(tenant.issuer.clone(), tenant);
+        self
+    }
+
+    /// Resolve issuers that aren't configured with [`tenant`](Self::tenant).
+    pub fn resolver(mut self, resolver: impl IssuerResolver) -> Self {
+        self.resolver = Some(Box::new(resolver));
+        self
+    }
+
+    /// Require the `aud` claim to match this value, for every tenant.
+    ///
+    /// See [`JwtValidatorBuilder::audience`].
+    pub fn audience(mut self, audience: impl Into<String>) -> Self {
+        self.audience = Some(audience.into());
+        self
+    }
 
-        let jwks = self.inner.jwks.read().await;
-        self.decode_token(token, &jwks)
+    /// How long to reject an issuer the resolver didn't know without asking
+    /// it again. Up to 10,000 issuers are remembered. Zero disables this.
+    /// Defaults to 60 seconds.
+    pub fn unresolved_ttl(mut self, ttl: Duration) -> Self {
+        self.unresolved_ttl = ttl;
+        self
+    }
+
+    /// Build the validator. Each tenant's JWKS is fetched on its first token.
+    pub fn build(self) -> MultiIssuerValidator {
+        MultiIssuerValidator {
+            inner: Arc::new(MultiIssuerInner {
+                tenants: self.tenants,
+                resolver: self.resolver,
+                audience: self.audience,
+                unresolved_ttl: self.unresolved_ttl,
+                keys: RwLock::new(HashMap::new()),
+                unresolved: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+}
+
+struct MultiIssuerInner {
+    tenants: HashMap<String, Tenant>,
+    resolver: Option<Box<dyn IssuerResolver>>,
+    audience: Option<String>,
+    unresolved_ttl: Duration,
+    /// Loaded keys by issuer.
+    keys: RwLock<HashMap<String, Arc<TenantKeys>>>,
+    /// When issuers the resolver didn't know may be looked up again.
+    unresolved: Mutex<HashMap<String, Instant>>,
+}
+
+struct TenantKeys {
+    tenant: String,
+    keys: KeySet,
+    validation: Validation,
+}
+
+/// JWT validator for an endpoint shared by several identity-provider
+/// tenants.
+///
+/// Each token's `iss` selects the tenant, and so the JWKS that must have
+/// signed it, from an allow-list or an [`IssuerResolver`]. Keys are cached
+/// per issuer and refreshed on unknown `kid`s as in [`JwtValidator`]. The
+/// tenant id is reported in [`OAuthClaims::tenant`].
+///
+/// ```rust,ignore
+/// use rmcp_axum::auth::jwt::{MultiIssuerValidator, Tenant};
+///
+/// let validator = MultiIssuerValidator::builder()
+///     .tenant(Tenant::from_jwks_url(
+///         "acme",
+///         "https://acme.auth.example.com/",
+///         "https://acme.auth.example.com/.well-known/jwks.json",
+///     ))
+///     .resolver(CustomerDirectory::new(db))
+///     .audience("https://mcp.example.com")
+///     .build();
+/// ```
+#[derive(Clone)]
+pub struct MultiIssuerValidator {
+    inner: Arc<MultiIssuerInner>,
+}
+
+impl MultiIssuerValidator {
+    /// Start building a multi-issuer validator.
+    pub fn builder() -> MultiIssuerValidatorBuilder {
+        MultiIssuerValidatorBuilder {
+            tenants: HashMap::new(),
+            resolver: None,
+            audience: None,
+            unresolved_ttl: Duration::from_secs(60),
+        }
+    }
+
+    async fn tenant_keys(&self, issuer: &str) -> Result<Arc<TenantKeys>> {
+        if let Some(keys) = self.inner.keys.read().await.get(issuer) {
+            return Ok(keys.clone());
+        }
+
+        let tenant = match self.inner.tenants.get(issuer) {
+            Some(tenant) => tenant.clone(),
+            None => match self.resolve(issuer).await? {
+                Some(tenant) => tenant,
+                None => bail!("untrusted issuer: {issuer}"),
+            },
+        };
+        let mut validation = validation(self.inner.audience.as_deref(), Some(issuer));
+        validation.algorithms = tenant.algorithms;
+        let keys = Arc::new(TenantKeys {
+            keys: KeySet::load(tenant.source)
+                .await
+                .with_context(|| format!("failed to load JWKS for tenant {}", tenant.id))?,
+            validation,
+            tenant: tenant.id,
+        });
+        self.inner
+            .keys
+            .write()
+            .await
+            .insert(issuer.to_owned(), keys.clone());
+        Ok(keys)
+    }
+
+    /// Ask the resolver for the tenant of `issuer`, remembering issuers it
+    /// doesn't know for the `unresolved_ttl`.
+    async fn resolve(&self, issuer: &str) -> Result<Option<Tenant>> {
+        let Some(ref resolver) = self.inner.resolver else {
+            return Ok(None);
+        };
+        let now = Instant::now();
+        if self
+            .unresolved()
+            .get(issuer)
+            .is_some_and(|until| *until > now)
+        {
+            return Ok(None);
+        }
+
+        let tenant = resolver
+            .resolve(issuer)
+            .await?
+            .filter(|tenant| tenant.issuer == issuer);
+        if tenant.is_none() && !self.inner.unresolved_ttl.is_zero() {
+            let mut unresolved = self.unresolved();
+            if unresolved.len() >= MAX_UNRESOLVED {
+                unresolved.retain(|_, until| *until > now);
+            }
+            if unresolved.len() < MAX_UNRESOLVED {
+                unresolved.insert(issuer.to_owned(), now + self.inner.unresolved_ttl);
+            }
+        }
+        Ok(tenant)
+    }
+
+    fn unresolved(&self) -> std::sync::MutexGuard<'_, HashMap<String, Instant>> {
+        self.inner
+            .unresolved
+            .lock()
+            .expect("unresolved issuers poisoned")
+    }
+}
+
+impl Validator for MultiIssuerValidator {
+    type Claims = OAuthClaims;
+    type Error = anyhow::Error;
+
+    async fn validate(&self, token: &str) -> Result<OAuthClaims> {
+        let issuer = unverified_issuer(token)?;
+        let keys = self.tenant_keys(&issuer).await?;
+        let mut claims = keys.keys.decode(token, &keys.validation).await?;
+        claims.tenant = Some(keys.tenant.clone());
+        Ok(claims)
     }
 }
 
@@ -205,3 +550,136 @@ async fn fetch_jwks(url: &str) -> Result<JwkSet> {
         .context("failed to parse JWKS")?;
     Ok(jwks)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{IssuerResolver, MultiIssuerValidator, Tenant};
+    use crate::auth::Validator;
+    use anyhow::Result;
+    use futures::future::BoxFuture;
+    use jsonwebtoken::{Algorithm, EncodingKey, Header, encode, jwk::JwkSet};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const ACME_SECRET: &[u8] = b"acme-signing-secret-0123456789ab";
+    const GLOBEX_SECRET: &[u8] = b"globex-signing-secret-0123456789";
+
+    fn jwks(k: &str) -> JwkSet {
+        serde_json::from_value(serde_json::json!({
+            "keys": [{ "kty": "oct", "kid": "k1", "alg": "HS256", "k": k }],
+        }))
+        .unwrap()
+    }
+
+    fn acme() -> Tenant {
+        let keys = jwks("YWNtZS1zaWduaW5nLXNlY3JldC0wMTIzNDU2Nzg5YWI");
+        Tenant::from_jwks_source("acme", "https://acme.example.com", keys)
+    }
+
+    fn globex() -> Tenant {
+        let keys = jwks("Z2xvYmV4LXNpZ25pbmctc2VjcmV0LTAxMjM0NTY3ODk");
+        Tenant::from_jwks_source("globex", "https://globex.example.com", keys)
+    }
+
+    fn token(issuer: &str, secret: &[u8]) -> String {
+        token_with(Algorithm::HS256, issuer, secret)
+    }
+
+    fn token_with(alg: Algorithm, issuer: &str, secret: &[u8]) -> String {
+        let header = Header {
+            kid: Some("k1".into()),
+            ..Header::new(alg)
+        };
+        let claims = serde_json::json!({
+            "sub": "alice",
+            "iss": issuer,
+            "aud": "https://mcp.example.com",
+            "exp": jsonwebtoken::get_current_timestamp() + 60,
+        });
+        encode(&header, &claims, &EncodingKey::from_secret(secret)).unwrap()
+    }
+
+    #[derive(Default)]
+    struct Directory {
+        calls: AtomicUsize,
+    }
+
+    impl IssuerResolver for &'static Directory {
+        fn resolve<'a>(&'a self, issuer: &'a str) -> BoxFuture<'a, Result<Option<Tenant>>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Box::pin(async move { Ok((issuer == "https://globex.example.com").then(globex)) })
+        }
+    }
+
+    #[tokio::test]
+    async fn selects_keys_by_issuer() {
+        let validator = MultiIssuerValidator::builder()
+            .tenant(acme())
+            .tenant(globex())
+            .audience("https://mcp.example.com")
+            .build();
+
+        let claims = validator
+            .validate(&token("https://acme.example.com", ACME_SECRET))
+            .await
+            .unwrap();
+        assert_eq!(claims.tenant.as_deref(), Some("acme"));
+        let claims = validator
+            .validate(&token("https://globex.example.com", GLOBEX_SECRET))
+            .await
+            .unwrap();
+        assert_eq!(claims.tenant.as_deref(), Some("globex"));
+
+        // Claiming another tenant's issuer doesn't help without its key.
+        assert!(
+            validator
+                .validate(&token("https://acme.example.com", GLOBEX_SECRET))
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn resolves_unknown_issuers_once() {
+        let directory: &'static Directory = Box::leak(Box::default());
+        let validator = MultiIssuerValidator::builder()
+            .tenant(acme())
+            .resolver(directory)
+            .build();
+
+        // Unknown issuers are remembered, so the resolver is asked once.
+        for _ in 0..2 {
+            let err = validator
+                .validate(&token("https://evil.example.com", GLOBEX_SECRET))
+                .await
+                .unwrap_err();
+            assert!(err.to_string().contains("untrusted issuer"));
+        }
+
+        for _ in 0..2 {
+            let claims = validator
+                .validate(&token("https://globex.example.com", GLOBEX_SECRET))
+                .await
+                .unwrap();
+            assert_eq!(claims.tenant.as_deref(), Some("globex"));
+        }
+        assert_eq!(directory.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn pins_algorithms_of_keys_without_alg() {
+        let keys: JwkSet = serde_json::from_value(serde_json::json!({
+            "keys": [{ "kty": "oct", "kid": "k1", "k": "YWNtZS1zaWduaW5nLXNlY3JldC0wMTIzNDU2Nzg5YWI" }],
+        }))
+        .unwrap();
+        let tenant = || Tenant::from_jwks_source("acme", "https://acme.example.com", keys.clone());
+        let hs384 = token_with(Algorithm::HS384, "https://acme.example.com", ACME_SECRET);
+
+        let validator = MultiIssuerValidator::builder().tenant(tenant()).build();
+        assert!(validator.validate(&hs384).await.is_err());
+
+        let validator = MultiIssuerValidator::builder()
+            .tenant(tenant().algorithms([Algorithm::HS384]))
+            .build();
+        assert!(validator.validate(&hs384).await.is_ok());
+    }
+}
@@ -3,11 +3,15 @@
 //! Provides a tower middleware that validates incoming requests using a
 //! user-defined [`Authenticator`] trait. On success, the authenticated
 //! claims are inserted into HTTP extensions and become accessible in MCP
-//! tool handlers via `Extension(parts): Extension<Parts>`.
+//! tool handlers via the [`Claims`](crate::tool::Claims) extractor (feature
+//! `server`) or `Extension(parts): Extension<Parts>`.
 //!
-//! When configured with a [`ResourceServerConfig`](oauth::ResourceServerConfig),
-//! the middleware emits spec-compliant `WWW-Authenticate` headers in 401
-//! responses per the MCP authorization specification.
+//! Rejected requests get a `WWW-Authenticate` challenge with the RFC 6750
+//! `error` code derived from the authenticator's [`AuthError`], and a
+//! JSON-RPC style JSON body. When configured with a
+//! [`ResourceServerConfig`](oauth::ResourceServerConfig), the challenge also
+//! carries `resource_metadata` and `scope` per the MCP authorization
+//! specification.
 //!
 //! # Example
 //!
@@ -40,18 +44,48 @@
 //! ```
 
 mod bearer;
+mod composite;
+mod error;
+mod principal;
+mod scope;
 
 pub mod oauth;
+pub mod revocation;
+
+#[cfg(feature = "api-key")]
+pub mod api_key;
+
+#[cfg(feature = "dpop")]
+pub mod dpop;
+
+#[cfg(feature = "introspection")]
+pub mod introspection;
 
 #[cfg(feature = "jwt")]
 pub mod jwt;
 
+#[cfg(feature = "mtls")]
+pub mod mtls;
+
+#[cfg(feature = "authorization-server")]
+pub mod server;
+
 pub use bearer::BearerAuth;
+pub use composite::{AuthenticatorExt, Either, FirstOf, MapClaims};
+pub use error::AuthError;
+pub use principal::Principal;
+pub use scope::ScopedClaims;
 
 use futures::future::BoxFuture;
-use http::{Request, Response, StatusCode};
-use oauth::{ResourceServerConfig, www_authenticate_401};
-use std::task::{Context, Poll};
+use http::{Request, Response};
+use oauth::{
+    AudienceClaims, ProtectedResource, ResourceBound, ResourceServerConfig, auth_error_response,
+};
+use revocation::{Revocable, RevocableClaims, RevocationStore};
+use std::{
+    sync::Arc,
+    task::{Context, Poll},
+};
 
 /// Trait for validating incoming MCP requests.
 ///
@@ -69,6 +103,16 @@ pub trait Authenticator: Clone + Send + Sync + 'static {
         &self,
         parts: &http::request::Parts,
     ) -> impl Future<Output = Result<Self::Claims, Self::Error>> + Send;
+
+    /// Convert a failure into the [`AuthError`] that shapes the rejection
+    /// response.
+    ///
+    /// The default reports the error message as
+    /// [`AuthError::InvalidToken`]; authenticators returning [`AuthError`]
+    /// override this to keep the status and error code.
+    fn reject(error: Self::Error) -> AuthError {
+        AuthError::other(error)
+    }
 }
 
 /// Trait for validating a credential string (e.g., a Bearer token).
@@ -132,13 +176,44 @@ impl<A> AuthLayer<A> {
     /// Configure OAuth resource server metadata for spec-compliant error
     /// responses.
     ///
-    /// When set, 401 responses will include a `WWW-Authenticate` header with
+    /// When set, `WWW-Authenticate` challenges will include
     /// `resource_metadata` and `scope` parameters per the MCP authorization
     /// specification.
     pub fn with_resource_server(mut self, config: ResourceServerConfig) -> Self {
         self.resource_server = Some(config);
         self
     }
+
+    /// Bind this layer to a [`ProtectedResource`] (RFC 8707).
+    ///
+    /// Tokens whose audience doesn't name the canonical resource URI are
+    /// rejected, and 401 challenges point at the resource's metadata URL.
+    /// Serve the metadata with [`ProtectedResource::router`] so both come
+    /// from the same configuration.
+    pub fn with_protected_resource(self, resource: ProtectedResource) -> AuthLayer<ResourceBound<A>>
+    where
+        A: Authenticator,
+        A::Claims: AudienceClaims,
+    {
+        AuthLayer {
+            resource_server: Some(resource.resource_server_config()),
+            authenticator: ResourceBound::new(self.authenticator, resource),
+        }
+    }
+
+    /// Reject credentials revoked in `store`, by token id, subject or
+    /// session id.
+    pub fn with_revocation<R>(self, store: Arc<R>) -> AuthLayer<Revocable<A, R>>
+    where
+        A: Authenticator,
+        A::Claims: RevocableClaims,
+        R: RevocationStore,
+    {
+        AuthLayer {
+            resource_server: self.resource_server,
+            authenticator: Revocable::new(self.authenticator, store),
+        }
+    }
 }
 
 impl<A, S> tower::Layer<S> for AuthLayer<A>
@@ -196,17 +271,10 @@ where
                     req.extensions_mut().insert(claims);
                     inner.call(req).await
                 }
-                Err(err) => {
-                    let mut builder = Response::builder().status(StatusCode::UNAUTHORIZED);
-                    if let Some(ref config) = resource_server {
-                        builder = builder
-                            .header(http::header::WWW_AUTHENTICATE, www_authenticate_401(config));
-                    }
-                    let response = builder
-                        .body(axum::body::Body::from(err.to_string()))
-                        .expect("valid response");
-                    Ok(response)
-                }
+                Err(err) => Ok(auth_error_response(
+                    resource_server.as_ref(),
+                    &A::reject(err),
+                )),
             }
         })
     }
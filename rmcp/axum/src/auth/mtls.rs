@@ -0,0 +1,525 @@
+//! Client certificate (mTLS) authentication plugin.
+//!
+//! Reads the client's leaf certificate either from the TLS connection, via
+//! a [`PeerCertificates`] request extension, or from a header set by a
+//! TLS-terminating proxy. Forwarded certificates are only honored from
+//! configured proxy addresses. The certificate's subject and SANs become
+//! [`CertificateClaims`], and it can be required to be signed by a
+//! specific CA.
+//!
+//! With axum-server and rustls, insert [`PeerCertificates`] from the
+//! acceptor so the certificate verified during the handshake reaches the
+//! authenticator.
+//!
+//! Requires the `mtls` feature.
+//!
+//! ```rust,ignore
+//! use rmcp_axum::auth::AuthLayer;
+//! use rmcp_axum::auth::mtls::{CertIdentity, ClientCertAuth};
+//!
+//! // Behind nginx with `proxy_set_header X-SSL-Client-Cert $ssl_client_escaped_cert;`
+//! let auth = ClientCertAuth::builder()
+//!     .forwarded_header("x-ssl-client-cert")
+//!     .trusted_proxy("10.0.0.0/8")
+//!     .trusted_ca_pem(include_bytes!("internal-ca.pem"))
+//!     .identity(CertIdentity::UriSan)
+//!     .build()?;
+//!
+//! let app = axum::Router::new()
+//!     .nest_service("/mcp", service)
+//!     .layer(AuthLayer::new(auth))
+//!     .into_make_service_with_connect_info::<std::net::SocketAddr>();
+//! ```
+
+use crate::auth::{AuthError, Authenticator};
+use anyhow::{Context, Result, anyhow, bail};
+use axum::extract::ConnectInfo;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use http::{HeaderName, request::Parts};
+use sha2::{Digest, Sha256};
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+};
+use x509_parser::{
+    certificate::X509Certificate, extensions::GeneralName, pem::parse_x509_pem, prelude::FromDer,
+};
+
+/// DER-encoded certificate chain presented by the TLS peer, leaf first.
+///
+/// Insert this into request extensions from the TLS acceptor.
+#[derive(Clone, Debug)]
+pub struct PeerCertificates(pub Vec<Vec<u8>>);
+
+/// Which part of the certificate becomes [`CertificateClaims::principal`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CertIdentity {
+    /// The subject common name (default).
+    #[default]
+    CommonName,
+    /// The full subject distinguished name.
+    Subject,
+    /// The first URI SAN, e.g. a SPIFFE ID.
+    UriSan,
+    /// The first DNS SAN.
+    DnsSan,
+    /// The first email SAN.
+    EmailSan,
+}
+
+/// Claims extracted from a client certificate.
+#[derive(Clone, Debug)]
+pub struct CertificateClaims {
+    /// The identity selected by [`CertIdentity`].
+    pub principal: String,
+    /// Subject distinguished name, e.g. `CN=billing,O=Example`.
+    pub subject: String,
+    /// Subject common name.
+    pub common_name: Option<String>,
+    /// DNS SANs.
+    pub dns_names: Vec<String>,
+    /// URI SANs.
+    pub uris: Vec<String>,
+    /// Email SANs.
+    pub emails: Vec<String>,
+    /// Issuer distinguished name.
+    pub issuer: String,
+    /// Serial number (hex).
+    pub serial: String,
+    /// SHA-256 fingerprint of the DER certificate (hex).
+    pub fingerprint: String,
+}
+
+/// Builder for [`ClientCertAuth`].
+#[derive(Default)]
+pub struct ClientCertAuthBuilder {
+    forwarded_header: Option<String>,
+    trusted_proxies: Vec<String>,
+    trusted_cas: Vec<Vec<u8>>,
+    identity: CertIdentity,
+}
+
+impl ClientCertAuthBuilder {
+    /// Accept certificates forwarded by a proxy in this header.
+    ///
+    /// The value may be a URL-encoded or plain PEM certificate (nginx
+    /// `$ssl_client_escaped_cert`), base64 DER, or an Envoy
+    /// `X-Forwarded-Client-Cert` element with a `Cert="..."` field.
+    pub fn forwarded_header(mut self, name: impl Into<String>) -> Self {
+        self.forwarded_header = Some(name.into());
+        self
+    }
+
+    /// Honor the forwarded header only from this address or CIDR range.
+    /// Repeatable.
+    ///
+    /// The peer address comes from axum's `ConnectInfo<SocketAddr>`; without
+    /// it the header is never honored.
+    pub fn trusted_proxy(mut self, cidr: impl Into<String>) -> Self {
+        self.trusted_proxies.push(cidr.into());
+        self
+    }
+
+    /// Require certificates to be issued by this CA (PEM). Repeatable; any
+    /// listed CA is accepted.
+    pub fn trusted_ca_pem(mut self, pem: impl AsRef<[u8]>) -> Self {
+        self.trusted_cas.push(pem.as_ref().to_vec());
+        self
+    }
+
+    /// Which certificate field identifies the caller.
+    pub fn identity(mut self, identity: CertIdentity) -> Self {
+        self.identity = identity;
+        self
+    }
+
+    /// Validate the configuration and build the authenticator.
+    pub fn build(self) -> Result<ClientCertAuth> {
+        let forwarded_header = self
+            .forwarded_header
+            .map(|h| HeaderName::try_from(h).context("invalid forwarded header name"))
+            .transpose()?;
+        if forwarded_header.is_some() && self.trusted_proxies.is_empty() {
+            bail!("a forwarded certificate header requires at least one trusted proxy");
+        }
+        let trusted_proxies = self
+            .trusted_proxies
+            .iter()
+            .map(|cidr| Cidr::parse(cidr))
+            .collect::<Result<_>>()?;
+        let trusted_cas = self
+            .trusted_cas
+            .iter()
+            .map(|pem| {
+                let (_, pem) = parse_x509_pem(pem).map_err(|e| anyhow!("invalid CA PEM: {e}"))?;
+                pem.parse_x509()
+                    .map_err(|e| anyhow!("invalid CA certificate: {e}"))?;
+                Ok(pem.contents)
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(ClientCertAuth {
+            inner: Arc::new(Inner {
+                forwarded_header,
+                trusted_proxies,
+                trusted_cas,
+                identity: self.identity,
+            }),
+        })
+    }
+}
+
+struct Inner {
+    forwarded_header: Option<HeaderName>,
+    trusted_proxies: Vec<Cidr>,
+    /// DER-encoded CA certificates.
+    trusted_cas: Vec<Vec<u8>>,
+    identity: CertIdentity,
+}
+
+/// [`Authenticator`] for client certificates.
+#[derive(Clone)]
+pub struct ClientCertAuth {
+    inner: Arc<Inner>,
+}
+
+impl ClientCertAuth {
+    /// Start building a client certificate authenticator.
+    pub fn builder() -> ClientCertAuthBuilder {
+        ClientCertAuthBuilder::default()
+    }
+
+    /// The leaf certificate (DER) presented by the client, if any.
+    fn certificate(&self, parts: &Parts) -> Result<Option<Vec<u8>>, AuthError> {
+        if let Some(PeerCertificates(chain)) = parts.extensions.get() {
+            return Ok(chain.first().cloned());
+        }
+        let Some(ref header) = self.inner.forwarded_header else {
+            return Ok(None);
+        };
+        let Some(value) = parts.headers.get(header) else {
+            return Ok(None);
+        };
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+        if !peer.is_some_and(|ip| self.inner.trusted_proxies.iter().any(|c| c.contains(ip))) {
+            // Anyone can send the header; only a trusted proxy's copy counts.
+            return Ok(None);
+        }
+        let value = value
+            .to_str()
+            .map_err(|_| AuthError::InvalidRequest("malformed client certificate header".into()))?;
+        decode_forwarded(value)
+            .map(Some)
+            .ok_or_else(|| AuthError::InvalidRequest("malformed client certificate header".into()))
+    }
+
+    fn verify_issuer(&self, cert: &X509Certificate<'_>) -> bool {
+        if self.inner.trusted_cas.is_empty() {
+            return true;
+        }
+        self.inner.trusted_cas.iter().any(|der| {
+            X509Certificate::from_der(der).is_ok_and(|(_, ca)| {
+                cert.issuer() == ca.subject()
+                    && cert.verify_signature(Some(ca.public_key())).is_ok()
+            })
+        })
+    }
+
+    fn claims(&self, der: &[u8]) -> Result<CertificateClaims, AuthError> {
+        let invalid = |msg: &str| AuthError::InvalidToken(msg.into());
+        let (_, cert) =
+            X509Certificate::from_der(der).map_err(|_| invalid("malformed client certificate"))?;
+        if !cert.validity().is_valid() {
+            return Err(invalid("client certificate is expired or not yet valid"));
+        }
+        if !self.verify_issuer(&cert) {
+            return Err(invalid("client certificate is not issued by a trusted CA"));
+        }
+
+        let (mut dns_names, mut uris, mut emails) = (Vec::new(), Vec::new(), Vec::new());
+        if let Ok(Some(san)) = cert.subject_alternative_name() {
+            for name in &san.value.general_names {
+                match name {
+                    GeneralName::DNSName(n) => dns_names.push(n.to_string()),
+                    GeneralName::URI(u) => uris.push(u.to_string()),
+                    GeneralName::RFC822Name(e) => emails.push(e.to_string()),
+                    _ => {}
+                }
+            }
+        }
+        let common_name = cert
+            .subject()
+            .iter_common_name()
+            .next()
+            .and_then(|cn| cn.as_str().ok())
+            .map(str::to_owned);
+        let subject = cert.subject().to_string();
+
+        let principal = match self.inner.identity {
+            CertIdentity::CommonName => common_name.clone(),
+            CertIdentity::Subject => Some(subject.clone()),
+            CertIdentity::UriSan => uris.first().cloned(),
+            CertIdentity::DnsSan => dns_names.first().cloned(),
+            CertIdentity::EmailSan => emails.first().cloned(),
+        }
+        .ok_or_else(|| {
+            AuthError::InvalidToken(format!(
+                "client certificate has no {:?} identity",
+                self.inner.identity
+            ))
+        })?;
+
+        Ok(CertificateClaims {
+            principal,
+            subject,
+            common_name,
+            dns_names,
+            uris,
+            emails,
+            issuer: cert.issuer().to_string(),
+            serial: cert.raw_serial_as_string(),
+            fingerprint: Sha256::digest(der)
+                .iter()
+                .map(|b| format!("{b:02x}"))
+                .collect(),
+        })
+    }
+}
+
+impl Authenticator for ClientCertAuth {
+    type Claims = CertificateClaims;
+    type Error = AuthError;
+
+    async fn authenticate(&self, parts: &Parts) -> Result<Self::Claims, Self::Error> {
+        let der = self
+            .certificate(parts)?
+            .ok_or(AuthError::MissingCredentials)?;
+        self.claims(&der)
+    }
+
+    fn reject(error: AuthError) -> AuthError {
+        error
+    }
+}
+
+/// Decode a forwarded certificate header value into DER.
+fn decode_forwarded(value: &str) -> Option<Vec<u8>> {
+    // Envoy XFCC: `By=...;Hash=...;Cert="<url-encoded PEM>";...`, first
+    // element is the client.
+    let value = match value.find("Cert=\"") {
+        Some(start) => {
+            let rest = &value[start + 6..];
+            &rest[..rest.find('"')?]
+        }
+        None => value,
+    };
+    let decoded = percent_encoding::percent_decode_str(value)
+        .decode_utf8()
+        .ok()?;
+    if decoded.contains("-----BEGIN") {
+        let (_, pem) = parse_x509_pem(decoded.as_bytes()).ok()?;
+        return Some(pem.contents);
+    }
+    let compact: String = decoded.split_whitespace().collect();
+    STANDARD.decode(compact).ok()
+}
+
+/// An IP network in CIDR notation; a bare address is a single-host network.
+struct Cidr {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    fn parse(s: &str) -> Result<Self> {
+        let (addr, prefix) = s.split_once('/').unwrap_or((s, ""));
+        let network: IpAddr = addr
+            .parse()
+            .with_context(|| format!("invalid trusted proxy address: {s}"))?;
+        let max = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = if prefix.is_empty() {
+            max
+        } else {
+            prefix
+                .parse()
+                .ok()
+                .filter(|p| *p <= max)
+                .ok_or_else(|| anyhow!("invalid trusted proxy prefix: {s}"))?
+        };
+        Ok(Self { network, prefix })
+    }
+
+    fn contains(&self, ip: IpAddr) -> bool {
+        let ip = match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+            v4 => v4,
+        };
+        let (network, ip, bits) = match (self.network, ip) {
+            (IpAddr::V4(n), IpAddr::V4(i)) => (u32::from(n) as u128, u32::from(i) as u128, 32),
+            (IpAddr::V6(n), IpAddr::V6(i)) => (u128::from(n), u128::from(i), 128),
+            _ => return false,
+        };
+        let shift = bits - u32::from(self.prefix);
+        shift == bits || (network >> shift) == (ip >> shift)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CertIdentity, Cidr, ClientCertAuth, PeerCertificates};
+    use crate::auth::{AuthError, Authenticator};
+    use axum::extract::ConnectInfo;
+    use rcgen::{BasicConstraints, CertificateParams, DnType, Ia5String, IsCa, KeyPair, SanType};
+    use std::net::SocketAddr;
+
+    struct Ca {
+        cert: rcgen::Certificate,
+        key: KeyPair,
+    }
+
+    fn ca(name: &str) -> Ca {
+        let key = KeyPair::generate().unwrap();
+        let mut params = CertificateParams::default();
+        params.distinguished_name.push(DnType::CommonName, name);
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        Ca {
+            cert: params.self_signed(&key).unwrap(),
+            key,
+        }
+    }
+
+    fn leaf(ca: &Ca) -> rcgen::Certificate {
+        let key = KeyPair::generate().unwrap();
+        let mut params = CertificateParams::new(vec!["billing.internal".into()]).unwrap();
+        params
+            .distinguished_name
+            .push(DnType::CommonName, "billing");
+        params.subject_alt_names.push(SanType::URI(
+            Ia5String::try_from("spiffe://example.org/billing").unwrap(),
+        ));
+        params.signed_by(&key, &ca.cert, &ca.key).unwrap()
+    }
+
+    fn request(configure: impl FnOnce(&mut http::Request<()>)) -> http::request::Parts {
+        let mut req = http::Request::new(());
+        configure(&mut req);
+        req.into_parts().0
+    }
+
+    fn from_proxy(peer: &str, header: &str) -> http::request::Parts {
+        let addr: SocketAddr = peer.parse().unwrap();
+        request(|req| {
+            req.extensions_mut().insert(ConnectInfo(addr));
+            req.headers_mut()
+                .insert("x-ssl-client-cert", header.parse().unwrap());
+        })
+    }
+
+    fn escaped(pem: &str) -> String {
+        percent_encoding::utf8_percent_encode(pem, percent_encoding::NON_ALPHANUMERIC).to_string()
+    }
+
+    #[tokio::test]
+    async fn maps_connection_certificate_to_claims() {
+        let ca = ca("Internal CA");
+        let cert = leaf(&ca);
+        let parts = request(|req| {
+            req.extensions_mut()
+                .insert(PeerCertificates(vec![cert.der().to_vec()]));
+        });
+
+        let auth = ClientCertAuth::builder().build().unwrap();
+        let claims = auth.authenticate(&parts).await.unwrap();
+        assert_eq!(claims.principal, "billing");
+        assert_eq!(claims.dns_names, vec!["billing.internal"]);
+        assert_eq!(claims.issuer, "CN=Internal CA");
+        assert_eq!(claims.fingerprint.len(), 64);
+
+        let auth = ClientCertAuth::builder()
+            .identity(CertIdentity::UriSan)
+            .build()
+            .unwrap();
+        let claims = auth.authenticate(&parts).await.unwrap();
+        assert_eq!(claims.principal, "spiffe://example.org/billing");
+    }
+
+    #[tokio::test]
+    async fn forwarded_header_only_from_trusted_proxy() {
+        let ca = ca("Internal CA");
+        let cert = leaf(&ca);
+        let auth = ClientCertAuth::builder()
+            .forwarded_header("x-ssl-client-cert")
+            .trusted_proxy("10.0.0.0/8")
+            .build()
+            .unwrap();
+
+        let parts = from_proxy("10.1.2.3:4000", &escaped(&cert.pem()));
+        assert_eq!(
+            auth.authenticate(&parts).await.unwrap().principal,
+            "billing"
+        );
+
+        let parts = from_proxy("192.168.0.9:4000", &escaped(&cert.pem()));
+        assert_eq!(
+            auth.authenticate(&parts).await.unwrap_err(),
+            AuthError::MissingCredentials
+        );
+
+        let xfcc = format!("Hash=abc;Cert=\"{}\";URI=spiffe://x", escaped(&cert.pem()));
+        let parts = from_proxy("10.1.2.3:4000", &xfcc);
+        assert_eq!(
+            auth.authenticate(&parts).await.unwrap().principal,
+            "billing"
+        );
+    }
+
+    #[tokio::test]
+    async fn requires_trusted_ca() {
+        let trusted = ca("Internal CA");
+        let other = ca("Internal CA");
+        let auth = ClientCertAuth::builder()
+            .trusted_ca_pem(trusted.cert.pem())
+            .build()
+            .unwrap();
+
+        let parts = request(|req| {
+            req.extensions_mut()
+                .insert(PeerCertificates(vec![leaf(&trusted).der().to_vec()]));
+        });
+        assert!(auth.authenticate(&parts).await.is_ok());
+
+        // Same issuer name, different key.
+        let parts = request(|req| {
+            req.extensions_mut()
+                .insert(PeerCertificates(vec![leaf(&other).der().to_vec()]));
+        });
+        assert!(matches!(
+            auth.authenticate(&parts).await,
+            Err(AuthError::InvalidToken(_))
+        ));
+    }
+
+    #[test]
+    fn cidr_matching() {
+        let net = Cidr::parse("10.0.0.0/8").unwrap();
+        assert!(net.contains("10.255.0.1".parse().unwrap()));
+        assert!(net.contains("::ffff:10.0.0.1".parse().unwrap()));
+        assert!(!net.contains("11.0.0.1".parse().unwrap()));
+        assert!(
+            Cidr::parse("0.0.0.0/0")
+                .unwrap()
+                .contains("8.8.8.8".parse().unwrap())
+        );
+        assert!(Cidr::parse("::1").unwrap().contains("::1".parse().unwrap()));
+        assert!(Cidr::parse("10.0.0.0/33").is_err());
+        assert!(
+            ClientCertAuth::builder()
+                .forwarded_header("x-ssl-client-cert")
+                .build()
+                .is_err()
+        );
+    }
+}
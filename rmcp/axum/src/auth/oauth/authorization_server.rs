@@ -0,0 +1,123 @@
+//! OAuth 2.0 Authorization Server Metadata (RFC 8414).
+//!
+//! Many MCP clients look for `/.well-known/oauth-authorization-server` on the
+//! MCP server's own origin instead of following RFC 9728 discovery. This
+//! module provides the metadata type and a router that serves a static
+//! document; see [`AuthorizationServerProxy`](super::AuthorizationServerProxy)
+//! (feature `oauth-proxy`) to mirror an upstream identity provider instead.
+//!
+//! ```rust,ignore
+//! use rmcp_axum::auth::oauth::{AuthorizationServerMetadata, authorization_server_router};
+//!
+//! let metadata = AuthorizationServerMetadata {
+//!     issuer: "https://auth.example.com".into(),
+//!     authorization_endpoint: Some("https://auth.example.com/authorize".into()),
+//!     token_endpoint: Some("https://auth.example.com/token".into()),
+//!     response_types_supported: vec!["code".into()],
+//!     code_challenge_methods_supported: Some(vec!["S256".into()]),
+//!     ..Default::default()
+//! };
+//!
+//! let app = axum::Router::new()
+//!     .nest_service("/mcp", mcp_service)
+//!     .merge(authorization_server_router(metadata));
+//! ```
+
+use axum::{Json, response::IntoResponse};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Well-known path for Authorization Server Metadata (RFC 8414 §3).
+pub(crate) const AUTHORIZATION_SERVER_WELL_KNOWN: &str = "/.well-known/oauth-authorization-server";
+
+/// OAuth 2.0 Authorization Server Metadata ([RFC 8414](https://datatracker.ietf.org/doc/html/rfc8414)).
+///
+/// Fields not modelled here are preserved in [`extra`](Self::extra), so a
+/// proxied document round-trips unchanged.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct AuthorizationServerMetadata {
+    /// The authorization server's issuer identifier.
+    pub issuer: String,
+
+    /// URL of the authorization endpoint.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub authorization_endpoint: Option<String>,
+
+    /// URL of the token endpoint.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_endpoint: Option<String>,
+
+    /// URL of the JSON Web Key Set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jwks_uri: Option<String>,
+
+    /// URL of the dynamic client registration endpoint (RFC 7591).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub registration_endpoint: Option<String>,
+
+    /// Scopes supported by this authorization server.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scopes_supported: Option<Vec<String>>,
+
+    /// `response_type` values supported (e.g., `["code"]`).
+    #[serde(default)]
+    pub response_types_supported: Vec<String>,
+
+    /// `grant_type` values supported.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grant_types_supported: Option<Vec<String>>,
+
+    /// Client authentication methods supported at the token endpoint.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_endpoint_auth_methods_supported: Option<Vec<String>>,
+
+    /// PKCE code challenge methods supported (MCP requires `S256`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code_challenge_methods_supported: Option<Vec<String>>,
+
+    /// URL of the token revocation endpoint (RFC 7009).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revocation_endpoint: Option<String>,
+
+    /// URL of the token introspection endpoint (RFC 7662).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub introspection_endpoint: Option<String>,
+
+    /// Any other metadata parameters.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Create an axum [`Router`](axum::Router) that serves Authorization Server
+/// Metadata at `/.well-known/oauth-authorization-server`.
+///
+/// Use this when the metadata is known up front; it is served as
+/// `application/json`.
+pub fn authorization_server_router(metadata: AuthorizationServerMetadata) -> axum::Router {
+    let metadata = Arc::new(metadata);
+    axum::Router::new().route(
+        AUTHORIZATION_SERVER_WELL_KNOWN,
+        axum::routing::get(move || {
+            let metadata = metadata.clone();
+            async move { Json(metadata.as_ref().clone()).into_response() }
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::auth::oauth::AuthorizationServerMetadata;
+
+    #[test]
+    fn preserves_unknown_fields() {
+        let json = serde_json::json!({
+            "issuer": "https://auth.example.com",
+            "response_types_supported": ["code"],
+            "userinfo_endpoint": "https://auth.example.com/userinfo",
+        });
+        let metadata: AuthorizationServerMetadata = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(metadata.issuer, "https://auth.example.com");
+        assert!(metadata.extra.contains_key("userinfo_endpoint"));
+        assert_eq!(serde_json::to_value(&metadata).unwrap(), json);
+    }
+}
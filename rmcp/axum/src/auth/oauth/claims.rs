@@ -0,0 +1,61 @@
+//! Claims produced by OAuth token validators.
+
+use serde::{Deserialize, Serialize};
+
+/// Standard OAuth 2.1 token claims.
+///
+/// Non-exhaustive, so new claims can be added; outside this crate, start
+/// from [`OAuthClaims::default`] and set the fields.
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub struct OAuthClaims {
+    /// The subject (user/client identifier).
+    pub sub: String,
+    /// The issuer.
+    pub iss: Option<String>,
+    /// The audience.
+    pub aud: Option<Vec<String>>,
+    /// Granted scopes (space-separated in the token, parsed here).
+    pub scope: Vec<String>,
+    /// Expiration time (seconds since epoch).
+    pub exp: Option<u64>,
+    /// Unique token identifier.
+    pub jti: Option<String>,
+    /// Identity-provider session the token was issued in.
+    pub sid: Option<String>,
+    /// Key confirmation for sender-constrained tokens.
+    pub cnf: Option<Confirmation>,
+    /// Tenant whose issuer signed the token, set by
+    /// [`MultiIssuerValidator`](crate::auth::jwt::MultiIssuerValidator).
+    pub tenant: Option<String>,
+}
+
+/// The `aud` claim, a single audience or a list
+/// ([RFC 7519 §4.1.3](https://datatracker.ietf.org/doc/html/rfc7519#section-4.1.3)).
+#[cfg(any(feature = "jwt", feature = "introspection"))]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum Audience {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+#[cfg(any(feature = "jwt", feature = "introspection"))]
+impl Audience {
+    pub(crate) fn into_vec(self) -> Vec<String> {
+        match self {
+            Audience::Single(s) => vec![s],
+            Audience::Multiple(v) => v,
+        }
+    }
+}
+
+/// The `cnf` (confirmation) claim of a sender-constrained token
+/// ([RFC 7800](https://datatracker.ietf.org/doc/html/rfc7800)).
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Confirmation {
+    /// JWK SHA-256 thumbprint of the DPoP key the token is bound to
+    /// ([RFC 9449 §6](https://datatracker.ietf.org/doc/html/rfc9449#section-6)).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jkt: Option<String>,
+}
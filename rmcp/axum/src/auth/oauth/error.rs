@@ -4,6 +4,7 @@
 //! [RFC 6750 §3](https://datatracker.ietf.org/doc/html/rfc6750#section-3) and
 //! the [MCP Authorization specification](https://modelcontextprotocol.io/specification/draft/basic/authorization).
 
+use crate::auth::AuthError;
 use http::HeaderValue;
 
 /// Configuration for an MCP server acting as an OAuth 2.1 resource server.
@@ -32,12 +33,12 @@ pub struct ResourceServerConfig {
 pub fn www_authenticate_401(config: &ResourceServerConfig) -> HeaderValue {
     let mut value = format!(
         "Bearer resource_metadata=\"{}\"",
-        config.resource_metadata_url,
+        quoted_string_safe(&config.resource_metadata_url),
     );
     if let Some(ref scope) = config.default_scope {
-        value.push_str(&format!(", scope=\"{scope}\""));
+        value.push_str(&format!(", scope=\"{}\"", quoted_string_safe(scope)));
     }
-    // Safe: we control the format and it's valid ASCII.
+    // Safe: every parameter is restricted to printable ASCII.
     HeaderValue::from_str(&value).expect("valid WWW-Authenticate header")
 }
 
@@ -50,12 +51,91 @@ pub fn www_authenticate_401(config: &ResourceServerConfig) -> HeaderValue {
 /// and the MCP authorization spec.
 pub fn www_authenticate_403(config: &ResourceServerConfig, required_scope: &str) -> HeaderValue {
     let value = format!(
-        "Bearer error=\"insufficient_scope\", scope=\"{required_scope}\", resource_metadata=\"{}\"",
-        config.resource_metadata_url,
+        "Bearer error=\"insufficient_scope\", scope=\"{}\", resource_metadata=\"{}\"",
+        quoted_string_safe(required_scope),
+        quoted_string_safe(&config.resource_metadata_url),
     );
     HeaderValue::from_str(&value).expect("valid WWW-Authenticate header")
 }
 
+/// Build a `WWW-Authenticate` header value describing an [`AuthError`].
+///
+/// Format: `Bearer [error="<code>", error_description="<text>", ][resource_metadata="<url>", ][scope="<scopes>"]`
+///
+/// [`AuthError::InvalidDpopProof`] uses the `DPoP` scheme instead.
+///
+/// The `error` parameters follow
+/// [RFC 6750 §3.1](https://datatracker.ietf.org/doc/html/rfc6750#section-3.1)
+/// and are omitted when no credentials were presented. `scope` is the
+/// required scope for `insufficient_scope` and the default scope otherwise.
+pub fn www_authenticate(config: Option<&ResourceServerConfig>, error: &AuthError) -> HeaderValue {
+    let mut params = Vec::new();
+    if let Some(code) = error.error_code() {
+        params.push(format!("error=\"{code}\""));
+        params.push(format!(
+            "error_description=\"{}\"",
+            quoted_string_safe(&error.description())
+        ));
+    }
+    if let Some(config) = config {
+        params.push(format!(
+            "resource_metadata=\"{}\"",
+            quoted_string_safe(&config.resource_metadata_url)
+        ));
+    }
+    let scope = match error {
+        AuthError::InsufficientScope { scope } => Some(scope.as_str()),
+        _ => config.and_then(|c| c.default_scope.as_deref()),
+    };
+    if let Some(scope) = scope {
+        params.push(format!("scope=\"{}\"", quoted_string_safe(scope)));
+    }
+
+    let scheme = match error {
+        AuthError::InvalidDpopProof(_) => "DPoP",
+        _ => "Bearer",
+    };
+    let value = if params.is_empty() {
+        scheme.to_string()
+    } else {
+        format!("{scheme} {}", params.join(", "))
+    };
+    HeaderValue::from_str(&value).expect("valid WWW-Authenticate header")
+}
+
+/// Build the error response for an [`AuthError`].
+///
+/// The response has the status from [`AuthError::status`], a
+/// `WWW-Authenticate` challenge from [`www_authenticate`], and a JSON-RPC
+/// style JSON body from [`AuthError::to_json`].
+pub fn auth_error_response(
+    config: Option<&ResourceServerConfig>,
+    error: &AuthError,
+) -> http::Response<axum::body::Body> {
+    http::Response::builder()
+        .status(error.status())
+        .header(
+            http::header::WWW_AUTHENTICATE,
+            www_authenticate(config, error),
+        )
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(axum::body::Body::from(error.to_json().to_string()))
+        .expect("valid response")
+}
+
+/// Restrict text to the characters RFC 6750 allows in quoted parameters
+/// (printable ASCII except `"` and `\`), so configured values can't break
+/// the header.
+fn quoted_string_safe(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '"' | '\\' => '\'',
+            ' '..='~' => c,
+            _ => '?',
+        })
+        .collect()
+}
+
 /// Build a 403 Forbidden response with the proper `WWW-Authenticate` header
 /// for insufficient scope errors.
 ///
@@ -74,12 +154,106 @@ pub fn insufficient_scope_response(
     config: &ResourceServerConfig,
     required_scope: &str,
 ) -> http::Response<axum::body::Body> {
-    http::Response::builder()
-        .status(http::StatusCode::FORBIDDEN)
-        .header(
-            http::header::WWW_AUTHENTICATE,
-            www_authenticate_403(config, required_scope),
-        )
-        .body(axum::body::Body::from("insufficient scope"))
-        .expect("valid response")
+    auth_error_response(
+        Some(config),
+        &AuthError::InsufficientScope {
+            scope: required_scope.into(),
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::auth::{
+        AuthError,
+        oauth::{
+            ResourceServerConfig, auth_error_response, www_authenticate, www_authenticate_401,
+            www_authenticate_403,
+        },
+    };
+
+    fn config() -> ResourceServerConfig {
+        ResourceServerConfig {
+            resource_metadata_url: "https://mcp.example.com/.well-known/oauth-protected-resource"
+                .into(),
+            default_scope: Some("mcp:tools".into()),
+        }
+    }
+
+    #[test]
+    fn missing_credentials_has_no_error_code() {
+        let header = www_authenticate(Some(&config()), &AuthError::MissingCredentials);
+        assert_eq!(
+            header,
+            "Bearer resource_metadata=\"https://mcp.example.com/.well-known/oauth-protected-resource\", scope=\"mcp:tools\""
+        );
+        assert_eq!(
+            www_authenticate(None, &AuthError::MissingCredentials),
+            "Bearer"
+        );
+    }
+
+    #[test]
+    fn invalid_token_includes_sanitized_description() {
+        let err = AuthError::InvalidToken("bad \"sig\"\n".into());
+        let header = www_authenticate(None, &err);
+        assert_eq!(
+            header,
+            "Bearer error=\"invalid_token\", error_description=\"bad 'sig'?\""
+        );
+    }
+
+    #[test]
+    fn invalid_dpop_proof_uses_dpop_scheme() {
+        let err = AuthError::InvalidDpopProof("stale proof".into());
+        assert_eq!(
+            www_authenticate(None, &err),
+            "DPoP error=\"invalid_dpop_proof\", error_description=\"stale proof\""
+        );
+    }
+
+    #[test]
+    fn insufficient_scope_names_required_scope() {
+        let err = AuthError::InsufficientScope {
+            scope: "files:write".into(),
+        };
+        let header = www_authenticate(Some(&config()), &err);
+        assert!(header.to_str().unwrap().ends_with("scope=\"files:write\""));
+    }
+
+    #[test]
+    fn configured_values_are_sanitized() {
+        let config = ResourceServerConfig {
+            resource_metadata_url: "https://mcp.example.com/\"meta\"".into(),
+            default_scope: Some("mcp:tools\r\nX-Injected: 1".into()),
+        };
+        let expected = "Bearer resource_metadata=\"https://mcp.example.com/'meta'\", scope=\"mcp:tools??X-Injected: 1\"";
+        assert_eq!(www_authenticate_401(&config), expected);
+        assert_eq!(
+            www_authenticate(Some(&config), &AuthError::MissingCredentials),
+            expected
+        );
+        assert_eq!(
+            www_authenticate_403(&config, "files:\u{e9}"),
+            "Bearer error=\"insufficient_scope\", scope=\"files:?\", resource_metadata=\"https://mcp.example.com/'meta'\""
+        );
+    }
+
+    #[tokio::test]
+    async fn response_carries_status_and_json_rpc_body() {
+        let resp = auth_error_response(None, &AuthError::InvalidRequest("bad header".into()));
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+        assert_eq!(
+            resp.headers()[http::header::CONTENT_TYPE],
+            "application/json"
+        );
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["error"]["code"], -32600);
+        assert_eq!(json["error"]["message"], "bad header");
+        assert_eq!(json["error"]["data"]["error"], "invalid_request");
+        assert!(json["id"].is_null());
+    }
 }
@@ -12,7 +12,7 @@
 //!     authorization_servers: vec!["https://auth.example.com".into()],
 //!     scopes_supported: Some(vec!["mcp:tools".into()]),
 //!     bearer_methods_supported: Some(vec!["header".into()]),
-//!     resource_documentation: None,
+//!     ..Default::default()
 //! };
 //!
 //! let app = axum::Router::new()
@@ -28,7 +28,7 @@ use std::sync::Arc;
 ///
 /// MCP servers MUST include the `authorization_servers` field containing at
 /// least one authorization server.
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct ProtectedResourceMetadata {
     /// The resource identifier — canonical URI of this MCP server.
     ///
@@ -51,6 +51,14 @@ pub struct ProtectedResourceMetadata {
     /// URL of the resource documentation.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub resource_documentation: Option<String>,
+
+    /// JWS algorithms accepted for DPoP proofs (RFC 9449).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dpop_signing_alg_values_supported: Option<Vec<String>>,
+
+    /// Whether only DPoP-bound access tokens are accepted (RFC 9449).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dpop_bound_access_tokens_required: Option<bool>,
 }
 
 /// Create an axum [`Router`](axum::Router) that serves the Protected Resource
@@ -10,6 +10,16 @@
 //! - **Spec-compliant error responses**: 401 and 403 responses with proper
 //!   `WWW-Authenticate` headers per [RFC 6750](https://datatracker.ietf.org/doc/html/rfc6750).
 //!
+//! - **Audience binding** ([RFC 8707](https://datatracker.ietf.org/doc/html/rfc8707)):
+//!   [`ProtectedResource`] canonicalizes the resource URI and lets
+//!   [`AuthLayer`](crate::auth::AuthLayer) reject tokens issued for other
+//!   resources.
+//!
+//! - **Authorization Server Metadata** ([RFC 8414](https://datatracker.ietf.org/doc/html/rfc8414)):
+//!   Serve `/.well-known/oauth-authorization-server` on the MCP server's
+//!   origin, statically or proxied from an upstream identity provider
+//!   together with dynamic client registration (feature `oauth-proxy`).
+//!
 //! # Example
 //!
 //! ```rust,ignore
@@ -23,7 +33,7 @@
 //!     authorization_servers: vec!["https://auth.example.com".into()],
 //!     scopes_supported: Some(vec!["mcp:tools".into()]),
 //!     bearer_methods_supported: Some(vec!["header".into()]),
-//!     resource_documentation: None,
+//!     ..Default::default()
 //! };
 //!
 //! let rs_config = ResourceServerConfig {
@@ -38,10 +48,29 @@
 //!     .layer(AuthLayer::new(BearerAuth::new(validator)).with_resource_server(rs_config));
 //! ```
 
+mod authorization_server;
+mod claims;
 mod error;
 mod metadata;
+mod resource;
+
+#[cfg(feature = "oauth-proxy")]
+mod proxy;
 
+#[cfg(feature = "authorization-server")]
+pub(crate) use authorization_server::AUTHORIZATION_SERVER_WELL_KNOWN;
+pub use authorization_server::{AuthorizationServerMetadata, authorization_server_router};
+#[cfg(any(feature = "jwt", feature = "introspection"))]
+pub(crate) use claims::Audience;
+pub use claims::{Confirmation, OAuthClaims};
 pub use error::{
-    ResourceServerConfig, insufficient_scope_response, www_authenticate_401, www_authenticate_403,
+    ResourceServerConfig, auth_error_response, insufficient_scope_response, www_authenticate,
+    www_authenticate_401, www_authenticate_403,
 };
 pub use metadata::{ProtectedResourceMetadata, metadata_router};
+pub use resource::{
+    AudienceClaims, InvalidResourceUri, ProtectedResource, ResourceBound, canonical_resource_uri,
+};
+
+#[cfg(feature = "oauth-proxy")]
+pub use proxy::{AuthorizationServerProxy, AuthorizationServerProxyBuilder};
@@ -0,0 +1,421 @@
+//! Authorization Server Metadata proxy with optional DCR passthrough.
+//!
+//! Mirrors an upstream identity provider's RFC 8414 metadata on the MCP
+//! server's origin, and optionally forwards RFC 7591 dynamic client
+//! registration to it. This unblocks clients that look for
+//! `/.well-known/oauth-authorization-server` on the MCP server itself
+//! instead of following RFC 9728 discovery.
+//!
+//! Requires the `oauth-proxy` feature.
+//!
+//! ```rust,ignore
+//! use rmcp_axum::auth::oauth::{AuthorizationServerProxy, metadata_router};
+//!
+//! let proxy = AuthorizationServerProxy::from_issuer("https://auth.example.com")
+//!     .registration_passthrough("https://mcp.example.com")
+//!     .build();
+//!
+//! let app = axum::Router::new()
+//!     .nest_service("/mcp", mcp_service)
+//!     .merge(metadata_router(metadata))
+//!     .merge(proxy.router());
+//! ```
+
+use super::{AuthorizationServerMetadata, authorization_server::AUTHORIZATION_SERVER_WELL_KNOWN};
+use anyhow::{Context, Result, anyhow, bail};
+use axum::{Json, body::Bytes, extract::State, response::IntoResponse};
+use http::{HeaderMap, StatusCode};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::sync::RwLock;
+
+/// Default time upstream metadata is cached.
+const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(300);
+
+/// Path of the local registration endpoint when passthrough is enabled.
+const REGISTER_PATH: &str = "/register";
+
+/// Builder for [`AuthorizationServerProxy`].
+pub struct AuthorizationServerProxyBuilder {
+    issuer: String,
+    public_url: Option<String>,
+    cache_ttl: Duration,
+    client: Option<reqwest::Client>,
+}
+
+impl AuthorizationServerProxyBuilder {
+    /// Forward dynamic client registration through this server.
+    ///
+    /// `public_url` is the MCP server's externally visible origin; the served
+    /// metadata advertises `<public_url>/register` as the
+    /// `registration_endpoint`, and requests to it are forwarded to the
+    /// upstream registration endpoint.
+    pub fn registration_passthrough(mut self, public_url: impl Into<String>) -> Self {
+        self.public_url = Some(public_url.into().trim_end_matches('/').to_owned());
+        self
+    }
+
+    /// How long upstream metadata is cached (default 5 minutes).
+    pub fn cache_ttl(mut self, ttl: Duration) -> Self {
+        self.cache_ttl = ttl;
+        self
+    }
+
+    /// Use a preconfigured HTTP client (timeouts, proxies, TLS roots).
+    pub fn http_client(mut self, client: reqwest::Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Build the proxy. Upstream metadata is fetched lazily.
+    pub fn build(self) -> AuthorizationServerProxy {
+        AuthorizationServerProxy {
+            inner: Arc::new(ProxyInner {
+                client: self.client.unwrap_or_default(),
+                issuer: self.issuer.trim_end_matches('/').to_owned(),
+                public_url: self.public_url,
+                cache_ttl: self.cache_ttl,
+                cached: RwLock::new(None),
+            }),
+        }
+    }
+}
+
+struct ProxyInner {
+    client: reqwest::Client,
+    issuer: String,
+    public_url: Option<String>,
+    cache_ttl: Duration,
+    cached: RwLock<Option<(Instant, AuthorizationServerMetadata)>>,
+}
+
+/// Serves an upstream authorization server's metadata on this origin.
+#[derive(Clone)]
+pub struct AuthorizationServerProxy {
+    inner: Arc<ProxyInner>,
+}
+
+impl AuthorizationServerProxy {
+    /// Start building a proxy for the given upstream issuer.
+    pub fn from_issuer(issuer: impl Into<String>) -> AuthorizationServerProxyBuilder {
+        AuthorizationServerProxyBuilder {
+            issuer: issuer.into(),
+            public_url: None,
+            cache_ttl: DEFAULT_CACHE_TTL,
+            client: None,
+        }
+    }
+
+    /// Router serving `/.well-known/oauth-authorization-server`, plus
+    /// `/register` when registration passthrough is enabled.
+    pub fn router(&self) -> axum::Router {
+        let mut router = axum::Router::new().route(
+            AUTHORIZATION_SERVER_WELL_KNOWN,
+            axum::routing::get(serve_metadata),
+        );
+        if self.inner.public_url.is_some() {
+            router = router.route(REGISTER_PATH, axum::routing::post(forward_registration));
+        }
+        router.with_state(self.clone())
+    }
+
+    /// Metadata as served to clients, fetching from upstream if the cache is
+    /// stale.
+    pub async fn metadata(&self) -> Result<AuthorizationServerMetadata> {
+        let mut metadata = self.upstream_metadata().await?;
+        if let Some(ref public_url) = self.inner.public_url
+            && metadata.registration_endpoint.is_some()
+        {
+            metadata.registration_endpoint = Some(format!("{public_url}{REGISTER_PATH}"));
+        }
+        Ok(metadata)
+    }
+
+    async fn upstream_metadata(&self) -> Result<AuthorizationServerMetadata> {
+        if let Some((fetched_at, ref metadata)) = *self.inner.cached.read().await
+            && fetched_at.elapsed() < self.inner.cache_ttl
+        {
+            return Ok(metadata.clone());
+        }
+
+        let metadata = self.fetch().await?;
+        *self.inner.cached.write().await = Some((Instant::now(), metadata.clone()));
+        Ok(metadata)
+    }
+
+    /// Fetch upstream metadata, trying RFC 8414 then OpenID Connect discovery.
+    async fn fetch(&self) -> Result<AuthorizationServerMetadata> {
+        let issuer = &self.inner.issuer;
+        let url = url::Url::parse(issuer).context("invalid issuer URL")?;
+        let origin = url.origin().ascii_serialization();
+        let path = url.path().trim_end_matches('/');
+        let candidates = [
+            format!("{origin}{AUTHORIZATION_SERVER_WELL_KNOWN}{path}"),
+            format!("{issuer}/.well-known/openid-configuration"),
+        ];
+
+        let mut last_err = anyhow!("no discovery URL tried");
+        for candidate in candidates {
+            match self.fetch_from(&candidate).await {
+                Ok(metadata) => return Ok(metadata),
+                Err(e) => last_err = e,
+            }
+        }
+        Err(last_err)
+    }
+
+    async fn fetch_from(&self, url: &str) -> Result<AuthorizationServerMetadata> {
+        let resp = self
+            .inner
+            .client
+            .get(url)
+            .send()
+            .await
+            .with_context(|| format!("failed to fetch {url}"))?;
+        if !resp.status().is_success() {
+            bail!("{url} returned {}", resp.status());
+        }
+        let metadata: AuthorizationServerMetadata = resp
+            .json()
+            .await
+            .with_context(|| format!("failed to parse metadata from {url}"))?;
+        // RFC 8414 §3.3: the issuer must be the one metadata was asked for.
+        if metadata.issuer.trim_end_matches('/') != self.inner.issuer {
+            bail!(
+                "{url} describes issuer {}, expected {}",
+                metadata.issuer,
+                self.inner.issuer
+            );
+        }
+        Ok(metadata)
+    }
+}
+
+async fn serve_metadata(State(proxy): State<AuthorizationServerProxy>) -> axum::response::Response {
+    match proxy.metadata().await {
+        Ok(metadata) => Json(metadata).into_response(),
+        Err(e) => upstream_error(e),
+    }
+}
+
+/// Forward a registration request to the upstream registration endpoint.
+///
+/// The body and the `Content-Type` and `Authorization` headers (for initial
+/// access tokens) are passed through; the upstream status, `Content-Type`
+/// and body are returned unchanged.
+async fn forward_registration(
+    State(proxy): State<AuthorizationServerProxy>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> axum::response::Response {
+    let endpoint = match proxy.upstream_metadata().await {
+        Ok(metadata) => metadata.registration_endpoint,
+        Err(e) => return upstream_error(e),
+    };
+    let Some(endpoint) = endpoint else {
+        return oauth_error(
+            StatusCode::NOT_FOUND,
+            "invalid_request",
+            "dynamic client registration is not supported",
+        );
+    };
+
+    let mut request = proxy.inner.client.post(&endpoint).body(body);
+    for name in [http::header::CONTENT_TYPE, http::header::AUTHORIZATION] {
+        if let Some(value) = headers.get(&name) {
+            request = request.header(name, value);
+        }
+    }
+    let resp = match request.send().await {
+        Ok(resp) => resp,
+        Err(e) => return upstream_error(anyhow!(e).context("registration request failed")),
+    };
+
+    let status = resp.status();
+    let content_type = resp.headers().get(http::header::CONTENT_TYPE).cloned();
+    let body = match resp.bytes().await {
+        Ok(body) => body,
+        Err(e) => return upstream_error(anyhow!(e).context("registration response failed")),
+    };
+    let mut response = (status, body).into_response();
+    if let Some(content_type) = content_type {
+        response
+            .headers_mut()
+            .insert(http::header::CONTENT_TYPE, content_type);
+    }
+    response
+}
+
+fn upstream_error(e: anyhow::Error) -> axum::response::Response {
+    oauth_error(StatusCode::BAD_GATEWAY, "server_error", &format!("{e:#}"))
+}
+
+fn oauth_error(status: StatusCode, error: &str, description: &str) -> axum::response::Response {
+    let body = serde_json::json!({
+        "error": error,
+        "error_description": description,
+    });
+    (status, Json(body)).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::auth::oauth::AuthorizationServerProxy;
+    use axum::{Json, Router, routing::get, routing::post};
+    use tower::ServiceExt;
+
+    /// Spawn a mock identity provider; returns its issuer URL.
+    async fn upstream() -> String {
+        upstream_claiming(|issuer| issuer.to_owned()).await
+    }
+
+    /// Like [`upstream`], with metadata naming `claimed(issuer)` as issuer.
+    async fn upstream_claiming(claimed: impl Fn(&str) -> String) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let issuer = format!("http://{}", listener.local_addr().unwrap());
+        let metadata = serde_json::json!({
+            "issuer": claimed(&issuer),
+            "authorization_endpoint": format!("{issuer}/authorize"),
+            "token_endpoint": format!("{issuer}/token"),
+            "registration_endpoint": format!("{issuer}/clients"),
+            "response_types_supported": ["code"],
+        });
+        let app = Router::new()
+            .route(
+                "/.well-known/oauth-authorization-server",
+                get(move || async move { Json(metadata) }),
+            )
+            .route(
+                "/clients",
+                post(
+                    |headers: http::HeaderMap, Json(body): Json<serde_json::Value>| async move {
+                        let auth = headers
+                            .get(http::header::AUTHORIZATION)
+                            .map(|v| v.to_str().unwrap().to_owned());
+                        (
+                            http::StatusCode::CREATED,
+                            Json(serde_json::json!({
+                                "client_id": "c-1",
+                                "redirect_uris": body["redirect_uris"],
+                                "initial_access_token": auth,
+                            })),
+                        )
+                    },
+                ),
+            );
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        issuer
+    }
+
+    async fn json(resp: http::Response<axum::body::Body>) -> serde_json::Value {
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn mirrors_upstream_metadata() {
+        let issuer = upstream().await;
+        let router = AuthorizationServerProxy::from_issuer(&issuer)
+            .build()
+            .router();
+        let resp = router
+            .oneshot(
+                http::Request::get("/.well-known/oauth-authorization-server")
+                    .body(axum::body::Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let metadata = json(resp).await;
+        assert_eq!(metadata["issuer"], issuer);
+        assert_eq!(
+            metadata["registration_endpoint"],
+            format!("{issuer}/clients")
+        );
+    }
+
+    #[tokio::test]
+    async fn forwards_dynamic_client_registration() {
+        let issuer = upstream().await;
+        let router = AuthorizationServerProxy::from_issuer(&issuer)
+            .registration_passthrough("https://mcp.example.com/")
+            .build()
+            .router();
+
+        let resp = router
+            .clone()
+            .oneshot(
+                http::Request::get("/.well-known/oauth-authorization-server")
+                    .body(axum::body::Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            json(resp).await["registration_endpoint"],
+            "https://mcp.example.com/register"
+        );
+
+        let resp = router
+            .oneshot(
+                http::Request::post("/register")
+                    .header(http::header::CONTENT_TYPE, "application/json")
+                    .header(http::header::AUTHORIZATION, "Bearer iat")
+                    .body(axum::body::Body::from(
+                        r#"{"redirect_uris":["http://localhost:3000/cb"]}"#,
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), http::StatusCode::CREATED);
+        let client = json(resp).await;
+        assert_eq!(client["client_id"], "c-1");
+        assert_eq!(client["redirect_uris"][0], "http://localhost:3000/cb");
+        assert_eq!(client["initial_access_token"], "Bearer iat");
+    }
+
+    #[tokio::test]
+    async fn rejects_metadata_of_other_issuers() {
+        let metadata = |issuer: &str| {
+            let router = AuthorizationServerProxy::from_issuer(issuer)
+                .build()
+                .router();
+            router.oneshot(
+                http::Request::get("/.well-known/oauth-authorization-server")
+                    .body(axum::body::Body::empty())
+                    .unwrap(),
+            )
+        };
+
+        let issuer = upstream_claiming(|issuer| format!("{issuer}/")).await;
+        let resp = metadata(&issuer).await.unwrap();
+        assert_eq!(resp.status(), http::StatusCode::OK);
+
+        let issuer = upstream_claiming(|_| "https://evil.example.com".into()).await;
+        let resp = metadata(&issuer).await.unwrap();
+        assert_eq!(resp.status(), http::StatusCode::BAD_GATEWAY);
+    }
+
+    #[tokio::test]
+    async fn reports_unreachable_upstream() {
+        let router = AuthorizationServerProxy::from_issuer("http://127.0.0.1:9")
+            .build()
+            .router();
+        let resp = router
+            .oneshot(
+                http::Request::get("/.well-known/oauth-authorization-server")
+                    .body(axum::body::Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), http::StatusCode::BAD_GATEWAY);
+        assert_eq!(json(resp).await["error"], "server_error");
+    }
+}
@@ -0,0 +1,382 @@
+//! Audience binding of access tokens to this resource server (RFC 8707).
+//!
+//! The MCP authorization spec requires servers to reject tokens that were not
+//! issued for them. [`ProtectedResource`] holds the canonical resource URI
+//! together with its [`ProtectedResourceMetadata`], and drives both the
+//! metadata endpoint and audience enforcement in
+//! [`AuthLayer`](crate::auth::AuthLayer), so the two can't drift apart.
+//!
+//! ```rust,ignore
+//! use rmcp_axum::auth::{AuthLayer, BearerAuth};
+//! use rmcp_axum::auth::oauth::{ProtectedResource, ProtectedResourceMetadata};
+//!
+//! let resource = ProtectedResource::new(ProtectedResourceMetadata {
+//!     resource: "https://MCP.example.com:443/mcp/".into(),
+//!     authorization_servers: vec!["https://auth.example.com".into()],
+//!     scopes_supported: Some(vec!["mcp:tools".into()]),
+//!     bearer_methods_supported: Some(vec!["header".into()]),
+//!     ..Default::default()
+//! })?
+//! .default_scope("mcp:tools");
+//!
+//! // Canonicalized to `https://mcp.example.com/mcp`.
+//! let app = axum::Router::new()
+//!     .nest_service("/mcp", mcp_service)
+//!     .merge(resource.router())
+//!     .layer(AuthLayer::new(BearerAuth::new(validator)).with_protected_resource(resource));
+//! ```
+
+use super::{OAuthClaims, ProtectedResourceMetadata, ResourceServerConfig, metadata_router};
+use crate::auth::{AuthError, Authenticator};
+use std::fmt;
+
+/// Well-known path suffix for Protected Resource Metadata (RFC 9728 §3).
+const WELL_KNOWN: &str = "/.well-known/oauth-protected-resource";
+
+/// Error returned for a resource URI that can't be canonicalized.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidResourceUri(String);
+
+impl fmt::Display for InvalidResourceUri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid resource URI: {}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidResourceUri {}
+
+/// Canonicalize a resource URI per RFC 8707 §2 and the MCP authorization
+/// spec.
+///
+/// - The URI must be absolute with an `http` or `https` scheme and a host.
+/// - Fragments are rejected.
+/// - Scheme and host are lowercased and default ports are dropped.
+/// - A trailing slash on the path is removed (`https://a.com/` becomes
+///   `https://a.com`, `https://a.com/mcp/` becomes `https://a.com/mcp`).
+pub fn canonical_resource_uri(uri: &str) -> Result<String, InvalidResourceUri> {
+    CanonicalUri::parse(uri).map(|c| c.to_string())
+}
+
+/// A resource URI split into its canonical components.
+struct CanonicalUri {
+    /// `scheme://host[:port]`
+    origin: String,
+    /// Path without a trailing slash; empty for the root.
+    path: String,
+    query: Option<String>,
+}
+
+impl CanonicalUri {
+    fn parse(uri: &str) -> Result<Self, InvalidResourceUri> {
+        let url = url::Url::parse(uri).map_err(|e| InvalidResourceUri(format!("{uri}: {e}")))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(InvalidResourceUri(format!("{uri}: scheme must be http(s)")));
+        }
+        let Some(host) = url.host_str() else {
+            return Err(InvalidResourceUri(format!("{uri}: missing host")));
+        };
+        if url.fragment().is_some() {
+            return Err(InvalidResourceUri(format!(
+                "{uri}: fragment is not allowed"
+            )));
+        }
+
+        // `Url` already lowercases scheme and host and drops default ports.
+        let mut origin = format!("{}://{host}", url.scheme());
+        if let Some(port) = url.port() {
+            origin.push_str(&format!(":{port}"));
+        }
+        Ok(Self {
+            origin,
+            path: url.path().trim_end_matches('/').to_owned(),
+            query: url.query().map(String::from),
+        })
+    }
+}
+
+impl fmt::Display for CanonicalUri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.origin, self.path)?;
+        if let Some(ref query) = self.query {
+            write!(f, "?{query}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Claims that carry a token audience.
+///
+/// Required by [`ResourceBound`] to check RFC 8707 audience binding.
+pub trait AudienceClaims {
+    /// The `aud` values of the token.
+    fn audiences(&self) -> &[String];
+}
+
+impl AudienceClaims for OAuthClaims {
+    fn audiences(&self) -> &[String] {
+        self.aud.as_deref().unwrap_or_default()
+    }
+}
+
+/// A protected resource with a canonical URI.
+///
+/// Serves its metadata with [`router`](Self::router) and, through
+/// [`AuthLayer::with_protected_resource`](crate::auth::AuthLayer::with_protected_resource),
+/// rejects tokens whose audience doesn't match.
+#[derive(Clone, Debug)]
+pub struct ProtectedResource {
+    metadata: ProtectedResourceMetadata,
+    origin: String,
+    path: String,
+    default_scope: Option<String>,
+}
+
+impl ProtectedResource {
+    /// Canonicalize `metadata.resource` and wrap the metadata.
+    pub fn new(mut metadata: ProtectedResourceMetadata) -> Result<Self, InvalidResourceUri> {
+        let canonical = CanonicalUri::parse(&metadata.resource)?;
+        metadata.resource = canonical.to_string();
+        Ok(Self {
+            metadata,
+            origin: canonical.origin,
+            path: canonical.path,
+            default_scope: None,
+        })
+    }
+
+    /// Default scopes to include in 401 `WWW-Authenticate` challenges.
+    pub fn default_scope(mut self, scope: impl Into<String>) -> Self {
+        self.default_scope = Some(scope.into());
+        self
+    }
+
+    /// The canonical resource URI.
+    pub fn resource(&self) -> &str {
+        &self.metadata.resource
+    }
+
+    /// The metadata document, with the canonical resource URI.
+    pub fn metadata(&self) -> &ProtectedResourceMetadata {
+        &self.metadata
+    }
+
+    /// Path of the metadata document on this server.
+    ///
+    /// Per RFC 9728 §3.1 the well-known segment is inserted between the host
+    /// and the resource path: `https://a.com/mcp` is described at
+    /// `/.well-known/oauth-protected-resource/mcp`.
+    pub fn metadata_path(&self) -> String {
+        format!("{WELL_KNOWN}{}", self.path)
+    }
+
+    /// Absolute URL of the metadata document.
+    pub fn metadata_url(&self) -> String {
+        format!("{}{}", self.origin, self.metadata_path())
+    }
+
+    /// Resource server config for `WWW-Authenticate` challenges.
+    pub fn resource_server_config(&self) -> ResourceServerConfig {
+        ResourceServerConfig {
+            resource_metadata_url: self.metadata_url(),
+            default_scope: self.default_scope.clone(),
+        }
+    }
+
+    /// Router serving the metadata at [`metadata_path`](Self::metadata_path).
+    ///
+    /// When the resource has a path, the document is also served at the root
+    /// well-known path for clients that don't do path insertion.
+    pub fn router(&self) -> axum::Router {
+        let path = self.metadata_path();
+        let router = metadata_router(self.metadata.clone());
+        if path == WELL_KNOWN {
+            return router;
+        }
+        let metadata = self.metadata.clone();
+        router.route(
+            &path,
+            axum::routing::get(move || {
+                let metadata = metadata.clone();
+                async move { axum::Json(metadata) }
+            }),
+        )
+    }
+
+    /// Whether any of `audiences` names this resource after canonicalization.
+    pub fn accepts_audience(&self, audiences: &[String]) -> bool {
+        audiences.iter().any(|aud| {
+            canonical_resource_uri(aud).is_ok_and(|canonical| canonical == self.resource())
+        })
+    }
+}
+
+/// Authenticator that rejects tokens not issued for a [`ProtectedResource`].
+///
+/// Created by [`AuthLayer::with_protected_resource`](crate::auth::AuthLayer::with_protected_resource).
+#[derive(Clone)]
+pub struct ResourceBound<A> {
+    authenticator: A,
+    resource: ProtectedResource,
+}
+
+impl<A> ResourceBound<A> {
+    pub fn new(authenticator: A, resource: ProtectedResource) -> Self {
+        Self {
+            authenticator,
+            resource,
+        }
+    }
+}
+
+impl<A> Authenticator for ResourceBound<A>
+where
+    A: Authenticator,
+    A::Claims: AudienceClaims,
+{
+    type Claims = A::Claims;
+    type Error = AuthError;
+
+    async fn authenticate(
+        &self,
+        parts: &http::request::Parts,
+    ) -> Result<Self::Claims, Self::Error> {
+        let claims = self
+            .authenticator
+            .authenticate(parts)
+            .await
+            .map_err(A::reject)?;
+        if !self.resource.accepts_audience(claims.audiences()) {
+            return Err(AuthError::InvalidToken(format!(
+                "token audience does not include {}",
+                self.resource.resource()
+            )));
+        }
+        Ok(claims)
+    }
+
+    fn reject(error: AuthError) -> AuthError {
+        error
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::auth::{
+        AuthLayer, Authenticator,
+        oauth::{
+            OAuthClaims, ProtectedResource, ProtectedResourceMetadata, canonical_resource_uri,
+        },
+    };
+    use tower::ServiceExt;
+
+    fn resource(uri: &str) -> ProtectedResource {
+        ProtectedResource::new(ProtectedResourceMetadata {
+            resource: uri.into(),
+            authorization_servers: vec!["https://auth.example.com".into()],
+            scopes_supported: None,
+            bearer_methods_supported: None,
+            ..Default::default()
+        })
+        .expect("valid resource")
+    }
+
+    /// Authenticator yielding a fixed audience.
+    #[derive(Clone)]
+    struct FixedAudience(&'static str);
+
+    impl Authenticator for FixedAudience {
+        type Claims = OAuthClaims;
+        type Error = String;
+
+        async fn authenticate(&self, _: &http::request::Parts) -> Result<OAuthClaims, String> {
+            Ok(OAuthClaims {
+                sub: "user".into(),
+                aud: Some(vec![self.0.into()]),
+                ..Default::default()
+            })
+        }
+    }
+
+    #[test]
+    fn canonicalizes_resource_uris() {
+        for (input, expected) in [
+            ("https://MCP.Example.com", "https://mcp.example.com"),
+            ("https://mcp.example.com/", "https://mcp.example.com"),
+            (
+                "HTTPS://mcp.example.com:443/mcp/",
+                "https://mcp.example.com/mcp",
+            ),
+            ("http://localhost:8080/mcp", "http://localhost:8080/mcp"),
+            (
+                "https://mcp.example.com/mcp?v=1",
+                "https://mcp.example.com/mcp?v=1",
+            ),
+        ] {
+            assert_eq!(canonical_resource_uri(input).as_deref(), Ok(expected));
+        }
+        assert!(canonical_resource_uri("https://mcp.example.com#frag").is_err());
+        assert!(canonical_resource_uri("mcp.example.com").is_err());
+        assert!(canonical_resource_uri("ftp://mcp.example.com").is_err());
+    }
+
+    #[test]
+    fn derives_metadata_location_from_resource() {
+        let root = resource("https://mcp.example.com/");
+        assert_eq!(
+            root.metadata_url(),
+            "https://mcp.example.com/.well-known/oauth-protected-resource"
+        );
+
+        let nested = resource("https://mcp.example.com:8443/mcp?v=1");
+        assert_eq!(
+            nested.metadata_path(),
+            "/.well-known/oauth-protected-resource/mcp"
+        );
+        assert_eq!(
+            nested.resource_server_config().resource_metadata_url,
+            "https://mcp.example.com:8443/.well-known/oauth-protected-resource/mcp"
+        );
+    }
+
+    #[test]
+    fn matches_normalized_audiences() {
+        let res = resource("https://mcp.example.com/mcp");
+        assert!(res.accepts_audience(&["https://MCP.example.com/mcp/".into()]));
+        assert!(!res.accepts_audience(&["https://mcp.example.com".into()]));
+        assert!(!res.accepts_audience(&[]));
+    }
+
+    #[tokio::test]
+    async fn layer_rejects_foreign_audience() {
+        let res = resource("https://mcp.example.com/mcp");
+        let app = |aud| {
+            axum::Router::new()
+                .route("/mcp", axum::routing::get(|| async { "ok" }))
+                .merge(res.router())
+                .layer(AuthLayer::new(FixedAudience(aud)).with_protected_resource(res.clone()))
+        };
+        let request = || {
+            http::Request::get("/mcp")
+                .body(axum::body::Body::empty())
+                .unwrap()
+        };
+
+        let ok = app("https://mcp.example.com/mcp/")
+            .oneshot(request())
+            .await
+            .unwrap();
+        assert_eq!(ok.status(), http::StatusCode::OK);
+
+        let denied = app("https://other.example.com")
+            .oneshot(request())
+            .await
+            .unwrap();
+        assert_eq!(denied.status(), http::StatusCode::UNAUTHORIZED);
+        let challenge = denied.headers()[http::header::WWW_AUTHENTICATE]
+            .to_str()
+            .unwrap();
+        assert!(challenge.contains(
+            "resource_metadata=\"https://mcp.example.com/.well-known/oauth-protected-resource/mcp\""
+        ));
+    }
+}
@@ -0,0 +1,47 @@
+//! Stable identity of an authenticated caller.
+
+use super::{Either, oauth::OAuthClaims};
+
+/// Claims that identify the caller, for keying per-principal state such as
+/// rate limits and audit records.
+pub trait Principal {
+    /// Identifier of the caller, e.g. the token subject or API key id.
+    fn principal(&self) -> &str;
+}
+
+impl Principal for String {
+    fn principal(&self) -> &str {
+        self
+    }
+}
+
+impl Principal for OAuthClaims {
+    fn principal(&self) -> &str {
+        &self.sub
+    }
+}
+
+/// Keyed by the key id, so each key gets its own limits even when several
+/// act as the same subject.
+#[cfg(feature = "api-key")]
+impl Principal for super::api_key::ApiKeyClaims {
+    fn principal(&self) -> &str {
+        &self.key_id
+    }
+}
+
+#[cfg(feature = "mtls")]
+impl Principal for super::mtls::CertificateClaims {
+    fn principal(&self) -> &str {
+        &self.principal
+    }
+}
+
+impl<L: Principal, R: Principal> Principal for Either<L, R> {
+    fn principal(&self) -> &str {
+        match self {
+            Either::Left(l) => l.principal(),
+            Either::Right(r) => r.principal(),
+        }
+    }
+}
@@ -0,0 +1,508 @@
+//! Revocation of credentials before they expire.
+//!
+//! [`AuthLayer::with_revocation`](crate::auth::AuthLayer::with_revocation)
+//! rejects tokens whose `jti`, subject or session id appears in a
+//! [`RevocationStore`], so a leaked token or a departed user stops working
+//! immediately rather than at `exp`. [`revocation_router`] adds entries over
+//! HTTP, and [`McpServerBuilder::revocations`](crate::server::McpServerBuilder::revocations)
+//! also terminates the revoked caller's open MCP sessions.
+//!
+//! ```rust,ignore
+//! use rmcp_axum::auth::revocation::{FileRevocations, revocation_router};
+//!
+//! let revocations = Arc::new(FileRevocations::open("/var/lib/mcp/revoked.jsonl")?);
+//!
+//! let app = axum::Router::new()
+//!     .nest_service("/mcp", mcp_service)
+//!     .layer(AuthLayer::new(BearerAuth::new(jwt)).with_revocation(revocations.clone()))
+//!     .nest(
+//!         "/admin",
+//!         revocation_router(revocations).layer(AuthLayer::new(admin_auth)),
+//!     );
+//! ```
+
+use super::{AuthError, Authenticator, Either, oauth::OAuthClaims};
+use axum::{Json, extract::State, routing::post};
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, Once, RwLock, Weak},
+    time::{Duration, SystemTime},
+};
+
+/// Path of the admin route served by [`revocation_router`].
+pub const REVOCATIONS_PATH: &str = "/revocations";
+
+/// A revoked token, principal or session.
+///
+/// Serialized as a single-key object, e.g. `{"sub": "alice"}`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Revocation {
+    /// A single token, by its `jti` claim.
+    Jti(String),
+    /// Every token of a subject.
+    Sub(String),
+    /// Every token of an identity-provider session, by its `sid` claim.
+    Sid(String),
+}
+
+/// Claims that can be matched against [`Revocation`]s.
+pub trait RevocableClaims {
+    /// The token identifier (`jti`).
+    fn jti(&self) -> Option<&str> {
+        None
+    }
+
+    /// The subject (`sub`).
+    fn sub(&self) -> Option<&str> {
+        None
+    }
+
+    /// The identity-provider session id (`sid`).
+    fn sid(&self) -> Option<&str> {
+        None
+    }
+
+    /// Every revocation that would reject these claims.
+    fn revocations(&self) -> Vec<Revocation> {
+        let jti = self.jti().map(|v| Revocation::Jti(v.into()));
+        let sub = self.sub().map(|v| Revocation::Sub(v.into()));
+        let sid = self.sid().map(|v| Revocation::Sid(v.into()));
+        [jti, sub, sid].into_iter().flatten().collect()
+    }
+}
+
+/// Plain string claims are taken as the subject.
+impl RevocableClaims for String {
+    fn sub(&self) -> Option<&str> {
+        Some(self)
+    }
+}
+
+impl RevocableClaims for OAuthClaims {
+    fn jti(&self) -> Option<&str> {
+        self.jti.as_deref()
+    }
+
+    fn sub(&self) -> Option<&str> {
+        Some(&self.sub)
+    }
+
+    fn sid(&self) -> Option<&str> {
+        self.sid.as_deref()
+    }
+}
+
+#[cfg(feature = "api-key")]
+impl RevocableClaims for super::api_key::ApiKeyClaims {
+    fn sub(&self) -> Option<&str> {
+        Some(&self.subject)
+    }
+}
+
+impl<L: RevocableClaims, R: RevocableClaims> RevocableClaims for Either<L, R> {
+    fn jti(&self) -> Option<&str> {
+        match self {
+            Either::Left(l) => l.jti(),
+            Either::Right(r) => r.jti(),
+        }
+    }
+
+    fn sub(&self) -> Option<&str> {
+        match self {
+            Either::Left(l) => l.sub(),
+            Either::Right(r) => r.sub(),
+        }
+    }
+
+    fn sid(&self) -> Option<&str> {
+        match self {
+            Either::Left(l) => l.sid(),
+            Either::Right(r) => r.sid(),
+        }
+    }
+}
+
+/// Storage for revocations.
+///
+/// Implement this to share revocations between replicas, e.g. in Redis.
+pub trait RevocationStore: Send + Sync + 'static {
+    /// Whether `revocation` has been recorded.
+    fn is_revoked(&self, revocation: &Revocation) -> impl Future<Output = bool> + Send;
+
+    /// Record `revocation`.
+    fn revoke(&self, revocation: Revocation) -> impl Future<Output = io::Result<()>> + Send;
+}
+
+/// Revocations held in process memory, lost on restart.
+#[derive(Debug, Default)]
+pub struct InMemoryRevocations {
+    revoked: RwLock<HashSet<Revocation>>,
+}
+
+impl InMemoryRevocations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl RevocationStore for InMemoryRevocations {
+    async fn is_revoked(&self, revocation: &Revocation) -> bool {
+        self.revoked
+            .read()
+            .expect("revocations poisoned")
+            .contains(revocation)
+    }
+
+    async fn revoke(&self, revocation: Revocation) -> io::Result<()> {
+        self.revoked
+            .write()
+            .expect("revocations poisoned")
+            .insert(revocation);
+        Ok(())
+    }
+}
+
+/// Revocations persisted to a JSON Lines file, one [`Revocation`] per line.
+///
+/// A background thread reloads the file when its modification time changes,
+/// checked every [`poll_interval`](Self::poll_interval), so entries written
+/// by other processes or by hand take effect without a restart. Lookups only
+/// read the loaded set. Malformed lines are skipped with a warning.
+#[derive(Debug)]
+pub struct FileRevocations {
+    shared: Arc<FileShared>,
+    poll_interval: Duration,
+    /// Starts the watcher thread on first use, once configured.
+    watcher: Once,
+}
+
+#[derive(Debug)]
+struct FileShared {
+    path: PathBuf,
+    /// The loaded revocations, replaced whole by reloads.
+    revoked: RwLock<HashSet<Revocation>>,
+    /// Modification time of the loaded file. Held while appending or
+    /// reloading, so a reload can't drop a concurrent revocation.
+    modified: Mutex<Option<SystemTime>>,
+}
+
+impl FileRevocations {
+    /// Load revocations from `path`, creating the file if needed.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_owned();
+        File::options().create(true).append(true).open(&path)?;
+        let (revoked, modified) = load(&path)?;
+        Ok(Self {
+            shared: Arc::new(FileShared {
+                path,
+                revoked: RwLock::new(revoked),
+                modified: Mutex::new(modified),
+            }),
+            poll_interval: Duration::from_secs(1),
+            watcher: Once::new(),
+        })
+    }
+
+    /// How often to check the file for changes. Zero disables reloading.
+    /// Defaults to one second.
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// The loaded revocations, watching the file from now on.
+    fn shared(&self) -> &FileShared {
+        self.watcher.call_once(|| {
+            if self.poll_interval.is_zero() {
+                return;
+            }
+            let shared = Arc::downgrade(&self.shared);
+            let interval = self.poll_interval;
+            let spawned = std::thread::Builder::new()
+                .name("revocations".into())
+                .spawn(move || watch(shared, interval));
+            if let Err(e) = spawned {
+                tracing::error!("failed to watch {}: {e}", self.shared.path.display());
+            }
+        });
+        &self.shared
+    }
+}
+
+/// Reload the file behind `shared` whenever it changes, until the store is
+/// dropped.
+fn watch(shared: Weak<FileShared>, interval: Duration) {
+    loop {
+        std::thread::sleep(interval);
+        match shared.upgrade() {
+            Some(shared) => shared.reload_if_changed(),
+            None => return,
+        }
+    }
+}
+
+impl FileShared {
+    fn reload_if_changed(&self) {
+        let mut loaded = self.modified.lock().expect("revocations poisoned");
+        let modified = std::fs::metadata(&self.path)
+            .and_then(|m| m.modified())
+            .ok();
+        if modified == *loaded {
+            return;
+        }
+        match load(&self.path) {
+            Ok((revoked, modified)) => {
+                let previous = std::mem::replace(
+                    &mut *self.revoked.write().expect("revocations poisoned"),
+                    revoked,
+                );
+                drop(previous);
+                *loaded = modified;
+            }
+            Err(e) => tracing::error!("failed to reload {}: {e}", self.path.display()),
+        }
+    }
+}
+
+fn load(path: &Path) -> io::Result<(HashSet<Revocation>, Option<SystemTime>)> {
+    let file = File::open(path)?;
+    let modified = file.metadata()?.modified().ok();
+    let mut revoked = HashSet::new();
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line) {
+            Ok(revocation) => {
+                revoked.insert(revocation);
+            }
+            Err(e) => tracing::warn!("skipping revocation {line:?} in {}: {e}", path.display()),
+        }
+    }
+    Ok((revoked, modified))
+}
+
+impl RevocationStore for FileRevocations {
+    async fn is_revoked(&self, revocation: &Revocation) -> bool {
+        self.shared()
+            .revoked
+            .read()
+            .expect("revocations poisoned")
+            .contains(revocation)
+    }
+
+    async fn revoke(&self, revocation: Revocation) -> io::Result<()> {
+        let mut line = serde_json::to_vec(&revocation).expect("revocation serializes");
+        line.push(b'\n');
+        let shared = self.shared();
+        let _file = shared.modified.lock().expect("revocations poisoned");
+        File::options()
+            .append(true)
+            .open(&shared.path)?
+            .write_all(&line)?;
+        shared
+            .revoked
+            .write()
+            .expect("revocations poisoned")
+            .insert(revocation);
+        Ok(())
+    }
+}
+
+/// Authenticator that rejects revoked credentials.
+///
+/// Created by [`AuthLayer::with_revocation`](crate::auth::AuthLayer::with_revocation).
+pub struct Revocable<A, R> {
+    authenticator: A,
+    store: Arc<R>,
+}
+
+impl<A: Clone, R> Clone for Revocable<A, R> {
+    fn clone(&self) -> Self {
+        Self {
+            authenticator: self.authenticator.clone(),
+            store: self.store.clone(),
+        }
+    }
+}
+
+impl<A, R> Revocable<A, R> {
+    pub fn new(authenticator: A, store: Arc<R>) -> Self {
+        Self {
+            authenticator,
+            store,
+        }
+    }
+}
+
+impl<A, R> Authenticator for Revocable<A, R>
+where
+    A: Authenticator,
+    A::Claims: RevocableClaims,
+    R: RevocationStore,
+{
+    type Claims = A::Claims;
+    type Error = AuthError;
+
+    async fn authenticate(
+        &self,
+        parts: &http::request::Parts,
+    ) -> Result<Self::Claims, Self::Error> {
+        let claims = self
+            .authenticator
+            .authenticate(parts)
+            .await
+            .map_err(A::reject)?;
+        for revocation in claims.revocations() {
+            if self.store.is_revoked(&revocation).await {
+                return Err(AuthError::InvalidToken("token has been revoked".into()));
+            }
+        }
+        Ok(claims)
+    }
+
+    fn reject(error: AuthError) -> AuthError {
+        error
+    }
+}
+
+/// Router with a `POST /revocations` admin route that records the
+/// [`Revocation`] in the JSON body and answers 204.
+///
+/// The route is unauthenticated; nest it behind an admin-only
+/// [`AuthLayer`](crate::auth::AuthLayer).
+pub fn revocation_router<R: RevocationStore>(store: Arc<R>) -> axum::Router {
+    axum::Router::new()
+        .route(REVOCATIONS_PATH, post(revoke::<R>))
+        .with_state(store)
+}
+
+async fn revoke<R: RevocationStore>(
+    State(store): State<Arc<R>>,
+    Json(revocation): Json<Revocation>,
+) -> StatusCode {
+    match store.revoke(revocation).await {
+        Ok(()) => StatusCode::NO_CONTENT,
+        Err(e) => {
+            tracing::error!("failed to record revocation: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        FileRevocations, InMemoryRevocations, Revocation, RevocationStore, revocation_router,
+    };
+    use crate::auth::{AuthLayer, Authenticator, oauth::OAuthClaims};
+    use axum::{Router, body::Body, routing::post};
+    use http::{Request, StatusCode, header};
+    use std::{io::Write, sync::Arc, time::Duration};
+    use tower::ServiceExt;
+
+    /// Authenticator yielding the `x-sub` header as subject and `x-jti` as
+    /// token id.
+    #[derive(Clone)]
+    struct Header;
+
+    impl Authenticator for Header {
+        type Claims = OAuthClaims;
+        type Error = String;
+
+        async fn authenticate(&self, parts: &http::request::Parts) -> Result<OAuthClaims, String> {
+            let header = |name| {
+                parts
+                    .headers
+                    .get(name)
+                    .and_then(|v| v.to_str().ok())
+                    .map(String::from)
+            };
+            Ok(OAuthClaims {
+                sub: header("x-sub").ok_or("missing subject")?,
+                jti: header("x-jti"),
+                ..Default::default()
+            })
+        }
+    }
+
+    fn request(sub: &str, jti: &str) -> Request<Body> {
+        Request::post("/mcp")
+            .header("x-sub", sub)
+            .header("x-jti", jti)
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    async fn status(app: &Router, req: Request<Body>) -> StatusCode {
+        app.clone().oneshot(req).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn rejects_revoked_subjects_and_tokens() {
+        let store = Arc::new(InMemoryRevocations::new());
+        let app = Router::new()
+            .route("/mcp", post(|| async { "ok" }))
+            .layer(AuthLayer::new(Header).with_revocation(store.clone()))
+            .merge(revocation_router(store.clone()));
+
+        assert_eq!(status(&app, request("alice", "t1")).await, StatusCode::OK);
+
+        let revoke = Request::post("/revocations")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"sub":"alice"}"#))
+            .unwrap();
+        assert_eq!(status(&app, revoke).await, StatusCode::NO_CONTENT);
+        assert_eq!(
+            status(&app, request("alice", "t2")).await,
+            StatusCode::UNAUTHORIZED
+        );
+
+        store.revoke(Revocation::Jti("t3".into())).await.unwrap();
+        assert_eq!(
+            status(&app, request("bob", "t3")).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(status(&app, request("bob", "t4")).await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn file_store_reloads_external_edits() {
+        let path = std::env::temp_dir().join(format!("revocations-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let store = FileRevocations::open(&path)
+            .unwrap()
+            .poll_interval(Duration::from_millis(10));
+        let alice = Revocation::Sub("alice".into());
+        let bob = Revocation::Sub("bob".into());
+
+        store.revoke(alice.clone()).await.unwrap();
+        assert!(store.is_revoked(&alice).await);
+
+        // Written by another process; the mtime has to move for the reload.
+        std::thread::sleep(Duration::from_millis(20));
+        let mut file = std::fs::File::options().append(true).open(&path).unwrap();
+        writeln!(file, "not json\n{{\"sub\":\"bob\"}}").unwrap();
+        drop(file);
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !store.is_revoked(&bob).await {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("reloaded in the background");
+        assert!(store.is_revoked(&alice).await);
+
+        let reopened = FileRevocations::open(&path).unwrap();
+        assert!(reopened.is_revoked(&alice).await);
+        assert!(reopened.is_revoked(&bob).await);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
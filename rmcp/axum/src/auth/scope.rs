@@ -0,0 +1,37 @@
+//! Scopes granted to an authenticated caller.
+
+use super::{Either, oauth::OAuthClaims};
+
+/// Claims that carry granted OAuth scopes, for per-tool authorization such
+/// as [`ToolRouterExt::require_scope`](crate::tool::ToolRouterExt::require_scope).
+pub trait ScopedClaims {
+    /// The granted scopes.
+    fn scopes(&self) -> &[String];
+
+    /// Whether `scope` was granted.
+    fn has_scope(&self, scope: &str) -> bool {
+        self.scopes().iter().any(|s| s == scope)
+    }
+}
+
+impl ScopedClaims for OAuthClaims {
+    fn scopes(&self) -> &[String] {
+        &self.scope
+    }
+}
+
+#[cfg(feature = "api-key")]
+impl ScopedClaims for super::api_key::ApiKeyClaims {
+    fn scopes(&self) -> &[String] {
+        &self.scope
+    }
+}
+
+impl<L: ScopedClaims, R: ScopedClaims> ScopedClaims for Either<L, R> {
+    fn scopes(&self) -> &[String] {
+        match self {
+            Either::Left(l) => l.scopes(),
+            Either::Right(r) => r.scopes(),
+        }
+    }
+}
@@ -0,0 +1,773 @@
+//! HTTP endpoints of the embedded authorization server.
+
+use super::{
+    AuthorizationRequest, AuthorizationServer, CODE_TTL, Consent, ConsentHandler,
+    UNUSED_CLIENT_TTL, expired,
+};
+use crate::auth::oauth::canonical_resource_uri;
+use axum::{
+    Form, Json,
+    extract::{Query, Request, State},
+    response::{IntoResponse, Redirect, Response},
+};
+use base64::{
+    Engine,
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+};
+use http::{HeaderMap, StatusCode};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{collections::HashMap, time::Instant};
+
+pub(super) const AUTHORIZE_PATH: &str = "/authorize";
+pub(super) const TOKEN_PATH: &str = "/token";
+pub(super) const REGISTER_PATH: &str = "/register";
+pub(super) const JWKS_PATH: &str = "/jwks.json";
+
+/// A registered client.
+pub(super) struct Client {
+    name: Option<String>,
+    redirect_uris: Vec<String>,
+    /// SHA-256 of the client secret; `None` for public clients.
+    secret_hash: Option<[u8; 32]>,
+    registered_at: Instant,
+    /// Whether the client redeemed a code.
+    used: bool,
+}
+
+/// An issued authorization code awaiting redemption.
+pub(super) struct PendingCode {
+    client_id: String,
+    redirect_uri: String,
+    code_challenge: String,
+    subject: String,
+    scope: Vec<String>,
+    resource: Option<String>,
+    created_at: Instant,
+}
+
+/// Client metadata submitted for registration (RFC 7591 §2).
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ClientRegistration {
+    /// Allowed redirect URIs.
+    pub redirect_uris: Vec<String>,
+    /// Human-readable client name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_name: Option<String>,
+    /// `none` for public clients, or `client_secret_basic` /
+    /// `client_secret_post` for confidential ones.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_endpoint_auth_method: Option<String>,
+}
+
+/// Registration response (RFC 7591 §3.2.1).
+#[derive(Clone, Debug, Serialize)]
+pub struct RegisteredClient {
+    /// The issued client identifier.
+    pub client_id: String,
+    /// The issued secret, for confidential clients.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_secret: Option<String>,
+    /// Registration time (seconds since epoch).
+    pub client_id_issued_at: u64,
+    /// `0`: secrets don't expire.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_secret_expires_at: Option<u64>,
+    /// The registered metadata.
+    #[serde(flatten)]
+    pub metadata: ClientRegistration,
+}
+
+impl<C: ConsentHandler> AuthorizationServer<C> {
+    /// Register a client, as the registration endpoint does.
+    pub fn register_client(
+        &self,
+        mut registration: ClientRegistration,
+    ) -> Result<RegisteredClient, String> {
+        if registration.redirect_uris.is_empty() {
+            return Err("redirect_uris is required".into());
+        }
+        for uri in &registration.redirect_uris {
+            validate_redirect_uri(uri)?;
+        }
+        let method = registration
+            .token_endpoint_auth_method
+            .get_or_insert_with(|| "client_secret_basic".into());
+        let client_secret = match method.as_str() {
+            "none" => None,
+            "client_secret_basic" | "client_secret_post" => Some(self.random_token(32)),
+            other => return Err(format!("unsupported token_endpoint_auth_method: {other}")),
+        };
+
+        let client_id = self.random_token(16);
+        {
+            let mut clients = self.inner.clients.lock().expect("clients poisoned");
+            if clients.len() >= self.inner.max_clients {
+                clients.retain(|_, c| c.used || !expired(c.registered_at, UNUSED_CLIENT_TTL));
+            }
+            if clients.len() >= self.inner.max_clients {
+                return Err("too many registered clients".into());
+            }
+            clients.insert(
+                client_id.clone(),
+                Client {
+                    name: registration.client_name.clone(),
+                    redirect_uris: registration.redirect_uris.clone(),
+                    secret_hash: client_secret.as_deref().map(sha256),
+                    registered_at: Instant::now(),
+                    used: false,
+                },
+            );
+        }
+        Ok(RegisteredClient {
+            client_id,
+            client_secret_expires_at: client_secret.as_ref().map(|_| 0),
+            client_secret,
+            client_id_issued_at: super::unix_now(),
+            metadata: registration,
+        })
+    }
+
+    /// Pick the token audience for a requested resource.
+    fn audience(&self, requested: Option<&str>) -> Result<Option<String>, OAuthError> {
+        let requested = requested
+            .map(canonical_resource_uri)
+            .transpose()
+            .map_err(|e| OAuthError::new("invalid_target", e.to_string()))?;
+        let allowed = &self.inner.resources;
+        match requested {
+            None => Ok(allowed.first().cloned()),
+            Some(r) if allowed.is_empty() || allowed.contains(&r) => Ok(Some(r)),
+            Some(r) => Err(OAuthError::new(
+                "invalid_target",
+                format!("unknown resource: {r}"),
+            )),
+        }
+    }
+}
+
+/// OAuth 2.0 error response body (RFC 6749 §5.2).
+#[derive(Debug, Serialize)]
+struct OAuthError {
+    error: &'static str,
+    error_description: String,
+}
+
+impl OAuthError {
+    fn new(error: &'static str, description: impl Into<String>) -> Self {
+        Self {
+            error,
+            error_description: description.into(),
+        }
+    }
+}
+
+impl IntoResponse for OAuthError {
+    fn into_response(self) -> Response {
+        let status = if self.error == "invalid_client" {
+            StatusCode::UNAUTHORIZED
+        } else {
+            StatusCode::BAD_REQUEST
+        };
+        (status, Json(self)).into_response()
+    }
+}
+
+pub(super) async fn metadata<C: ConsentHandler>(
+    State(server): State<AuthorizationServer<C>>,
+) -> Response {
+    Json(server.metadata()).into_response()
+}
+
+pub(super) async fn jwks<C: ConsentHandler>(
+    State(server): State<AuthorizationServer<C>>,
+) -> Response {
+    Json(server.jwks()).into_response()
+}
+
+pub(super) async fn register<C: ConsentHandler>(
+    State(server): State<AuthorizationServer<C>>,
+    Json(registration): Json<ClientRegistration>,
+) -> Response {
+    match server.register_client(registration) {
+        Ok(client) => (StatusCode::CREATED, Json(client)).into_response(),
+        Err(e) => OAuthError::new("invalid_client_metadata", e).into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct AuthorizeParams {
+    response_type: Option<String>,
+    client_id: Option<String>,
+    redirect_uri: Option<String>,
+    scope: Option<String>,
+    state: Option<String>,
+    code_challenge: Option<String>,
+    code_challenge_method: Option<String>,
+    resource: Option<String>,
+}
+
+pub(super) async fn authorize<C: ConsentHandler>(
+    State(server): State<AuthorizationServer<C>>,
+    request: Request,
+) -> Response {
+    let (parts, _) = request.into_parts();
+    let params = match Query::<AuthorizeParams>::try_from_uri(&parts.uri) {
+        Ok(Query(params)) => params,
+        Err(e) => return OAuthError::new("invalid_request", e.to_string()).into_response(),
+    };
+
+    // Errors before the redirect URI is verified must not redirect.
+    let Some(client_id) = params.client_id else {
+        return OAuthError::new("invalid_request", "client_id is required").into_response();
+    };
+    let (client_name, redirect_uri) = {
+        let clients = server.inner.clients.lock().expect("clients poisoned");
+        let Some(client) = clients.get(&client_id) else {
+            return OAuthError::new("invalid_client", "unknown client").into_response();
+        };
+        let redirect_uri = match params.redirect_uri {
+            Some(uri) if client.redirect_uris.contains(&uri) => uri,
+            None if client.redirect_uris.len() == 1 => client.redirect_uris[0].clone(),
+            _ => {
+                return OAuthError::new("invalid_request", "redirect_uri is not registered")
+                    .into_response();
+            }
+        };
+        (client.name.clone(), redirect_uri)
+    };
+
+    let redirect = |query: &[(&str, &str)]| {
+        let mut url = url::Url::parse(&redirect_uri).expect("validated at registration");
+        {
+            let mut pairs = url.query_pairs_mut();
+            for (k, v) in query {
+                pairs.append_pair(k, v);
+            }
+            if let Some(ref state) = params.state {
+                pairs.append_pair("state", state);
+            }
+            pairs.append_pair("iss", server.issuer());
+        }
+        Redirect::to(url.as_str()).into_response()
+    };
+    let redirect_error = |error: &str, description: &str| {
+        redirect(&[("error", error), ("error_description", description)])
+    };
+
+    if params.response_type.as_deref() != Some("code") {
+        return redirect_error("unsupported_response_type", "response_type must be code");
+    }
+    let Some(code_challenge) = params.code_challenge else {
+        return redirect_error("invalid_request", "code_challenge is required");
+    };
+    if params.code_challenge_method.as_deref() != Some("S256") {
+        return redirect_error("invalid_request", "code_challenge_method must be S256");
+    }
+    let resource = match server.audience(params.resource.as_deref()) {
+        Ok(resource) => resource,
+        Err(e) => return redirect_error(e.error, &e.error_description),
+    };
+
+    let request = AuthorizationRequest {
+        client_id: client_id.clone(),
+        client_name,
+        redirect_uri: redirect_uri.clone(),
+        scope: params
+            .scope
+            .as_deref()
+            .map(|s| s.split_whitespace().map(String::from).collect())
+            .unwrap_or_default(),
+        resource: resource.clone(),
+    };
+    let (subject, scope) = match server.inner.consent.consent(&request, &parts).await {
+        Consent::Approve { subject, scope } => (subject, scope),
+        Consent::Deny => return redirect_error("access_denied", "the user denied the request"),
+        Consent::Respond(response) => return response,
+    };
+
+    let code = server.random_token(32);
+    let mut codes = server.inner.codes.lock().expect("codes poisoned");
+    // Unredeemed codes would otherwise stay forever.
+    codes.retain(|_, pending| !expired(pending.created_at, CODE_TTL));
+    codes.insert(
+        code.clone(),
+        PendingCode {
+            client_id,
+            redirect_uri: redirect_uri.clone(),
+            code_challenge,
+            subject,
+            scope,
+            resource,
+            created_at: Instant::now(),
+        },
+    );
+    drop(codes);
+    redirect(&[("code", &code)])
+}
+
+#[derive(Serialize)]
+struct TokenResponse {
+    access_token: String,
+    token_type: &'static str,
+    expires_in: u64,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    scope: String,
+}
+
+pub(super) async fn token<C: ConsentHandler>(
+    State(server): State<AuthorizationServer<C>>,
+    headers: HeaderMap,
+    Form(form): Form<HashMap<String, String>>,
+) -> Response {
+    match exchange_code(&server, &headers, &form) {
+        Ok(response) => {
+            let mut response = Json(response).into_response();
+            response
+                .headers_mut()
+                .insert(http::header::CACHE_CONTROL, "no-store".parse().unwrap());
+            response
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+fn exchange_code<C: ConsentHandler>(
+    server: &AuthorizationServer<C>,
+    headers: &HeaderMap,
+    form: &HashMap<String, String>,
+) -> Result<TokenResponse, OAuthError> {
+    let param = |name: &str| form.get(name).map(String::as_str);
+    match param("grant_type") {
+        Some("authorization_code") => {}
+        Some(other) => {
+            return Err(OAuthError::new(
+                "unsupported_grant_type",
+                format!("unsupported grant_type: {other}"),
+            ));
+        }
+        None => return Err(OAuthError::new("invalid_request", "grant_type is required")),
+    }
+
+    let (client_id, client_secret) = client_credentials(headers, form)?;
+    {
+        let clients = server.inner.clients.lock().expect("clients poisoned");
+        let client = clients
+            .get(&client_id)
+            .ok_or_else(|| OAuthError::new("invalid_client", "unknown client"))?;
+        if let Some(expected) = client.secret_hash {
+            let presented = client_secret
+                .as_deref()
+                .ok_or_else(|| OAuthError::new("invalid_client", "client secret required"))?;
+            if sha256(presented) != expected {
+                return Err(OAuthError::new("invalid_client", "invalid client secret"));
+            }
+        }
+    }
+
+    let code =
+        param("code").ok_or_else(|| OAuthError::new("invalid_request", "code is required"))?;
+    // Codes are single use: remove before any further checks.
+    let pending = server
+        .inner
+        .codes
+        .lock()
+        .expect("codes poisoned")
+        .remove(code)
+        .filter(|p| !expired(p.created_at, CODE_TTL))
+        .ok_or_else(|| OAuthError::new("invalid_grant", "invalid or expired code"))?;
+    if pending.client_id != client_id {
+        return Err(OAuthError::new(
+            "invalid_grant",
+            "code was issued to another client",
+        ));
+    }
+    if param("redirect_uri").is_some_and(|uri| uri != pending.redirect_uri) {
+        return Err(OAuthError::new("invalid_grant", "redirect_uri mismatch"));
+    }
+    let verifier = param("code_verifier")
+        .ok_or_else(|| OAuthError::new("invalid_request", "code_verifier is required"))?;
+    if URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes())) != pending.code_challenge {
+        return Err(OAuthError::new("invalid_grant", "PKCE verification failed"));
+    }
+    if let Some(resource) = param("resource") {
+        let requested = canonical_resource_uri(resource)
+            .map_err(|e| OAuthError::new("invalid_target", e.to_string()))?;
+        if pending.resource.as_deref() != Some(requested.as_str()) {
+            return Err(OAuthError::new(
+                "invalid_target",
+                "resource differs from the authorization request",
+            ));
+        }
+    }
+
+    if let Some(client) = server
+        .inner
+        .clients
+        .lock()
+        .expect("clients poisoned")
+        .get_mut(&client_id)
+    {
+        client.used = true;
+    }
+
+    let access_token = server
+        .issue_token(
+            &client_id,
+            &pending.subject,
+            &pending.scope,
+            pending.resource.as_deref(),
+        )
+        .map_err(|e| OAuthError::new("server_error", format!("{e:#}")))?;
+    Ok(TokenResponse {
+        access_token,
+        token_type: "Bearer",
+        expires_in: server.inner.token_ttl.as_secs(),
+        scope: pending.scope.join(" "),
+    })
+}
+
+/// Client credentials from HTTP Basic auth or the form body.
+fn client_credentials(
+    headers: &HeaderMap,
+    form: &HashMap<String, String>,
+) -> Result<(String, Option<String>), OAuthError> {
+    let basic = headers
+        .get(http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Basic "));
+    if let Some(encoded) = basic {
+        let decoded = STANDARD
+            .decode(encoded.trim())
+            .ok()
+            .and_then(|d| String::from_utf8(d).ok())
+            .ok_or_else(|| OAuthError::new("invalid_client", "malformed Basic credentials"))?;
+        let (id, secret) = decoded
+            .split_once(':')
+            .ok_or_else(|| OAuthError::new("invalid_client", "malformed Basic credentials"))?;
+        return Ok((id.to_owned(), Some(secret.to_owned())));
+    }
+    let id = form
+        .get("client_id")
+        .cloned()
+        .ok_or_else(|| OAuthError::new("invalid_client", "client_id is required"))?;
+    Ok((id, form.get("client_secret").cloned()))
+}
+
+/// OAuth 2.1 §2.3.1: redirect URIs must be absolute without a fragment, and
+/// plain `http` is only allowed for loopback addresses.
+fn validate_redirect_uri(uri: &str) -> Result<(), String> {
+    let url = url::Url::parse(uri).map_err(|e| format!("invalid redirect_uri {uri}: {e}"))?;
+    if url.fragment().is_some() {
+        return Err(format!("redirect_uri must not have a fragment: {uri}"));
+    }
+    if url.scheme() == "http"
+        && !matches!(url.host_str(), Some("localhost" | "127.0.0.1" | "[::1]"))
+    {
+        return Err(format!(
+            "http redirect_uri must be a loopback address: {uri}"
+        ));
+    }
+    Ok(())
+}
+
+fn sha256(value: &str) -> [u8; 32] {
+    Sha256::digest(value.as_bytes()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PendingCode;
+    use crate::auth::{
+        AuthLayer, BearerAuth, Validator,
+        oauth::ProtectedResource,
+        server::{
+            AuthorizationRequest, AuthorizationServer, CODE_TTL, ClientRegistration, Consent,
+            ConsentHandler,
+        },
+    };
+    use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+    use sha2::{Digest, Sha256};
+    use std::time::Instant;
+    use tower::ServiceExt;
+
+    const ISSUER: &str = "https://mcp.example.com";
+    const RESOURCE: &str = "https://mcp.example.com/mcp";
+    const REDIRECT: &str = "http://localhost:3000/callback";
+    const VERIFIER: &str = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+
+    /// Approves everyone as `alice` unless the `deny` scope is requested.
+    #[derive(Clone)]
+    struct Alice;
+
+    impl ConsentHandler for Alice {
+        async fn consent(
+            &self,
+            request: &AuthorizationRequest,
+            _: &http::request::Parts,
+        ) -> Consent {
+            if request.scope.iter().any(|s| s == "deny") {
+                return Consent::Deny;
+            }
+            Consent::Approve {
+                subject: "alice".into(),
+                scope: request.scope.clone(),
+            }
+        }
+    }
+
+    fn server() -> AuthorizationServer<Alice> {
+        AuthorizationServer::builder(ISSUER, Alice)
+            .resource(RESOURCE)
+            .scopes_supported(["mcp:tools"])
+            .build()
+            .unwrap()
+    }
+
+    async fn send(
+        router: &axum::Router,
+        req: http::Request<axum::body::Body>,
+    ) -> http::Response<axum::body::Body> {
+        router.clone().oneshot(req).await.unwrap()
+    }
+
+    async fn json(resp: http::Response<axum::body::Body>) -> serde_json::Value {
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    async fn register(router: &axum::Router) -> String {
+        let resp = send(
+            router,
+            http::Request::post("/register")
+                .header(http::header::CONTENT_TYPE, "application/json")
+                .body(axum::body::Body::from(
+                    serde_json::json!({
+                        "redirect_uris": [REDIRECT],
+                        "client_name": "test",
+                        "token_endpoint_auth_method": "none",
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(resp.status(), http::StatusCode::CREATED);
+        let client = json(resp).await;
+        assert!(client.get("client_secret").is_none());
+        client["client_id"].as_str().unwrap().to_owned()
+    }
+
+    /// Run `/authorize` and return the redirect's query parameters.
+    async fn authorize(
+        router: &axum::Router,
+        client_id: &str,
+        scope: &str,
+    ) -> Vec<(String, String)> {
+        let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(VERIFIER.as_bytes()));
+        let uri = format!(
+            "/authorize?response_type=code&client_id={client_id}&redirect_uri={REDIRECT}\
+             &scope={scope}&state=xyz&code_challenge={challenge}&code_challenge_method=S256"
+        );
+        let resp = send(
+            router,
+            http::Request::get(uri)
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert!(resp.status().is_redirection());
+        let location = resp.headers()[http::header::LOCATION].to_str().unwrap();
+        assert!(location.starts_with(REDIRECT));
+        url::Url::parse(location)
+            .unwrap()
+            .query_pairs()
+            .map(|(k, v)| (k.into_owned(), v.into_owned()))
+            .collect()
+    }
+
+    fn get<'a>(query: &'a [(String, String)], key: &str) -> Option<&'a str> {
+        query
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    async fn redeem(
+        router: &axum::Router,
+        client_id: &str,
+        code: &str,
+        verifier: &str,
+    ) -> http::Response<axum::body::Body> {
+        let body = format!(
+            "grant_type=authorization_code&code={code}&client_id={client_id}\
+             &redirect_uri={REDIRECT}&code_verifier={verifier}"
+        );
+        send(
+            router,
+            http::Request::post("/token")
+                .header(
+                    http::header::CONTENT_TYPE,
+                    "application/x-www-form-urlencoded",
+                )
+                .body(axum::body::Body::from(body))
+                .unwrap(),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn serves_metadata_and_jwks() {
+        let router = server().router();
+        let resp = send(
+            &router,
+            http::Request::get("/.well-known/oauth-authorization-server")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await;
+        let metadata = json(resp).await;
+        assert_eq!(metadata["issuer"], ISSUER);
+        assert_eq!(metadata["token_endpoint"], format!("{ISSUER}/token"));
+        assert_eq!(metadata["code_challenge_methods_supported"][0], "S256");
+
+        let resp = send(
+            &router,
+            http::Request::get("/jwks.json")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await;
+        let jwks = json(resp).await;
+        assert_eq!(jwks["keys"][0]["kty"], "OKP");
+        assert_eq!(jwks["keys"][0]["alg"], "EdDSA");
+    }
+
+    #[tokio::test]
+    async fn authorization_code_flow_end_to_end() {
+        let server = server();
+        let router = server.router();
+        let client_id = register(&router).await;
+
+        let query = authorize(&router, &client_id, "mcp:tools").await;
+        assert_eq!(get(&query, "state"), Some("xyz"));
+        assert_eq!(get(&query, "iss"), Some(ISSUER));
+        let code = get(&query, "code").expect("code issued").to_owned();
+
+        let resp = redeem(&router, &client_id, &code, VERIFIER).await;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let token = json(resp).await;
+        let access_token = token["access_token"].as_str().unwrap();
+        assert_eq!(token["token_type"], "Bearer");
+
+        // Validate in-process, then through the resource-bound layer.
+        let validator = server.validator().build().await.unwrap();
+        let claims = validator.validate(access_token).await.unwrap();
+        assert_eq!(claims.sub, "alice");
+        assert_eq!(claims.scope, vec!["mcp:tools"]);
+        assert_eq!(claims.aud, Some(vec![RESOURCE.to_string()]));
+
+        let resource =
+            ProtectedResource::new(server.protected_resource_metadata(RESOURCE)).unwrap();
+        let app = axum::Router::new()
+            .route("/mcp", axum::routing::post(|| async { "ok" }))
+            .layer(AuthLayer::new(BearerAuth::new(validator)).with_protected_resource(resource));
+        let resp = app
+            .oneshot(
+                http::Request::post("/mcp")
+                    .header(
+                        http::header::AUTHORIZATION,
+                        format!("Bearer {access_token}"),
+                    )
+                    .body(axum::body::Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), http::StatusCode::OK);
+
+        // Codes are single use.
+        let resp = redeem(&router, &client_id, &code, VERIFIER).await;
+        assert_eq!(json(resp).await["error"], "invalid_grant");
+    }
+
+    #[tokio::test]
+    async fn rejects_wrong_pkce_verifier() {
+        let router = server().router();
+        let client_id = register(&router).await;
+        let query = authorize(&router, &client_id, "mcp:tools").await;
+        let code = get(&query, "code").unwrap();
+        let resp = redeem(&router, &client_id, code, "wrong-verifier").await;
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+        assert_eq!(json(resp).await["error"], "invalid_grant");
+    }
+
+    #[tokio::test]
+    async fn denied_consent_redirects_with_error() {
+        let router = server().router();
+        let client_id = register(&router).await;
+        let query = authorize(&router, &client_id, "deny").await;
+        assert_eq!(get(&query, "error"), Some("access_denied"));
+        assert_eq!(get(&query, "state"), Some("xyz"));
+        assert!(get(&query, "code").is_none());
+    }
+
+    #[tokio::test]
+    async fn rotated_keys_keep_validating_old_tokens() {
+        let server = server();
+        let scope = vec!["mcp:tools".to_string()];
+        let validator = server.validator().build().await.unwrap();
+
+        let old = server
+            .issue_token("svc", "bob", &scope, Some(RESOURCE))
+            .unwrap();
+        server.rotate_keys();
+        let new = server
+            .issue_token("svc", "bob", &scope, Some(RESOURCE))
+            .unwrap();
+
+        assert_eq!(server.jwks().keys.len(), 2);
+        assert!(validator.validate(&old).await.is_ok());
+        assert!(validator.validate(&new).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn caps_clients_and_purges_expired_codes() {
+        let server = AuthorizationServer::builder(ISSUER, Alice)
+            .max_clients(1)
+            .build()
+            .unwrap();
+        let router = server.router();
+        let client_id = register(&router).await;
+        let registration = ClientRegistration {
+            redirect_uris: vec![REDIRECT.into()],
+            ..Default::default()
+        };
+        assert_eq!(
+            server.register_client(registration).unwrap_err(),
+            "too many registered clients"
+        );
+
+        let stale = PendingCode {
+            client_id: client_id.clone(),
+            redirect_uri: REDIRECT.into(),
+            code_challenge: String::new(),
+            subject: "alice".into(),
+            scope: Vec::new(),
+            resource: None,
+            created_at: Instant::now() - CODE_TTL,
+        };
+        server
+            .inner
+            .codes
+            .lock()
+            .unwrap()
+            .insert("stale".into(), stale);
+        authorize(&router, &client_id, "mcp:tools").await;
+        let codes = server.inner.codes.lock().unwrap();
+        assert!(!codes.contains_key("stale"));
+        assert_eq!(codes.len(), 1);
+    }
+}
@@ -0,0 +1,129 @@
+//! Signing keys with rotation for the embedded authorization server.
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use jsonwebtoken::{
+    Algorithm, EncodingKey, Header,
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm,
+        OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse,
+    },
+};
+use ring::{
+    rand::{SecureRandom, SystemRandom},
+    signature::{Ed25519KeyPair, KeyPair},
+};
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+/// An Ed25519 signing key.
+struct SigningKey {
+    kid: String,
+    encoding: EncodingKey,
+    jwk: Jwk,
+    created_at: Instant,
+    /// When the key stopped signing; `None` for the current key.
+    retired_at: Option<Instant>,
+}
+
+impl SigningKey {
+    fn generate(rng: &SystemRandom) -> Self {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(rng).expect("Ed25519 key generation");
+        let pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).expect("valid generated key");
+        let kid = random_token(rng, 12);
+        let jwk = Jwk {
+            common: CommonParameters {
+                public_key_use: Some(PublicKeyUse::Signature),
+                key_algorithm: Some(KeyAlgorithm::EdDSA),
+                key_id: Some(kid.clone()),
+                ..Default::default()
+            },
+            algorithm: AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                key_type: OctetKeyPairType::OctetKeyPair,
+                curve: EllipticCurve::Ed25519,
+                x: URL_SAFE_NO_PAD.encode(pair.public_key().as_ref()),
+            }),
+        };
+        Self {
+            kid,
+            encoding: EncodingKey::from_ed_der(pkcs8.as_ref()),
+            jwk,
+            created_at: Instant::now(),
+            retired_at: None,
+        }
+    }
+}
+
+/// The current signing key plus retired keys still published for
+/// verification.
+pub(super) struct KeyRing {
+    rng: SystemRandom,
+    keys: Vec<SigningKey>,
+    rotate_every: Option<Duration>,
+    /// How long a retired key stays in the JWKS; at least the token lifetime.
+    retain_for: Duration,
+}
+
+impl KeyRing {
+    pub(super) fn new(rotate_every: Option<Duration>, retain_for: Duration) -> Self {
+        let rng = SystemRandom::new();
+        let keys = vec![SigningKey::generate(&rng)];
+        Self {
+            rng,
+            keys,
+            rotate_every,
+            retain_for,
+        }
+    }
+
+    /// Replace the signing key, keeping the old one published until every
+    /// token it signed has expired.
+    pub(super) fn rotate(&mut self) {
+        let now = Instant::now();
+        for key in &mut self.keys {
+            key.retired_at.get_or_insert(now);
+        }
+        self.keys
+            .retain(|k| k.retired_at.is_none_or(|at| at.elapsed() < self.retain_for));
+        self.keys.push(SigningKey::generate(&self.rng));
+    }
+
+    /// Sign `claims`, rotating first if the current key is due.
+    pub(super) fn sign<T: Serialize>(
+        &mut self,
+        claims: &T,
+    ) -> jsonwebtoken::errors::Result<String> {
+        if self
+            .rotate_every
+            .is_some_and(|every| self.current().created_at.elapsed() >= every)
+        {
+            self.rotate();
+        }
+        let key = self.current();
+        let mut header = Header::new(Algorithm::EdDSA);
+        header.kid = Some(key.kid.clone());
+        header.typ = Some("at+jwt".into());
+        jsonwebtoken::encode(&header, claims, &key.encoding)
+    }
+
+    /// Public keys for verification, current key first.
+    pub(super) fn jwks(&self) -> JwkSet {
+        JwkSet {
+            keys: self.keys.iter().rev().map(|k| k.jwk.clone()).collect(),
+        }
+    }
+
+    pub(super) fn random_token(&self, bytes: usize) -> String {
+        random_token(&self.rng, bytes)
+    }
+
+    fn current(&self) -> &SigningKey {
+        self.keys.last().expect("key ring is never empty")
+    }
+}
+
+/// A URL-safe random string of `bytes` bytes of entropy.
+fn random_token(rng: &SystemRandom, bytes: usize) -> String {
+    let mut buf = vec![0u8; bytes];
+    rng.fill(&mut buf).expect("system randomness");
+    URL_SAFE_NO_PAD.encode(buf)
+}
@@ -0,0 +1,419 @@
+//! Embedded minimal OAuth 2.1 authorization server.
+//!
+//! For small self-hosted deployments without an identity provider. Provides:
+//!
+//! - Authorization code grant with mandatory PKCE (`S256`).
+//! - Dynamic client registration ([RFC 7591](https://datatracker.ietf.org/doc/html/rfc7591)).
+//! - A pluggable [`ConsentHandler`] that authenticates the user and approves
+//!   or denies each authorization request.
+//! - Ed25519-signed JWT access tokens with key rotation, published at a JWKS
+//!   endpoint.
+//! - Authorization Server Metadata ([RFC 8414](https://datatracker.ietf.org/doc/html/rfc8414)).
+//!
+//! The server implements [`JwksSource`](crate::auth::jwt::JwksSource), so a
+//! [`JwtValidator`](crate::auth::jwt::JwtValidator) in the same binary
+//! verifies its tokens without any HTTP round-trip.
+//!
+//! Requires the `authorization-server` feature.
+//!
+//! ```rust,ignore
+//! use rmcp_axum::auth::{AuthLayer, BearerAuth};
+//! use rmcp_axum::auth::oauth::{ProtectedResource, metadata_router};
+//! use rmcp_axum::auth::server::{AuthorizationServer, AuthorizationRequest, Consent, ConsentHandler};
+//!
+//! #[derive(Clone)]
+//! struct SingleUser;
+//!
+//! impl ConsentHandler for SingleUser {
+//!     async fn consent(&self, request: &AuthorizationRequest, _: &http::request::Parts) -> Consent {
+//!         Consent::Approve { subject: "admin".into(), scope: request.scope.clone() }
+//!     }
+//! }
+//!
+//! let server = AuthorizationServer::builder("https://mcp.example.com", SingleUser)
+//!     .resource("https://mcp.example.com/mcp")
+//!     .scopes_supported(["mcp:tools"])
+//!     .build()?;
+//!
+//! let resource = ProtectedResource::new(server.protected_resource_metadata("https://mcp.example.com/mcp"))?;
+//! let validator = server.validator().build().await?;
+//!
+//! let app = axum::Router::new()
+//!     .nest_service("/mcp", mcp_service)
+//!     .layer(AuthLayer::new(BearerAuth::new(validator)).with_protected_resource(resource.clone()))
+//!     .merge(resource.router())
+//!     .merge(server.router());
+//! ```
+
+mod endpoints;
+mod keys;
+
+use crate::auth::{
+    jwt::{JwksSource, JwtValidator, JwtValidatorBuilder},
+    oauth::{
+        AUTHORIZATION_SERVER_WELL_KNOWN, AuthorizationServerMetadata, ProtectedResourceMetadata,
+        canonical_resource_uri,
+    },
+};
+use anyhow::{Context, Result, bail};
+use futures::future::BoxFuture;
+use jsonwebtoken::jwk::JwkSet;
+use keys::KeyRing;
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+pub use endpoints::{ClientRegistration, RegisteredClient};
+
+/// Default access token lifetime.
+const DEFAULT_TOKEN_TTL: Duration = Duration::from_secs(3600);
+
+/// Lifetime of an authorization code.
+const CODE_TTL: Duration = Duration::from_secs(60);
+
+/// Default cap on registered clients.
+const DEFAULT_MAX_CLIENTS: usize = 10_000;
+
+/// How long a registered client may go without redeeming a code before
+/// its registration can be dropped.
+const UNUSED_CLIENT_TTL: Duration = Duration::from_secs(3600);
+
+/// An authorization request awaiting the user's consent.
+#[derive(Clone, Debug)]
+pub struct AuthorizationRequest {
+    /// The requesting client.
+    pub client_id: String,
+    /// Human-readable client name from registration.
+    pub client_name: Option<String>,
+    /// Where the user agent is sent afterwards.
+    pub redirect_uri: String,
+    /// Requested scopes.
+    pub scope: Vec<String>,
+    /// Canonical resource the token is requested for (RFC 8707).
+    pub resource: Option<String>,
+}
+
+/// Outcome of a [`ConsentHandler`].
+pub enum Consent {
+    /// Issue a code for `subject` with the granted `scope`.
+    Approve {
+        /// The authenticated user.
+        subject: String,
+        /// Granted scopes; may be narrower than requested.
+        scope: Vec<String>,
+    },
+    /// Redirect back to the client with `access_denied`.
+    Deny,
+    /// Return this response instead, e.g. a login or consent page that
+    /// submits back to the authorization endpoint.
+    Respond(http::Response<axum::body::Body>),
+}
+
+/// Authenticates the user and decides on an authorization request.
+///
+/// Called from the authorization endpoint with the request's HTTP parts, so
+/// implementations can read session cookies or render a login page via
+/// [`Consent::Respond`].
+pub trait ConsentHandler: Send + Sync + 'static {
+    /// Decide on `request`.
+    fn consent(
+        &self,
+        request: &AuthorizationRequest,
+        parts: &http::request::Parts,
+    ) -> impl Future<Output = Consent> + Send;
+}
+
+/// Builder for [`AuthorizationServer`].
+pub struct AuthorizationServerBuilder<C> {
+    issuer: String,
+    consent: C,
+    resources: Vec<String>,
+    scopes_supported: Option<Vec<String>>,
+    token_ttl: Duration,
+    rotate_every: Option<Duration>,
+    max_clients: usize,
+}
+
+impl<C: ConsentHandler> AuthorizationServerBuilder<C> {
+    /// Allow tokens for this resource (RFC 8707). Repeatable.
+    ///
+    /// The first resource is the default audience when a client doesn't send
+    /// a `resource` parameter; requests for unlisted resources are rejected
+    /// with `invalid_target`. With no resources configured, tokens carry
+    /// whatever resource the client asked for.
+    pub fn resource(mut self, resource: impl Into<String>) -> Self {
+        self.resources.push(resource.into());
+        self
+    }
+
+    /// Scopes advertised in metadata.
+    pub fn scopes_supported<I, S>(mut self, scopes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.scopes_supported = Some(scopes.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Access token lifetime (default 1 hour).
+    pub fn token_ttl(mut self, ttl: Duration) -> Self {
+        self.token_ttl = ttl;
+        self
+    }
+
+    /// Most clients registered at once (default 10 000).
+    ///
+    /// Registration is unauthenticated, so this bounds the memory anyone
+    /// can make the server use. Clients that haven't redeemed a code within
+    /// an hour of registering are dropped to make room; beyond that,
+    /// registration fails with `invalid_client_metadata`.
+    pub fn max_clients(mut self, max: usize) -> Self {
+        self.max_clients = max;
+        self
+    }
+
+    /// Rotate the signing key at this interval.
+    ///
+    /// Rotation happens lazily on the next signing operation; retired keys
+    /// stay in the JWKS until every token they signed has expired.
+    pub fn rotate_keys_every(mut self, interval: Duration) -> Self {
+        self.rotate_every = Some(interval);
+        self
+    }
+
+    /// Generate the first signing key and build the server.
+    pub fn build(self) -> Result<AuthorizationServer<C>> {
+        let url = url::Url::parse(&self.issuer).context("invalid issuer URL")?;
+        if url.query().is_some() || url.fragment().is_some() {
+            bail!("issuer must not have a query or fragment");
+        }
+        let issuer = self.issuer.trim_end_matches('/').to_owned();
+        let base_path = url.path().trim_end_matches('/').to_owned();
+        let resources = self
+            .resources
+            .iter()
+            .map(|r| canonical_resource_uri(r))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(AuthorizationServer {
+            inner: Arc::new(ServerInner {
+                issuer,
+                base_path,
+                consent: self.consent,
+                resources,
+                scopes_supported: self.scopes_supported,
+                token_ttl: self.token_ttl,
+                max_clients: self.max_clients,
+                keys: Mutex::new(KeyRing::new(self.rotate_every, self.token_ttl)),
+                clients: Mutex::new(HashMap::new()),
+                codes: Mutex::new(HashMap::new()),
+            }),
+        })
+    }
+}
+
+struct ServerInner<C> {
+    issuer: String,
+    /// Path component of the issuer, prefixed to every endpoint.
+    base_path: String,
+    consent: C,
+    resources: Vec<String>,
+    scopes_supported: Option<Vec<String>>,
+    token_ttl: Duration,
+    max_clients: usize,
+    keys: Mutex<KeyRing>,
+    clients: Mutex<HashMap<String, endpoints::Client>>,
+    codes: Mutex<HashMap<String, endpoints::PendingCode>>,
+}
+
+/// Embedded OAuth 2.1 authorization server.
+pub struct AuthorizationServer<C> {
+    inner: Arc<ServerInner<C>>,
+}
+
+impl<C> Clone for AuthorizationServer<C> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+/// Access token claims issued by the embedded server.
+#[derive(Serialize)]
+struct IssuedClaims<'a> {
+    iss: &'a str,
+    sub: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    aud: Option<&'a str>,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    scope: String,
+    client_id: &'a str,
+    iat: u64,
+    exp: u64,
+    jti: String,
+}
+
+impl<C: ConsentHandler> AuthorizationServer<C> {
+    /// Start building a server for `issuer`, the externally visible base URL
+    /// under which [`router`](Self::router) is mounted.
+    pub fn builder(issuer: impl Into<String>, consent: C) -> AuthorizationServerBuilder<C> {
+        AuthorizationServerBuilder {
+            issuer: issuer.into(),
+            consent,
+            resources: Vec::new(),
+            scopes_supported: None,
+            token_ttl: DEFAULT_TOKEN_TTL,
+            rotate_every: None,
+            max_clients: DEFAULT_MAX_CLIENTS,
+        }
+    }
+
+    /// The issuer identifier.
+    pub fn issuer(&self) -> &str {
+        &self.inner.issuer
+    }
+
+    /// Router serving the authorization, token, registration and JWKS
+    /// endpoints plus `/.well-known/oauth-authorization-server`.
+    pub fn router(&self) -> axum::Router {
+        let base = &self.inner.base_path;
+        axum::Router::new()
+            .route(
+                &format!("{AUTHORIZATION_SERVER_WELL_KNOWN}{base}"),
+                axum::routing::get(endpoints::metadata::<C>),
+            )
+            .route(
+                &format!("{base}{}", endpoints::AUTHORIZE_PATH),
+                axum::routing::get(endpoints::authorize::<C>),
+            )
+            .route(
+                &format!("{base}{}", endpoints::TOKEN_PATH),
+                axum::routing::post(endpoints::token::<C>),
+            )
+            .route(
+                &format!("{base}{}", endpoints::REGISTER_PATH),
+                axum::routing::post(endpoints::register::<C>),
+            )
+            .route(
+                &format!("{base}{}", endpoints::JWKS_PATH),
+                axum::routing::get(endpoints::jwks::<C>),
+            )
+            .with_state(self.clone())
+    }
+
+    /// The Authorization Server Metadata document.
+    pub fn metadata(&self) -> AuthorizationServerMetadata {
+        let issuer = &self.inner.issuer;
+        AuthorizationServerMetadata {
+            issuer: issuer.clone(),
+            authorization_endpoint: Some(format!("{issuer}{}", endpoints::AUTHORIZE_PATH)),
+            token_endpoint: Some(format!("{issuer}{}", endpoints::TOKEN_PATH)),
+            jwks_uri: Some(format!("{issuer}{}", endpoints::JWKS_PATH)),
+            registration_endpoint: Some(format!("{issuer}{}", endpoints::REGISTER_PATH)),
+            scopes_supported: self.inner.scopes_supported.clone(),
+            response_types_supported: vec!["code".into()],
+            grant_types_supported: Some(vec!["authorization_code".into()]),
+            token_endpoint_auth_methods_supported: Some(vec![
+                "none".into(),
+                "client_secret_basic".into(),
+                "client_secret_post".into(),
+            ]),
+            code_challenge_methods_supported: Some(vec!["S256".into()]),
+            ..Default::default()
+        }
+    }
+
+    /// Protected Resource Metadata naming this server as the authorization
+    /// server for `resource`; pass it to
+    /// [`metadata_router`](crate::auth::oauth::metadata_router) or
+    /// [`ProtectedResource::new`](crate::auth::oauth::ProtectedResource::new).
+    pub fn protected_resource_metadata(
+        &self,
+        resource: impl Into<String>,
+    ) -> ProtectedResourceMetadata {
+        ProtectedResourceMetadata {
+            resource: resource.into(),
+            authorization_servers: vec![self.inner.issuer.clone()],
+            scopes_supported: self.inner.scopes_supported.clone(),
+            bearer_methods_supported: Some(vec!["header".into()]),
+            ..Default::default()
+        }
+    }
+
+    /// A [`JwtValidator`] builder that verifies this server's tokens in
+    /// process, with the issuer preset.
+    pub fn validator(&self) -> JwtValidatorBuilder {
+        JwtValidator::from_jwks_source(self.clone()).issuer(self.inner.issuer.clone())
+    }
+
+    /// Public keys currently published at the JWKS endpoint.
+    pub fn jwks(&self) -> JwkSet {
+        self.inner.keys.lock().expect("key ring poisoned").jwks()
+    }
+
+    /// Retire the current signing key and generate a new one.
+    pub fn rotate_keys(&self) {
+        self.inner.keys.lock().expect("key ring poisoned").rotate();
+    }
+
+    /// Sign an access token directly, bypassing the authorization flow.
+    ///
+    /// Useful for service accounts and tests.
+    pub fn issue_token(
+        &self,
+        client_id: &str,
+        subject: &str,
+        scope: &[String],
+        audience: Option<&str>,
+    ) -> Result<String> {
+        let iat = unix_now();
+        let claims = IssuedClaims {
+            iss: &self.inner.issuer,
+            sub: subject,
+            aud: audience,
+            scope: scope.join(" "),
+            client_id,
+            iat,
+            exp: iat + self.inner.token_ttl.as_secs(),
+            jti: self.random_token(16),
+        };
+        self.inner
+            .keys
+            .lock()
+            .expect("key ring poisoned")
+            .sign(&claims)
+            .context("failed to sign access token")
+    }
+
+    fn random_token(&self, bytes: usize) -> String {
+        self.inner
+            .keys
+            .lock()
+            .expect("key ring poisoned")
+            .random_token(bytes)
+    }
+}
+
+impl<C: ConsentHandler> JwksSource for AuthorizationServer<C> {
+    fn jwks(&self) -> BoxFuture<'_, Result<JwkSet>> {
+        let jwks = AuthorizationServer::jwks(self);
+        Box::pin(async move { Ok(jwks) })
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Whether `created_at` is older than `ttl`.
+fn expired(created_at: Instant, ttl: Duration) -> bool {
+    created_at.elapsed() >= ttl
+}
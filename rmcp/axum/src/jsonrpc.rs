@@ -0,0 +1,99 @@
+//! Minimal JSON-RPC inspection for middleware that needs to see MCP methods.
+//!
+//! Only the envelope is parsed; params and results stay as raw JSON.
+
+use axum::body::{Body, Bytes};
+use http::{Request, Response, StatusCode, request::Parts};
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Largest request body middleware will buffer for inspection.
+const MAX_BODY_BYTES: usize = 4 * 1024 * 1024;
+
+/// JSON-RPC error code for requests rejected by server policy.
+pub(crate) const SERVER_ERROR_CODE: i64 = -32000;
+
+/// A JSON-RPC request, notification or response envelope.
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct Message {
+    #[serde(default)]
+    pub id: Option<Value>,
+    #[serde(default)]
+    pub method: Option<String>,
+    #[serde(default)]
+    pub params: Option<Value>,
+    #[serde(default)]
+    pub result: Option<Value>,
+    #[serde(default)]
+    pub error: Option<Value>,
+}
+
+impl Message {
+    /// Whether this is a request expecting a response (not a notification).
+    pub fn is_request(&self) -> bool {
+        self.method.is_some() && self.id.is_some()
+    }
+
+    /// Whether this is a response to a request.
+    pub fn is_response(&self) -> bool {
+        self.method.is_none() && (self.result.is_some() || self.error.is_some())
+    }
+
+    /// Name of the tool or prompt, or URI of the resource, a request targets.
+    pub fn target(&self) -> Option<&str> {
+        let key = match self.method.as_deref()? {
+            "tools/call" | "prompts/get" => "name",
+            "resources/read" | "resources/subscribe" | "resources/unsubscribe" => "uri",
+            _ => return None,
+        };
+        self.params.as_ref()?.get(key)?.as_str()
+    }
+}
+
+/// Parse a single message or a batch. Anything else yields no messages.
+pub(crate) fn parse(bytes: &[u8]) -> Vec<Message> {
+    match serde_json::from_slice::<Value>(bytes) {
+        Ok(Value::Array(items)) => items
+            .into_iter()
+            .filter_map(|v| serde_json::from_value(v).ok())
+            .collect(),
+        Ok(value @ Value::Object(_)) => serde_json::from_value(value).into_iter().collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Whether `bytes` hold a batch rather than a single message.
+pub(crate) fn is_batch(bytes: &[u8]) -> bool {
+    bytes.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'[')
+}
+
+/// Buffer a request body so its messages can be inspected.
+///
+/// Non-`POST` requests carry no JSON-RPC payload and are returned with an
+/// empty buffer. Oversized bodies are rejected with `413`.
+pub(crate) async fn buffer(req: Request<Body>) -> Result<(Parts, Bytes), Response<Body>> {
+    let (parts, body) = req.into_parts();
+    if parts.method != http::Method::POST {
+        return Ok((parts, Bytes::new()));
+    }
+    match axum::body::to_bytes(body, MAX_BODY_BYTES).await {
+        Ok(bytes) => Ok((parts, bytes)),
+        Err(_) => Err(Response::builder()
+            .status(StatusCode::PAYLOAD_TOO_LARGE)
+            .body(Body::empty())
+            .expect("valid response")),
+    }
+}
+
+/// A JSON-RPC error response object.
+pub(crate) fn error(id: Option<&Value>, code: i64, message: &str, data: Option<Value>) -> Value {
+    let mut error = serde_json::json!({ "code": code, "message": message });
+    if let Some(data) = data {
+        error["data"] = data;
+    }
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": id.cloned().unwrap_or(Value::Null),
+        "error": error,
+    })
+}
@@ -12,8 +12,44 @@
 //!   and emits spec-compliant `WWW-Authenticate` headers on 401 responses.
 //! - **Protected Resource Metadata** — [`metadata_router`](auth::oauth::metadata_router)
 //!   serves the RFC 9728 `/.well-known/oauth-protected-resource` endpoint.
+//! - **Audience binding** — [`ProtectedResource`](auth::oauth::ProtectedResource)
+//!   drives both the metadata endpoint and RFC 8707 audience checks.
+//! - **Authorization Server Metadata** — [`authorization_server_router`](auth::oauth::authorization_server_router)
+//!   serves RFC 8414 metadata, and `AuthorizationServerProxy` mirrors an
+//!   upstream provider with dynamic client registration passthrough
+//!   (feature `oauth-proxy`).
 //! - **JWT validation** — [`JwtValidator`](auth::jwt::JwtValidator) validates
-//!   tokens against a JWKS endpoint (feature `jwt`).
+//!   tokens against a JWKS endpoint, and [`MultiIssuerValidator`](auth::jwt::MultiIssuerValidator)
+//!   against per-tenant issuers (feature `jwt`).
+//! - **DPoP** — [`BearerAuth::with_dpop`](auth::BearerAuth::with_dpop) accepts
+//!   sender-constrained tokens and verifies their RFC 9449 proofs (feature `dpop`).
+//! - **Token introspection** — [`IntrospectionValidator`](auth::introspection::IntrospectionValidator)
+//!   validates opaque tokens against an RFC 7662 endpoint (feature `introspection`).
+//! - **API keys** — [`ApiKeyAuth`](auth::api_key::ApiKeyAuth) authenticates
+//!   service accounts by hashed static keys (feature `api-key`).
+//! - **Client certificates** — [`ClientCertAuth`](auth::mtls::ClientCertAuth)
+//!   authenticates mTLS callers directly or behind a TLS-terminating proxy
+//!   (feature `mtls`).
+//! - **Embedded authorization server** — [`AuthorizationServer`](auth::server::AuthorizationServer)
+//!   issues PKCE-protected, Ed25519-signed tokens for self-hosted deployments
+//!   without an identity provider (feature `authorization-server`).
+//! - **Revocation** — [`AuthLayer::with_revocation`](auth::AuthLayer::with_revocation)
+//!   rejects revoked tokens, subjects and sessions before they expire.
+//! - **Origin validation** — [`OriginLayer`](origin::OriginLayer) blocks DNS
+//!   rebinding with an origin allow-list and handles CORS for browser clients.
+//! - **Rate limiting** — [`RateLimitLayer`](rate_limit::RateLimitLayer) applies
+//!   per-principal token-bucket quotas to tool calls and list operations.
+//! - **Audit logging** — [`AuditLayer`](audit::AuditLayer) records who called
+//!   which tool with what (redacted) arguments, and the outcome.
+//! - **Server builder** — [`McpServerBuilder`](server::McpServerBuilder)
+//!   assembles the MCP endpoint, auth, metadata, health probes and graceful
+//!   shutdown into one router (feature `server`).
+//! - **Tool authorization** — the [`Claims`](tool::Claims) extractor hands
+//!   tools the caller's claims, and
+//!   [`require_scope`](tool::ToolRouterExt::require_scope) guards tools by
+//!   scope (feature `server`).
+//! - **Composition** — [`AuthenticatorExt::or`](auth::AuthenticatorExt::or)
+//!   chains authenticators so one layer accepts several credential types.
 //!
 //! ## Example
 //!
@@ -28,7 +64,7 @@
 //!     authorization_servers: vec!["https://auth.example.com".into()],
 //!     scopes_supported: Some(vec!["mcp:tools".into()]),
 //!     bearer_methods_supported: Some(vec!["header".into()]),
-//!     resource_documentation: None,
+//!     ..Default::default()
 //! };
 //!
 //! let rs_config = ResourceServerConfig {
@@ -54,4 +90,13 @@
 
 pub use axum;
 
+pub mod audit;
 pub mod auth;
+pub mod origin;
+pub mod rate_limit;
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(feature = "server")]
+pub mod tool;
+
+mod jsonrpc;
@@ -0,0 +1,375 @@
+//! Origin validation and CORS for the MCP streamable HTTP transport.
+//!
+//! The MCP transport spec requires servers to validate `Origin` to prevent
+//! DNS rebinding attacks. [`OriginLayer`] rejects requests from origins
+//! outside an allow-list, answers CORS preflight requests, and exposes the
+//! response headers browser clients need (`Mcp-Session-Id`,
+//! `WWW-Authenticate`, ...).
+//!
+//! Add it outermost, so preflight requests skip authentication and rejected
+//! requests still carry CORS headers:
+//!
+//! ```rust,ignore
+//! use rmcp_axum::origin::OriginLayer;
+//!
+//! let app = axum::Router::new()
+//!     .nest_service("/mcp", mcp_service)
+//!     .merge(resource.router())
+//!     .layer(AuthLayer::new(BearerAuth::new(jwt)))
+//!     .layer(OriginLayer::new(["https://app.example.com"]));
+//! ```
+
+use crate::jsonrpc;
+use axum::body::Body;
+use futures::future::BoxFuture;
+use http::{HeaderName, HeaderValue, Method, Request, Response, StatusCode, header};
+use std::{
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+/// Request headers MCP clients send, allowed in preflight responses.
+const ALLOW_HEADERS: &[&str] = &[
+    "authorization",
+    "content-type",
+    "dpop",
+    "last-event-id",
+    "mcp-protocol-version",
+    "mcp-session-id",
+];
+
+/// Response headers MCP clients read, exposed to browser scripts.
+const EXPOSE_HEADERS: &str = "mcp-session-id, mcp-protocol-version, www-authenticate, retry-after";
+
+/// Methods of the streamable HTTP transport.
+const ALLOW_METHODS: &str = "GET, POST, DELETE, OPTIONS";
+
+/// Tower [`Layer`](tower::Layer) that applies [`OriginService`].
+///
+/// Requests without an `Origin` header come from non-browser clients and
+/// pass through unchanged.
+#[derive(Clone)]
+pub struct OriginLayer {
+    policy: Policy,
+}
+
+#[derive(Clone)]
+struct Policy {
+    /// Allowed origins, lowercased without a trailing slash.
+    origins: Vec<String>,
+    /// Allow any loopback origin and require a loopback `Host`.
+    loopback: bool,
+    allow_headers: HeaderValue,
+    max_age: Duration,
+}
+
+impl OriginLayer {
+    /// Allow browser requests from `origins`, e.g. `https://app.example.com`.
+    pub fn new<I>(origins: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        Self::with_policy(false, origins)
+    }
+
+    /// Safe defaults for a server bound to localhost.
+    ///
+    /// Browser requests must come from a loopback origin (`localhost`,
+    /// `127.0.0.0/8` or `[::1]`, any port), and every request must address
+    /// the server by a loopback `Host`, so a page on a rebound domain can't
+    /// reach it even with a same-origin request.
+    pub fn localhost() -> Self {
+        Self::with_policy(true, std::iter::empty::<String>())
+    }
+
+    fn with_policy<I>(loopback: bool, origins: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        Self {
+            policy: Policy {
+                origins: origins.into_iter().map(|o| normalize(&o.into())).collect(),
+                loopback,
+                allow_headers: HeaderValue::from_str(&ALLOW_HEADERS.join(", "))
+                    .expect("valid header list"),
+                max_age: Duration::from_secs(2 * 60 * 60),
+            },
+        }
+    }
+
+    /// Also allow `origin`.
+    pub fn allow_origin(mut self, origin: impl Into<String>) -> Self {
+        self.policy.origins.push(normalize(&origin.into()));
+        self
+    }
+
+    /// Also allow the request header `name` in preflight requests.
+    pub fn allow_header(mut self, name: HeaderName) -> Self {
+        let value = format!(
+            "{}, {name}",
+            self.policy.allow_headers.to_str().unwrap_or_default()
+        );
+        self.policy.allow_headers = HeaderValue::from_str(&value).expect("valid header list");
+        self
+    }
+
+    /// How long browsers may cache preflight results. Defaults to two hours.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.policy.max_age = max_age;
+        self
+    }
+}
+
+impl Policy {
+    fn allows_origin(&self, origin: &str) -> bool {
+        let origin = normalize(origin);
+        self.origins.contains(&origin) || (self.loopback && is_loopback_origin(&origin))
+    }
+
+    fn allows_host<B>(&self, req: &Request<B>) -> bool {
+        if !self.loopback {
+            return true;
+        }
+        // HTTP/2 requests carry the host in the URI authority instead.
+        let host = match req.headers().get(header::HOST) {
+            Some(host) => host.to_str().ok(),
+            None => req.uri().authority().map(|a| a.as_str()),
+        };
+        host.and_then(|h| url::Url::parse(&format!("http://{h}")).ok())
+            .is_some_and(|url| is_loopback(&url))
+    }
+}
+
+fn normalize(origin: &str) -> String {
+    origin.trim_end_matches('/').to_ascii_lowercase()
+}
+
+fn is_loopback_origin(origin: &str) -> bool {
+    url::Url::parse(origin)
+        .is_ok_and(|url| matches!(url.scheme(), "http" | "https") && is_loopback(&url))
+}
+
+fn is_loopback(url: &url::Url) -> bool {
+    match url.host() {
+        Some(url::Host::Domain(domain)) => domain == "localhost",
+        Some(url::Host::Ipv4(ip)) => ip.is_loopback(),
+        Some(url::Host::Ipv6(ip)) => ip.is_loopback(),
+        None => false,
+    }
+}
+
+impl<S> tower::Layer<S> for OriginLayer {
+    type Service = OriginService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        OriginService {
+            policy: Arc::new(self.policy.clone()),
+            inner,
+        }
+    }
+}
+
+/// Tower service that validates `Origin` and adds CORS headers.
+#[derive(Clone)]
+pub struct OriginService<S> {
+    policy: Arc<Policy>,
+    inner: S,
+}
+
+impl<S, B> tower::Service<Request<B>> for OriginService<S>
+where
+    S: tower::Service<Request<B>, Response = Response<Body>> + Clone + Send + 'static,
+    S::Future: Send,
+    S::Error: Send,
+    B: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<B>) -> Self::Future {
+        let policy = self.policy.clone();
+        let mut inner = self.inner.clone();
+        // swap to ensure poll_ready state is preserved
+        std::mem::swap(&mut self.inner, &mut inner);
+
+        Box::pin(async move {
+            if !policy.allows_host(&req) {
+                return Ok(forbidden("host not allowed"));
+            }
+            let Some(origin) = req.headers().get(header::ORIGIN).cloned() else {
+                return inner.call(req).await;
+            };
+            if !origin.to_str().is_ok_and(|o| policy.allows_origin(o)) {
+                return Ok(forbidden("origin not allowed"));
+            }
+
+            let preflight = req.method() == Method::OPTIONS
+                && req
+                    .headers()
+                    .contains_key(header::ACCESS_CONTROL_REQUEST_METHOD);
+            if preflight {
+                let mut resp = Response::builder()
+                    .status(StatusCode::NO_CONTENT)
+                    .header(header::ACCESS_CONTROL_ALLOW_METHODS, ALLOW_METHODS)
+                    .header(
+                        header::ACCESS_CONTROL_ALLOW_HEADERS,
+                        policy.allow_headers.clone(),
+                    )
+                    .header(header::ACCESS_CONTROL_MAX_AGE, policy.max_age.as_secs())
+                    .body(Body::empty())
+                    .expect("valid response");
+                allow(&mut resp, origin);
+                return Ok(resp);
+            }
+
+            let mut resp = inner.call(req).await?;
+            resp.headers_mut().insert(
+                header::ACCESS_CONTROL_EXPOSE_HEADERS,
+                HeaderValue::from_static(EXPOSE_HEADERS),
+            );
+            allow(&mut resp, origin);
+            Ok(resp)
+        })
+    }
+}
+
+fn allow(resp: &mut Response<Body>, origin: HeaderValue) {
+    let headers = resp.headers_mut();
+    headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+    headers.append(header::VARY, HeaderValue::from_static("origin"));
+}
+
+fn forbidden(message: &str) -> Response<Body> {
+    let body = jsonrpc::error(None, jsonrpc::SERVER_ERROR_CODE, message, None);
+    Response::builder()
+        .status(StatusCode::FORBIDDEN)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .expect("valid response")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OriginLayer;
+    use axum::{Router, body::Body, routing::post};
+    use http::{Request, StatusCode, header};
+    use tower::ServiceExt;
+
+    fn app(layer: OriginLayer) -> Router {
+        Router::new()
+            .route("/mcp", post(|| async { "ok" }))
+            .layer(layer)
+    }
+
+    fn request(method: &str, host: &str, origin: Option<&str>) -> Request<Body> {
+        let mut req = Request::builder()
+            .method(method)
+            .uri("/mcp")
+            .header(header::HOST, host);
+        if let Some(origin) = origin {
+            req = req.header(header::ORIGIN, origin);
+        }
+        if method == "OPTIONS" {
+            req = req.header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST");
+        }
+        req.body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn enforces_allow_list() {
+        let app = app(OriginLayer::new(["https://App.example.com/"]));
+
+        let resp = app
+            .clone()
+            .oneshot(request(
+                "POST",
+                "mcp.example.com",
+                Some("https://app.example.com"),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://app.example.com"
+        );
+        let exposed = resp.headers()[header::ACCESS_CONTROL_EXPOSE_HEADERS]
+            .to_str()
+            .unwrap();
+        assert!(exposed.contains("mcp-session-id") && exposed.contains("www-authenticate"));
+
+        let resp = app
+            .clone()
+            .oneshot(request(
+                "POST",
+                "mcp.example.com",
+                Some("https://evil.example"),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        // Non-browser clients send no Origin.
+        let resp = app
+            .oneshot(request("POST", "mcp.example.com", None))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn answers_preflight() {
+        let app = app(OriginLayer::new(["https://app.example.com"]));
+        let resp = app
+            .oneshot(request(
+                "OPTIONS",
+                "mcp.example.com",
+                Some("https://app.example.com"),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        let allowed = resp.headers()[header::ACCESS_CONTROL_ALLOW_HEADERS]
+            .to_str()
+            .unwrap();
+        assert!(allowed.contains("mcp-session-id") && allowed.contains("authorization"));
+        assert!(resp.headers().contains_key(header::ACCESS_CONTROL_MAX_AGE));
+    }
+
+    #[tokio::test]
+    async fn localhost_rejects_rebound_hosts() {
+        let app = app(OriginLayer::localhost());
+        let status = |host: &'static str, origin: Option<&'static str>| {
+            let app = app.clone();
+            async move {
+                app.oneshot(request("POST", host, origin))
+                    .await
+                    .unwrap()
+                    .status()
+            }
+        };
+
+        assert_eq!(
+            status("127.0.0.1:8080", Some("http://localhost:3000")).await,
+            StatusCode::OK
+        );
+        assert_eq!(status("[::1]:8080", None).await, StatusCode::OK);
+        assert_eq!(
+            status("localhost:8080", Some("http://evil.example")).await,
+            StatusCode::FORBIDDEN
+        );
+        // A rebound domain resolving to 127.0.0.1, even same-origin.
+        assert_eq!(
+            status("evil.example:8080", None).await,
+            StatusCode::FORBIDDEN
+        );
+    }
+}
@@ -0,0 +1,693 @@
+//! Per-principal rate limiting for MCP requests.
+//!
+//! [`RateLimitLayer`] reads the claims inserted by
+//! [`AuthLayer`](crate::auth::AuthLayer), inspects the JSON-RPC method of
+//! each request, and applies token-bucket [`Quota`]s keyed by the caller's
+//! [`Principal`]. Separate quotas can be set for `tools/call`, list
+//! operations, individual tools, and all requests.
+//!
+//! Rejected requests get `429 Too Many Requests` with `Retry-After` and a
+//! JSON-RPC error body. With [`json_rpc_errors`](RateLimitLayer::json_rpc_errors)
+//! they instead get a `200` carrying a JSON-RPC error for the request id, so
+//! clients surface the failure on the individual call. A batch is limited
+//! as a whole and rejected with one error per request in it.
+//!
+//! Limiter state lives in a [`RateLimitStore`]; [`InMemoryStore`] is used by
+//! default. Implement the trait to share limits across replicas.
+//!
+//! ```rust,ignore
+//! use rmcp_axum::auth::{AuthLayer, BearerAuth, oauth::OAuthClaims};
+//! use rmcp_axum::rate_limit::{Quota, RateLimitLayer, RateLimits};
+//!
+//! let limits = RateLimits::new()
+//!     .tool_calls(Quota::per_minute(60))
+//!     .lists(Quota::per_second(5))
+//!     .tool("expensive_search", Quota::per_hour(100));
+//!
+//! // Layers run outside-in: authenticate first, then rate limit.
+//! let app = axum::Router::new()
+//!     .nest_service("/mcp", mcp_service)
+//!     .layer(RateLimitLayer::<OAuthClaims>::new(limits))
+//!     .layer(AuthLayer::new(BearerAuth::new(validator)));
+//! ```
+
+use crate::{
+    auth::Principal,
+    jsonrpc::{self, Message},
+};
+use axum::body::Body;
+use futures::future::BoxFuture;
+use http::{Request, Response, StatusCode, header};
+use std::{
+    collections::HashMap,
+    marker::PhantomData,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+/// Default number of buckets [`InMemoryStore`] keeps before evicting idle
+/// ones.
+const DEFAULT_MAX_ENTRIES: usize = 100_000;
+
+/// A token-bucket quota: `requests` per `period`, with bursts of up to
+/// [`burst`](Self::burst) requests.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quota {
+    requests: u32,
+    period: Duration,
+    burst: u32,
+}
+
+impl Quota {
+    /// Allow `requests` per `period`. The burst size defaults to `requests`.
+    ///
+    /// # Panics
+    ///
+    /// If `requests` is zero or `period` is zero.
+    pub fn new(requests: u32, period: Duration) -> Self {
+        assert!(requests > 0, "quota must allow at least one request");
+        assert!(!period.is_zero(), "quota period must be non-zero");
+        Self {
+            requests,
+            period,
+            burst: requests,
+        }
+    }
+
+    /// Allow `requests` per second.
+    pub fn per_second(requests: u32) -> Self {
+        Self::new(requests, Duration::from_secs(1))
+    }
+
+    /// Allow `requests` per minute.
+    pub fn per_minute(requests: u32) -> Self {
+        Self::new(requests, Duration::from_secs(60))
+    }
+
+    /// Allow `requests` per hour.
+    pub fn per_hour(requests: u32) -> Self {
+        Self::new(requests, Duration::from_secs(3600))
+    }
+
+    /// Maximum number of requests allowed back to back.
+    pub fn burst(mut self, burst: u32) -> Self {
+        self.burst = burst.max(1);
+        self
+    }
+
+    /// Bucket capacity.
+    pub fn capacity(&self) -> u32 {
+        self.burst
+    }
+
+    /// Tokens refilled per second.
+    pub fn refill_rate(&self) -> f64 {
+        f64::from(self.requests) / self.period.as_secs_f64()
+    }
+}
+
+/// The quotas applied by [`RateLimitLayer`].
+///
+/// Every matching quota must admit a request; a `tools/call` for a tool with
+/// its own quota consumes from the tool, `tools/call` and overall buckets.
+#[derive(Clone, Debug, Default)]
+pub struct RateLimits {
+    requests: Option<Quota>,
+    tool_calls: Option<Quota>,
+    lists: Option<Quota>,
+    tools: HashMap<String, Quota>,
+}
+
+impl RateLimits {
+    /// No limits.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit all JSON-RPC requests.
+    pub fn requests(mut self, quota: Quota) -> Self {
+        self.requests = Some(quota);
+        self
+    }
+
+    /// Limit `tools/call` requests.
+    pub fn tool_calls(mut self, quota: Quota) -> Self {
+        self.tool_calls = Some(quota);
+        self
+    }
+
+    /// Limit list operations (`tools/list`, `resources/list`, ...).
+    pub fn lists(mut self, quota: Quota) -> Self {
+        self.lists = Some(quota);
+        self
+    }
+
+    /// Limit calls to one tool.
+    pub fn tool(mut self, name: impl Into<String>, quota: Quota) -> Self {
+        self.tools.insert(name.into(), quota);
+        self
+    }
+
+    /// Bucket names and quotas that apply to `message`.
+    fn quotas(&self, message: &Message) -> Vec<(String, Quota)> {
+        let method = message.method.as_deref().unwrap_or_default();
+        let mut quotas = Vec::new();
+        if let Some(quota) = self.requests {
+            quotas.push(("requests".to_owned(), quota));
+        }
+        if method == "tools/call" {
+            if let Some(quota) = self.tool_calls {
+                quotas.push((method.to_owned(), quota));
+            }
+            if let Some(name) = message.target()
+                && let Some(quota) = self.tools.get(name)
+            {
+                quotas.push((format!("tool:{name}"), *quota));
+            }
+        } else if method.ends_with("/list")
+            && let Some(quota) = self.lists
+        {
+            quotas.push(("list".to_owned(), quota));
+        }
+        quotas
+    }
+}
+
+/// Storage for token buckets.
+///
+/// A request is admitted only if every bucket it draws from has enough
+/// tokens, so the layer [`check`](Self::check)s all buckets before
+/// [`commit`](Self::commit)ting to any of them.
+pub trait RateLimitStore: Send + Sync + 'static {
+    /// Check that the bucket `key` governed by `quota` holds `tokens` tokens,
+    /// without taking them.
+    ///
+    /// Returns how long until they are available otherwise.
+    fn check(
+        &self,
+        key: &str,
+        quota: &Quota,
+        tokens: u32,
+    ) -> impl Future<Output = Result<(), Duration>> + Send;
+
+    /// Take `tokens` tokens from the bucket `key` governed by `quota`.
+    ///
+    /// Concurrent requests may overdraw the bucket between a check and its
+    /// commit; the deficit delays later requests.
+    fn commit(&self, key: &str, quota: &Quota, tokens: u32) -> impl Future<Output = ()> + Send;
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    /// When the bucket will be full again; full buckets can be evicted.
+    full_at: Instant,
+}
+
+/// Process-local [`RateLimitStore`].
+pub struct InMemoryStore {
+    buckets: Mutex<HashMap<String, Bucket>>,
+    max_entries: usize,
+}
+
+impl Default for InMemoryStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InMemoryStore {
+    /// Create an empty store.
+    pub fn new() -> Self {
+        Self {
+            buckets: Mutex::new(HashMap::new()),
+            max_entries: DEFAULT_MAX_ENTRIES,
+        }
+    }
+
+    /// Number of buckets after which full ones are evicted (default 100,000).
+    pub fn max_entries(mut self, max: usize) -> Self {
+        self.max_entries = max;
+        self
+    }
+
+    /// Refill the bucket `key` up to now and pass it to `f`.
+    fn with_bucket<T>(&self, key: &str, quota: &Quota, f: impl FnOnce(&mut Bucket) -> T) -> T {
+        let now = Instant::now();
+        let capacity = f64::from(quota.capacity());
+
+        let mut buckets = self.buckets.lock().expect("rate limit buckets poisoned");
+        if buckets.len() >= self.max_entries && !buckets.contains_key(key) {
+            buckets.retain(|_, b| b.full_at > now);
+        }
+        let bucket = buckets.entry(key.to_owned()).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+            full_at: now,
+        });
+
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * quota.refill_rate()).min(capacity);
+        bucket.updated = now;
+        f(bucket)
+    }
+}
+
+impl RateLimitStore for InMemoryStore {
+    async fn check(&self, key: &str, quota: &Quota, tokens: u32) -> Result<(), Duration> {
+        let needed = f64::from(tokens);
+        self.with_bucket(key, quota, |bucket| {
+            if bucket.tokens < needed {
+                return Err(Duration::from_secs_f64(
+                    (needed - bucket.tokens) / quota.refill_rate(),
+                ));
+            }
+            Ok(())
+        })
+    }
+
+    async fn commit(&self, key: &str, quota: &Quota, tokens: u32) {
+        let capacity = f64::from(quota.capacity());
+        self.with_bucket(key, quota, |bucket| {
+            bucket.tokens -= f64::from(tokens);
+            bucket.full_at = bucket.updated
+                + Duration::from_secs_f64((capacity - bucket.tokens) / quota.refill_rate());
+        })
+    }
+}
+
+/// Tower [`Layer`](tower::Layer) that applies [`RateLimitService`].
+///
+/// `C` is the claims type inserted by the authenticator. Requests without
+/// claims pass through unlimited, so place this layer inside
+/// [`AuthLayer`](crate::auth::AuthLayer).
+pub struct RateLimitLayer<C, S = InMemoryStore> {
+    limits: Arc<RateLimits>,
+    store: Arc<S>,
+    json_rpc_errors: bool,
+    _claims: PhantomData<fn() -> C>,
+}
+
+impl<C, S> Clone for RateLimitLayer<C, S> {
+    fn clone(&self) -> Self {
+        Self {
+            limits: self.limits.clone(),
+            store: self.store.clone(),
+            json_rpc_errors: self.json_rpc_errors,
+            _claims: PhantomData,
+        }
+    }
+}
+
+impl<C> RateLimitLayer<C> {
+    /// Apply `limits` with an [`InMemoryStore`].
+    pub fn new(limits: RateLimits) -> Self {
+        Self {
+            limits: Arc::new(limits),
+            store: Arc::new(InMemoryStore::new()),
+            json_rpc_errors: false,
+            _claims: PhantomData,
+        }
+    }
+}
+
+impl<C, S> RateLimitLayer<C, S> {
+    /// Keep limiter state in `store` instead.
+    pub fn with_store<T: RateLimitStore>(self, store: T) -> RateLimitLayer<C, T> {
+        RateLimitLayer {
+            limits: self.limits,
+            store: Arc::new(store),
+            json_rpc_errors: self.json_rpc_errors,
+            _claims: PhantomData,
+        }
+    }
+
+    /// Reject with `200` and a JSON-RPC error for the request id instead of
+    /// `429`.
+    pub fn json_rpc_errors(mut self) -> Self {
+        self.json_rpc_errors = true;
+        self
+    }
+}
+
+impl<C, S, I> tower::Layer<I> for RateLimitLayer<C, S> {
+    type Service = RateLimitService<C, S, I>;
+
+    fn layer(&self, inner: I) -> Self::Service {
+        RateLimitService {
+            layer: self.clone(),
+            inner,
+        }
+    }
+}
+
+/// Tower service that enforces [`RateLimits`] per principal.
+pub struct RateLimitService<C, S, I> {
+    layer: RateLimitLayer<C, S>,
+    inner: I,
+}
+
+impl<C, S, I: Clone> Clone for RateLimitService<C, S, I> {
+    fn clone(&self) -> Self {
+        Self {
+            layer: self.layer.clone(),
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<C, S, I> tower::Service<Request<Body>> for RateLimitService<C, S, I>
+where
+    C: Principal + Send + Sync + 'static,
+    S: RateLimitStore,
+    I: tower::Service<Request<Body>, Response = Response<Body>> + Clone + Send + 'static,
+    I::Future: Send,
+    I::Error: Send,
+{
+    type Response = I::Response;
+    type Error = I::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let layer = self.layer.clone();
+        let mut inner = self.inner.clone();
+        // swap to ensure poll_ready state is preserved
+        std::mem::swap(&mut self.inner, &mut inner);
+
+        Box::pin(async move {
+            let Some(principal) = req
+                .extensions()
+                .get::<C>()
+                .map(|c| c.principal().to_owned())
+            else {
+                return inner.call(req).await;
+            };
+            let (parts, bytes) = match jsonrpc::buffer(req).await {
+                Ok(buffered) => buffered,
+                Err(response) => return Ok(response),
+            };
+
+            // Tokens needed per bucket across the batch.
+            let messages = jsonrpc::parse(&bytes);
+            let requests: Vec<&Message> = messages.iter().filter(|m| m.is_request()).collect();
+            let mut costs: Vec<(String, Quota, u32)> = Vec::new();
+            for message in &requests {
+                for (bucket, quota) in layer.limits.quotas(message) {
+                    let key = format!("{principal}\n{bucket}");
+                    match costs.iter_mut().find(|(k, ..)| *k == key) {
+                        Some((_, _, tokens)) => *tokens += 1,
+                        None => costs.push((key, quota, 1)),
+                    }
+                }
+            }
+            for (key, quota, tokens) in &costs {
+                if let Err(retry_after) = layer.store.check(key, quota, *tokens).await {
+                    let batch = jsonrpc::is_batch(&bytes);
+                    return Ok(rejection(
+                        &requests,
+                        batch,
+                        retry_after,
+                        layer.json_rpc_errors,
+                    ));
+                }
+            }
+            for (key, quota, tokens) in &costs {
+                layer.store.commit(key, quota, *tokens).await;
+            }
+
+            inner
+                .call(Request::from_parts(parts, Body::from(bytes)))
+                .await
+        })
+    }
+}
+
+/// Reject `requests`, answering each with an error, in an array if they
+/// came in a `batch`.
+fn rejection(
+    requests: &[&Message],
+    batch: bool,
+    retry_after: Duration,
+    json_rpc_errors: bool,
+) -> Response<Body> {
+    // Round up so clients never retry early.
+    let secs = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+    let secs = secs.max(1);
+    let error = |message: &Message| {
+        jsonrpc::error(
+            message.id.as_ref(),
+            jsonrpc::SERVER_ERROR_CODE,
+            "rate limit exceeded",
+            Some(serde_json::json!({ "retryAfter": secs })),
+        )
+    };
+    let body = if batch {
+        requests.iter().map(|message| error(message)).collect()
+    } else {
+        error(requests[0])
+    };
+    let status = if json_rpc_errors {
+        StatusCode::OK
+    } else {
+        StatusCode::TOO_MANY_REQUESTS
+    };
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::RETRY_AFTER, secs)
+        .body(Body::from(body.to_string()))
+        .expect("valid response")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{InMemoryStore, Quota, RateLimitLayer, RateLimitStore, RateLimits};
+    use axum::{Extension, Router, body::Body, routing::post};
+    use http::{Request, StatusCode, header};
+    use std::time::Duration;
+    use tower::ServiceExt;
+
+    fn app(limits: RateLimits, user: &str) -> Router {
+        Router::new()
+            .route("/mcp", post(|| async { "ok" }))
+            .layer(RateLimitLayer::<String>::new(limits))
+            .layer(Extension(user.to_owned()))
+    }
+
+    fn call(method: &str, params: serde_json::Value) -> Request<Body> {
+        let body =
+            serde_json::json!({ "jsonrpc": "2.0", "id": 7, "method": method, "params": params });
+        Request::post("/mcp")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    fn tool_call(name: &str) -> Request<Body> {
+        call(
+            "tools/call",
+            serde_json::json!({ "name": name, "arguments": {} }),
+        )
+    }
+
+    #[tokio::test]
+    async fn bucket_refills_over_time() {
+        let store = InMemoryStore::new();
+        let quota = Quota::new(1, Duration::from_millis(50));
+        assert!(store.check("k", &quota, 1).await.is_ok());
+        assert!(store.check("k", &quota, 1).await.is_ok());
+        store.commit("k", &quota, 1).await;
+        let wait = store.check("k", &quota, 1).await.unwrap_err();
+        assert!(wait <= Duration::from_millis(50));
+        tokio::time::sleep(wait).await;
+        assert!(store.check("k", &quota, 1).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn rejected_requests_spend_no_quota() {
+        let limits = RateLimits::new()
+            .requests(Quota::per_minute(2))
+            .tool("search", Quota::per_minute(1));
+        let app = app(limits, "alice");
+
+        let ok = |resp: http::Response<Body>| resp.status() == StatusCode::OK;
+        assert!(ok(app.clone().oneshot(tool_call("search")).await.unwrap()));
+        // The tool quota rejects; the overall quota keeps its last token.
+        for _ in 0..3 {
+            assert!(!ok(app.clone().oneshot(tool_call("search")).await.unwrap()));
+        }
+        assert!(ok(app.clone().oneshot(tool_call("echo")).await.unwrap()));
+        assert!(!ok(app.oneshot(tool_call("echo")).await.unwrap()));
+    }
+
+    #[tokio::test]
+    async fn batches_need_tokens_for_every_request() {
+        let app = app(RateLimits::new().tool_calls(Quota::per_minute(2)), "alice");
+        let batch = |n: usize| {
+            let call = serde_json::json!({
+                "jsonrpc": "2.0", "id": 1, "method": "tools/call",
+                "params": { "name": "echo", "arguments": {} },
+            });
+            Request::post("/mcp")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(serde_json::json!(vec![call; n]).to_string()))
+                .unwrap()
+        };
+
+        let resp = app.clone().oneshot(batch(3)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        let resp = app.clone().oneshot(batch(2)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = app.oneshot(batch(1)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[tokio::test]
+    async fn limits_tool_calls_separately_from_lists() {
+        let app = app(RateLimits::new().tool_calls(Quota::per_minute(1)), "alice");
+
+        let resp = app.clone().oneshot(tool_call("echo")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let resp = app.clone().oneshot(tool_call("echo")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        let retry_after: u64 = resp.headers()[header::RETRY_AFTER]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!((1..=60).contains(&retry_after));
+
+        let resp = app
+            .oneshot(call("tools/list", serde_json::json!({})))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn per_tool_limits_and_principals_are_independent() {
+        let limits = RateLimits::new().tool("search", Quota::per_minute(1));
+        let alice = app(limits.clone(), "alice");
+
+        assert_eq!(
+            alice
+                .clone()
+                .oneshot(tool_call("search"))
+                .await
+                .unwrap()
+                .status(),
+            StatusCode::OK
+        );
+        assert_eq!(
+            alice
+                .clone()
+                .oneshot(tool_call("search"))
+                .await
+                .unwrap()
+                .status(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+        assert_eq!(
+            alice.oneshot(tool_call("echo")).await.unwrap().status(),
+            StatusCode::OK
+        );
+
+        // Each layer instance has its own store, so share one router.
+        let layer = RateLimitLayer::<String>::new(limits);
+        let route = Router::new()
+            .route("/mcp", post(|| async { "ok" }))
+            .layer(layer);
+        let for_user = |user: &str| route.clone().layer(Extension(user.to_owned()));
+        assert_eq!(
+            for_user("alice")
+                .oneshot(tool_call("search"))
+                .await
+                .unwrap()
+                .status(),
+            StatusCode::OK
+        );
+        assert_eq!(
+            for_user("bob")
+                .oneshot(tool_call("search"))
+                .await
+                .unwrap()
+                .status(),
+            StatusCode::OK
+        );
+    }
+
+    #[tokio::test]
+    async fn json_rpc_errors_mode_answers_in_band() {
+        let app = Router::new()
+            .route("/mcp", post(|| async { "ok" }))
+            .layer(
+                RateLimitLayer::<String>::new(RateLimits::new().requests(Quota::per_minute(1)))
+                    .json_rpc_errors(),
+            )
+            .layer(Extension("alice".to_owned()));
+
+        app.clone().oneshot(tool_call("echo")).await.unwrap();
+        let resp = app.oneshot(tool_call("echo")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["id"], 7);
+        assert_eq!(json["error"]["message"], "rate limit exceeded");
+    }
+
+    #[tokio::test]
+    async fn json_rpc_errors_mode_answers_every_request_of_a_batch() {
+        let app = Router::new()
+            .route("/mcp", post(|| async { "ok" }))
+            .layer(
+                RateLimitLayer::<String>::new(RateLimits::new().requests(Quota::per_minute(1)))
+                    .json_rpc_errors(),
+            )
+            .layer(Extension("alice".to_owned()));
+        let batch = serde_json::json!([
+            { "jsonrpc": "2.0", "id": 1, "method": "tools/list" },
+            { "jsonrpc": "2.0", "method": "notifications/initialized" },
+            { "jsonrpc": "2.0", "id": "two", "method": "prompts/list" },
+        ]);
+        let req = Request::post("/mcp")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(batch.to_string()))
+            .unwrap();
+
+        let resp = app.oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let ids: Vec<_> = json.as_array().unwrap().iter().map(|r| &r["id"]).collect();
+        assert_eq!(ids, [&serde_json::json!(1), &serde_json::json!("two")]);
+        assert_eq!(json[1]["error"]["message"], "rate limit exceeded");
+    }
+
+    #[tokio::test]
+    async fn requests_without_claims_pass_through() {
+        let app =
+            Router::new().route("/mcp", post(|| async { "ok" })).layer(
+                RateLimitLayer::<String>::new(RateLimits::new().requests(Quota::per_minute(1))),
+            );
+        for _ in 0..3 {
+            assert_eq!(
+                app.clone()
+                    .oneshot(tool_call("echo"))
+                    .await
+                    .unwrap()
+                    .status(),
+                StatusCode::OK
+            );
+        }
+    }
+}
@@ -0,0 +1,569 @@
+//! Ready-made axum router for an rmcp server.
+//!
+//! [`McpServerBuilder`] wires an rmcp [`ServerHandler`] into the streamable
+//! HTTP transport and adds the pieces every deployment needs: the auth layer,
+//! Protected Resource Metadata, health probes and graceful shutdown.
+//!
+//! ```rust,ignore
+//! use rmcp_axum::auth::{AuthLayer, BearerAuth};
+//! use rmcp_axum::server::McpServerBuilder;
+//! use tokio_util::sync::CancellationToken;
+//!
+//! let shutdown = CancellationToken::new();
+//! let app = McpServerBuilder::new(|| MyHandler::default())
+//!     .path("/mcp")
+//!     .session_idle_timeout(Duration::from_secs(600))
+//!     .protected_resource(resource.clone())
+//!     .auth(AuthLayer::new(BearerAuth::new(jwt)).with_protected_resource(resource))
+//!     .shutdown(shutdown.clone())
+//!     .build();
+//!
+//! axum::serve(listener, app)
+//!     .with_graceful_shutdown(shutdown.cancelled_owned())
+//!     .await?;
+//! ```
+
+use crate::{
+    auth::{
+        AuthLayer, Authenticator,
+        oauth::ProtectedResource,
+        revocation::{RevocableClaims, Revocation, RevocationStore},
+    },
+    origin::OriginLayer,
+};
+use axum::{
+    Router,
+    extract::{Request, State},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{Route, get},
+};
+use futures::future::BoxFuture;
+use http::StatusCode;
+use rmcp::{
+    ServerHandler,
+    transport::streamable_http_server::{
+        SessionId, SessionManager, StreamableHttpServerConfig, StreamableHttpService,
+        session::local::{LocalSessionManager, SessionConfig},
+    },
+};
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    sync::{Arc, Mutex, Weak},
+    time::Duration,
+};
+use tokio_util::sync::CancellationToken;
+
+/// Path of the liveness probe.
+pub const HEALTHZ_PATH: &str = "/healthz";
+
+/// Path of the readiness probe.
+pub const READYZ_PATH: &str = "/readyz";
+
+type RouterLayer = Box<dyn FnOnce(Router) -> Router + Send>;
+type ReadinessCheck = Arc<dyn Fn() -> BoxFuture<'static, bool> + Send + Sync>;
+type RevocationCheck = Arc<dyn Fn(Revocation) -> BoxFuture<'static, bool> + Send + Sync>;
+
+/// Header carrying the MCP session id.
+const SESSION_ID_HEADER: &str = "mcp-session-id";
+
+/// Revocation settings, with the claims and store types erased.
+struct Revocations {
+    claims: fn(&http::Extensions) -> Option<Vec<Revocation>>,
+    is_revoked: RevocationCheck,
+    sweep_interval: Duration,
+}
+
+/// Builder for an [`axum::Router`] serving an MCP server over streamable
+/// HTTP.
+///
+/// The built router serves:
+///
+/// - the MCP endpoint at [`path`](Self::path) (default `/mcp`), behind the
+///   configured [`layer`](Self::layer)s and [`auth`](Self::auth) layer;
+/// - Protected Resource Metadata, when a
+///   [`protected_resource`](Self::protected_resource) is set;
+/// - `/healthz`, which always answers 200 while the process is up;
+/// - `/readyz`, which answers 503 once [`shutdown`](Self::shutdown) is
+///   cancelled or the [`readiness`](Self::readiness) check fails.
+///
+/// Only the MCP endpoint is authenticated; probes and metadata stay public.
+pub struct McpServerBuilder<S, M = LocalSessionManager> {
+    factory: Arc<dyn Fn() -> S + Send + Sync>,
+    path: String,
+    session_manager: Arc<M>,
+    config: StreamableHttpServerConfig,
+    layers: Vec<RouterLayer>,
+    auth: Option<RouterLayer>,
+    resource: Option<ProtectedResource>,
+    readiness: Option<ReadinessCheck>,
+    revocations: Option<Revocations>,
+    origins: Option<OriginLayer>,
+}
+
+impl<S> McpServerBuilder<S>
+where
+    S: ServerHandler,
+{
+    /// Start building a router whose sessions are served by handlers from
+    /// `factory`, called once per session.
+    pub fn new(factory: impl Fn() -> S + Send + Sync + 'static) -> Self {
+        Self {
+            factory: Arc::new(factory),
+            path: "/mcp".into(),
+            session_manager: Arc::default(),
+            config: StreamableHttpServerConfig::default(),
+            layers: Vec::new(),
+            auth: None,
+            resource: None,
+            readiness: None,
+            revocations: None,
+            origins: None,
+        }
+    }
+
+    /// Close sessions that have been idle for `timeout`.
+    ///
+    /// Replaces the session store with a fresh [`LocalSessionManager`]; use
+    /// [`session_manager`](Self::session_manager) to supply a configured
+    /// store instead.
+    pub fn session_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.session_manager = Arc::new(LocalSessionManager {
+            session_config: SessionConfig {
+                keep_alive: Some(timeout),
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        self
+    }
+}
+
+impl<S, M> McpServerBuilder<S, M>
+where
+    S: ServerHandler,
+    M: SessionManager,
+{
+    /// Serve the MCP endpoint at `path` instead of `/mcp`.
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        self.path = path.into();
+        self
+    }
+
+    /// Store sessions in `session_manager` instead of the default in-memory
+    /// [`LocalSessionManager`].
+    pub fn session_manager<M2: SessionManager>(
+        self,
+        session_manager: Arc<M2>,
+    ) -> McpServerBuilder<S, M2> {
+        McpServerBuilder {
+            factory: self.factory,
+            path: self.path,
+            session_manager,
+            config: self.config,
+            layers: self.layers,
+            auth: self.auth,
+            resource: self.resource,
+            readiness: self.readiness,
+            revocations: self.revocations,
+            origins: self.origins,
+        }
+    }
+
+    /// Serve each request independently, without `Mcp-Session-Id` sessions.
+    pub fn stateless(mut self) -> Self {
+        self.config.stateful_mode = false;
+        self
+    }
+
+    /// Interval between SSE keep-alive pings, or `None` to disable them.
+    /// Defaults to 15 seconds.
+    pub fn sse_keep_alive(mut self, interval: Option<Duration>) -> Self {
+        self.config.sse_keep_alive = interval;
+        self
+    }
+
+    /// Terminate sessions and fail `/readyz` once `token` is cancelled.
+    ///
+    /// Pass the same token to
+    /// [`with_graceful_shutdown`](axum::serve::Serve::with_graceful_shutdown)
+    /// so open SSE streams don't hold the server up.
+    pub fn shutdown(mut self, token: CancellationToken) -> Self {
+        self.config.cancellation_token = token;
+        self
+    }
+
+    /// Serve the resource's Protected Resource Metadata (RFC 9728).
+    ///
+    /// Bind the auth layer to the same resource with
+    /// [`AuthLayer::with_protected_resource`] to enforce its audience.
+    pub fn protected_resource(mut self, resource: ProtectedResource) -> Self {
+        self.resource = Some(resource);
+        self
+    }
+
+    /// Authenticate requests to the MCP endpoint.
+    ///
+    /// The auth layer wraps every [`layer`](Self::layer), so those see the
+    /// caller's claims.
+    pub fn auth<A>(mut self, layer: AuthLayer<A>) -> Self
+    where
+        A: Authenticator,
+    {
+        self.auth = Some(Box::new(move |router: Router| router.layer(layer)));
+        self
+    }
+
+    /// Validate `Origin` and answer CORS preflight with `layer`.
+    ///
+    /// Covers the whole router, outside the auth layer, so preflight and
+    /// metadata requests from allowed browser origins succeed.
+    pub fn origins(mut self, layer: OriginLayer) -> Self {
+        self.origins = Some(layer);
+        self
+    }
+
+    /// Wrap the MCP endpoint in `layer`, e.g. a
+    /// [`RateLimitLayer`](crate::rate_limit::RateLimitLayer) or
+    /// [`AuditLayer`](crate::audit::AuditLayer).
+    ///
+    /// Layers added later wrap earlier ones, as with [`Router::layer`].
+    pub fn layer<L>(mut self, layer: L) -> Self
+    where
+        L: tower::Layer<Route> + Clone + Send + Sync + 'static,
+        L::Service: tower::Service<Request> + Clone + Send + Sync + 'static,
+        <L::Service as tower::Service<Request>>::Response: IntoResponse + 'static,
+        <L::Service as tower::Service<Request>>::Error: Into<Infallible> + 'static,
+        <L::Service as tower::Service<Request>>::Future: Send + 'static,
+    {
+        self.layers
+            .push(Box::new(move |router: Router| router.layer(layer)));
+        self
+    }
+
+    /// Report not ready on `/readyz` while `check` resolves to `false`, e.g.
+    /// until a backing database is reachable.
+    pub fn readiness<F, Fut>(mut self, check: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = bool> + Send + 'static,
+    {
+        self.readiness = Some(Arc::new(move || Box::pin(check())));
+        self
+    }
+
+    /// Terminate the open sessions of callers revoked in `store`.
+    ///
+    /// Sessions are tracked by the revocations of the claims of type `C` that
+    /// opened or used them, and checked against `store` every
+    /// `sweep_interval`. Pair this with
+    /// [`AuthLayer::with_revocation`] on the same store, which rejects the
+    /// revoked caller's further requests.
+    pub fn revocations<C, R>(mut self, store: Arc<R>, sweep_interval: Duration) -> Self
+    where
+        C: RevocableClaims + Send + Sync + 'static,
+        R: RevocationStore,
+    {
+        self.revocations = Some(Revocations {
+            claims: |extensions| extensions.get::<C>().map(C::revocations),
+            is_revoked: Arc::new(move |revocation| {
+                let store = store.clone();
+                Box::pin(async move { store.is_revoked(&revocation).await })
+            }),
+            sweep_interval,
+        });
+        self
+    }
+
+    /// Build the router.
+    ///
+    /// # Panics
+    ///
+    /// Panics outside a Tokio runtime when [`revocations`](Self::revocations)
+    /// are configured, as terminating sessions runs in a background task.
+    pub fn build(self) -> Router {
+        let factory = self.factory;
+        let shutdown = self.config.cancellation_token.clone();
+        let session_manager = self.session_manager.clone();
+        let service =
+            StreamableHttpService::new(move || Ok(factory()), self.session_manager, self.config);
+
+        let mut mcp = Router::new().route_service(&self.path, service);
+        if let Some(revocations) = self.revocations {
+            let tracker = Arc::new(SessionTracker {
+                claims: revocations.claims,
+                sessions: Mutex::default(),
+            });
+            tokio::spawn(terminate_revoked_sessions(
+                Arc::downgrade(&tracker),
+                session_manager,
+                revocations.is_revoked,
+                revocations.sweep_interval,
+                shutdown.clone(),
+            ));
+            mcp = mcp.layer(middleware::from_fn_with_state(tracker, track_session));
+        }
+        for layer in self.layers {
+            mcp = layer(mcp);
+        }
+        if let Some(auth) = self.auth {
+            mcp = auth(mcp);
+        }
+
+        let readiness = self.readiness;
+        let mut router = mcp.route(HEALTHZ_PATH, get(|| async { "ok" })).route(
+            READYZ_PATH,
+            get(move || ready(shutdown.clone(), readiness.clone())),
+        );
+        if let Some(resource) = self.resource {
+            router = router.merge(resource.router());
+        }
+        if let Some(origins) = self.origins {
+            router = router.layer(origins);
+        }
+        router
+    }
+}
+
+/// Revocations of the callers of each open session.
+struct SessionTracker {
+    claims: fn(&http::Extensions) -> Option<Vec<Revocation>>,
+    sessions: Mutex<HashMap<SessionId, Vec<Revocation>>>,
+}
+
+async fn track_session(
+    State(tracker): State<Arc<SessionTracker>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let revocations = (tracker.claims)(req.extensions());
+    let session = req.headers().get(SESSION_ID_HEADER).cloned();
+    let resp = next.run(req).await;
+
+    // New sessions only learn their id from the initialize response.
+    let session = session.or_else(|| resp.headers().get(SESSION_ID_HEADER).cloned());
+    if let Some(revocations) = revocations
+        && let Some(id) = session.as_ref().and_then(|v| v.to_str().ok())
+    {
+        tracker
+            .sessions
+            .lock()
+            .expect("session tracker poisoned")
+            .insert(id.into(), revocations);
+    }
+    resp
+}
+
+async fn terminate_revoked_sessions<M: SessionManager>(
+    tracker: Weak<SessionTracker>,
+    session_manager: Arc<M>,
+    is_revoked: RevocationCheck,
+    interval: Duration,
+    shutdown: CancellationToken,
+) {
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => return,
+            _ = tokio::time::sleep(interval) => {}
+        }
+        // Stop once the router is gone.
+        let Some(tracker) = tracker.upgrade() else {
+            return;
+        };
+        let sessions: Vec<_> = tracker
+            .sessions
+            .lock()
+            .expect("session tracker poisoned")
+            .iter()
+            .map(|(id, revocations)| (id.clone(), revocations.clone()))
+            .collect();
+
+        for (id, revocations) in sessions {
+            let mut revoked = false;
+            for revocation in revocations {
+                if is_revoked(revocation).await {
+                    revoked = true;
+                    break;
+                }
+            }
+            let open = session_manager.has_session(&id).await.unwrap_or(false);
+            if revoked && open {
+                match session_manager.close_session(&id).await {
+                    Ok(()) => tracing::info!("terminated session {id} of a revoked caller"),
+                    Err(e) => tracing::error!("failed to terminate revoked session {id}: {e}"),
+                }
+            }
+            if revoked || !open {
+                tracker
+                    .sessions
+                    .lock()
+                    .expect("session tracker poisoned")
+                    .remove(&id);
+            }
+        }
+    }
+}
+
+async fn ready(shutdown: CancellationToken, check: Option<ReadinessCheck>) -> impl IntoResponse {
+    if shutdown.is_cancelled() {
+        return (StatusCode::SERVICE_UNAVAILABLE, "shutting down");
+    }
+    if let Some(check) = check
+        && !check().await
+    {
+        return (StatusCode::SERVICE_UNAVAILABLE, "not ready");
+    }
+    (StatusCode::OK, "ok")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::McpServerBuilder;
+    use crate::auth::{
+        AuthLayer, BearerAuth, Validator,
+        revocation::{InMemoryRevocations, Revocation, RevocationStore},
+    };
+    use axum::{Router, body::Body};
+    use http::{Request, StatusCode, header};
+    use rmcp::{
+        ServerHandler,
+        transport::streamable_http_server::{SessionManager, session::local::LocalSessionManager},
+    };
+    use std::{
+        sync::{
+            Arc,
+            atomic::{AtomicBool, Ordering},
+        },
+        time::Duration,
+    };
+    use tokio_util::sync::CancellationToken;
+    use tower::ServiceExt;
+
+    #[derive(Clone, Default)]
+    struct Hello;
+
+    impl ServerHandler for Hello {}
+
+    #[derive(Clone)]
+    struct Secret;
+
+    impl Validator for Secret {
+        type Claims = String;
+        type Error = String;
+
+        async fn validate(&self, credential: &str) -> Result<String, String> {
+            if credential == "secret" {
+                Ok("alice".into())
+            } else {
+                Err("invalid".into())
+            }
+        }
+    }
+
+    fn initialize(token: Option<&str>) -> Request<Body> {
+        let body = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "initialize",
+            "params": {
+                "protocolVersion": "2025-06-18",
+                "capabilities": {},
+                "clientInfo": { "name": "test", "version": "0" },
+            },
+        });
+        let mut req = Request::post("/mcp")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::ACCEPT, "application/json, text/event-stream");
+        if let Some(token) = token {
+            req = req.header(header::AUTHORIZATION, format!("Bearer {token}"));
+        }
+        req.body(Body::from(body.to_string())).unwrap()
+    }
+
+    async fn status(app: &Router, path: &str) -> StatusCode {
+        let req = Request::get(path).body(Body::empty()).unwrap();
+        app.clone().oneshot(req).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn readyz_fails_after_shutdown() {
+        let shutdown = CancellationToken::new();
+        let app = McpServerBuilder::new(|| Hello)
+            .shutdown(shutdown.clone())
+            .build();
+        assert_eq!(status(&app, "/healthz").await, StatusCode::OK);
+        assert_eq!(status(&app, "/readyz").await, StatusCode::OK);
+
+        shutdown.cancel();
+        assert_eq!(status(&app, "/healthz").await, StatusCode::OK);
+        assert_eq!(
+            status(&app, "/readyz").await,
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+    }
+
+    #[tokio::test]
+    async fn readyz_runs_readiness_check() {
+        let ready = Arc::new(AtomicBool::new(false));
+        let flag = ready.clone();
+        let app = McpServerBuilder::new(|| Hello)
+            .readiness(move || {
+                let flag = flag.clone();
+                async move { flag.load(Ordering::SeqCst) }
+            })
+            .build();
+        assert_eq!(
+            status(&app, "/readyz").await,
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        ready.store(true, Ordering::SeqCst);
+        assert_eq!(status(&app, "/readyz").await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn auth_guards_only_the_mcp_endpoint() {
+        let app = McpServerBuilder::new(|| Hello)
+            .session_idle_timeout(Duration::from_secs(60))
+            .auth(AuthLayer::new(BearerAuth::new(Secret)))
+            .build();
+
+        let resp = app.clone().oneshot(initialize(None)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(status(&app, "/healthz").await, StatusCode::OK);
+
+        let resp = app
+            .clone()
+            .oneshot(initialize(Some("secret")))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(resp.headers().contains_key("mcp-session-id"));
+    }
+
+    #[tokio::test]
+    async fn revoked_sessions_are_terminated() {
+        let sessions = Arc::new(LocalSessionManager::default());
+        let revocations = Arc::new(InMemoryRevocations::new());
+        let app = McpServerBuilder::new(|| Hello)
+            .session_manager(sessions.clone())
+            .auth(AuthLayer::new(BearerAuth::new(Secret)).with_revocation(revocations.clone()))
+            .revocations::<String, _>(revocations.clone(), Duration::from_millis(10))
+            .build();
+
+        let resp = app
+            .clone()
+            .oneshot(initialize(Some("secret")))
+            .await
+            .unwrap();
+        let id: Arc<str> = resp.headers()["mcp-session-id"].to_str().unwrap().into();
+        assert!(sessions.has_session(&id).await.unwrap());
+
+        revocations
+            .revoke(Revocation::Sub("alice".into()))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(!sessions.has_session(&id).await.unwrap());
+    }
+}
@@ -0,0 +1,267 @@
+//! Authenticated claims inside rmcp tool handlers.
+//!
+//! [`AuthLayer`](crate::auth::AuthLayer) inserts the caller's claims into the
+//! HTTP request, which rmcp's streamable HTTP transport hands to tools as
+//! [`Parts`]. [`Claims`] extracts them directly, and
+//! [`ToolRouterExt::require_scope`] guards a tool behind a granted scope.
+//!
+//! ```rust,ignore
+//! use rmcp_axum::auth::oauth::OAuthClaims;
+//! use rmcp_axum::tool::{Claims, ToolRouterExt};
+//!
+//! #[tool_router]
+//! impl Files {
+//!     fn new() -> Self {
+//!         Self {
+//!             tool_router: Self::tool_router()
+//!                 .require_scope::<OAuthClaims>("write_file", "files:write"),
+//!         }
+//!     }
+//!
+//!     #[tool(description = "Who is calling")]
+//!     async fn whoami(&self, Claims(claims): Claims<OAuthClaims>) -> String {
+//!         claims.sub
+//!     }
+//! }
+//! ```
+
+use crate::auth::{AuthError, ScopedClaims};
+use futures::{FutureExt, future::BoxFuture};
+use http::request::Parts;
+use rmcp::{
+    ErrorData, RoleServer,
+    handler::server::{
+        common::{AsRequestContext, FromContextPart},
+        router::tool::ToolRouter,
+        tool::ToolCallContext,
+    },
+    model::{CallToolResult, ErrorCode},
+    service::RequestContext,
+};
+use std::sync::Arc;
+
+type ToolFuture<'a> = BoxFuture<'a, Result<CallToolResult, ErrorData>>;
+
+/// Extractor for the claims of the authenticated caller.
+///
+/// Fails with an `unauthorized` JSON-RPC error when the request carries no
+/// claims of type `C`, e.g. because the tool is served without an
+/// [`AuthLayer`](crate::auth::AuthLayer).
+#[derive(Clone, Debug)]
+pub struct Claims<C>(pub C);
+
+impl<C: ScopedClaims> Claims<C> {
+    /// Fail with an `insufficient_scope` JSON-RPC error unless `scope` was
+    /// granted.
+    pub fn require_scope(&self, scope: &str) -> Result<(), ErrorData> {
+        if self.0.has_scope(scope) {
+            Ok(())
+        } else {
+            Err(AuthError::InsufficientScope {
+                scope: scope.into(),
+            }
+            .into())
+        }
+    }
+}
+
+impl<X, C> FromContextPart<X> for Claims<C>
+where
+    X: AsRequestContext,
+    C: Clone + Send + Sync + 'static,
+{
+    fn from_context_part(context: &mut X) -> Result<Self, ErrorData> {
+        claims(context.as_request_context()).map(Claims)
+    }
+}
+
+fn claims<C>(context: &RequestContext<RoleServer>) -> Result<C, ErrorData>
+where
+    C: Clone + Send + Sync + 'static,
+{
+    context
+        .extensions
+        .get::<Parts>()
+        .and_then(|parts| parts.extensions.get::<C>())
+        .cloned()
+        .ok_or_else(|| AuthError::MissingCredentials.into())
+}
+
+/// Authorization helpers for an rmcp [`ToolRouter`].
+pub trait ToolRouterExt {
+    /// Reject calls to `tool` unless the caller's claims of type `C` grant
+    /// `scope`.
+    ///
+    /// The check runs before the tool's arguments are parsed, and fails
+    /// with the same JSON-RPC errors as [`Claims`] and
+    /// [`Claims::require_scope`]. Call it repeatedly to require several
+    /// scopes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the router has no tool named `tool`.
+    fn require_scope<C>(self, tool: &str, scope: impl Into<String>) -> Self
+    where
+        C: ScopedClaims + Clone + Send + Sync + 'static;
+}
+
+impl<S: Send + Sync + 'static> ToolRouterExt for ToolRouter<S> {
+    fn require_scope<C>(mut self, tool: &str, scope: impl Into<String>) -> Self
+    where
+        C: ScopedClaims + Clone + Send + Sync + 'static,
+    {
+        let route = self
+            .map
+            .get_mut(tool)
+            .unwrap_or_else(|| panic!("no tool named `{tool}` to require a scope for"));
+        let inner = route.call.clone();
+        let scope = scope.into();
+        route.call = Arc::new(move |context: ToolCallContext<'_, S>| -> ToolFuture<'_> {
+            let granted = claims::<C>(context.request_context())
+                .and_then(|claims| Claims(claims).require_scope(&scope));
+            match granted {
+                Ok(()) => inner(context),
+                Err(err) => std::future::ready(Err(err)).boxed(),
+            }
+        });
+        self
+    }
+}
+
+impl From<AuthError> for ErrorData {
+    /// Carries the same code, message and `data.error` as the HTTP error body
+    /// from [`AuthError::to_json`].
+    fn from(err: AuthError) -> Self {
+        let json = err.to_json();
+        let error = &json["error"];
+        ErrorData::new(
+            ErrorCode(error["code"].as_i64().unwrap_or_default() as i32),
+            err.description(),
+            Some(error["data"].clone()),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Claims, ToolRouterExt};
+    use crate::{
+        auth::{AuthLayer, BearerAuth, Validator, oauth::OAuthClaims},
+        server::McpServerBuilder,
+    };
+    use axum::{Router, body::Body};
+    use http::{Request, header};
+    use rmcp::{
+        ServerHandler, handler::server::router::tool::ToolRouter, tool, tool_handler, tool_router,
+    };
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    #[derive(Clone)]
+    struct Files {
+        tool_router: ToolRouter<Self>,
+    }
+
+    #[tool_router]
+    impl Files {
+        fn new() -> Self {
+            Self {
+                tool_router: Self::tool_router()
+                    .require_scope::<OAuthClaims>("write_file", "files:write"),
+            }
+        }
+
+        #[tool(description = "Who is calling")]
+        async fn whoami(&self, Claims(claims): Claims<OAuthClaims>) -> String {
+            claims.sub
+        }
+
+        #[tool(description = "Write a file")]
+        async fn write_file(&self) -> String {
+            "written".into()
+        }
+    }
+
+    #[tool_handler]
+    impl ServerHandler for Files {}
+
+    /// Accepts `<sub>:<scope>,<scope>...` as the token.
+    #[derive(Clone)]
+    struct Tokens;
+
+    impl Validator for Tokens {
+        type Claims = OAuthClaims;
+        type Error = String;
+
+        async fn validate(&self, token: &str) -> Result<OAuthClaims, String> {
+            let (sub, scope) = token.split_once(':').ok_or("malformed")?;
+            Ok(OAuthClaims {
+                sub: sub.into(),
+                scope: scope.split(',').map(String::from).collect(),
+                ..Default::default()
+            })
+        }
+    }
+
+    fn app(auth: bool) -> Router {
+        let builder = McpServerBuilder::new(Files::new).stateless();
+        if auth {
+            builder
+                .auth(AuthLayer::new(BearerAuth::new(Tokens)))
+                .build()
+        } else {
+            builder.build()
+        }
+    }
+
+    async fn call_tool(app: &Router, token: Option<&str>, tool: &str) -> Value {
+        let body = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "tools/call",
+            "params": { "name": tool, "arguments": {} },
+        });
+        let mut req = Request::post("/mcp")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::ACCEPT, "application/json, text/event-stream");
+        if let Some(token) = token {
+            req = req.header(header::AUTHORIZATION, format!("Bearer {token}"));
+        }
+        let req = req.body(Body::from(body.to_string())).unwrap();
+        let resp = app.clone().oneshot(req).await.unwrap();
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        let data = body
+            .lines()
+            .filter_map(|line| line.strip_prefix("data:"))
+            .find(|data| !data.trim().is_empty())
+            .unwrap_or(&body);
+        serde_json::from_str(data.trim()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn extracts_claims_in_tools() {
+        let resp = call_tool(&app(true), Some("alice:files:read"), "whoami").await;
+        assert_eq!(resp["result"]["content"][0]["text"], "alice");
+    }
+
+    #[tokio::test]
+    async fn missing_claims_are_unauthorized() {
+        let resp = call_tool(&app(false), None, "whoami").await;
+        assert_eq!(resp["error"]["code"], -32001);
+        assert_eq!(resp["error"]["data"]["error"], "unauthorized");
+    }
+
+    #[tokio::test]
+    async fn require_scope_guards_tool() {
+        let app = app(true);
+        let resp = call_tool(&app, Some("alice:files:read"), "write_file").await;
+        assert_eq!(resp["error"]["data"]["error"], "insufficient_scope");
+        assert_eq!(resp["error"]["message"], "requires scope: files:write");
+
+        let resp = call_tool(&app, Some("alice:files:read,files:write"), "write_file").await;
+        assert_eq!(resp["result"]["content"][0]["text"], "written");
+    }
+}
@@ -0,0 +1,11 @@
+//! Binary entry point for the rmcp-gateway CLI.
+
+use rmcp_gateway::cmd::App;
+
+#[tokio::main]
+async fn main() {
+    if let Err(e) = App::run().await {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,300 @@
+//! Bridging MCP servers between transports.
+//!
+//! A bridge is a [`Gateway`] over a single upstream whose names pass
+//! through unchanged, see [`GatewayConfig::bridge`](crate::GatewayConfig::bridge).
+//! Served over streamable HTTP, it exposes a stdio server remotely; served
+//! over stdio, it exposes a remote server to desktop hosts.
+//!
+//! HTTP sessions either share one gateway, and so one upstream process, or
+//! each get their own from a [`SessionGateway`]:
+//!
+//! ```rust,ignore
+//! use rmcp_axum::server::McpServerBuilder;
+//! use rmcp_gateway::{Gateway, GatewayConfig, bridge::SessionGateway, upstream::UpstreamConfig};
+//!
+//! let config = GatewayConfig::bridge(UpstreamConfig::stdio("time", "wmcp-time", ["--local"]));
+//! let session = SessionGateway::new(move || Gateway::builder().config(config.clone()));
+//! let app = McpServerBuilder::new(move || session.clone()).build();
+//! ```
+
+use crate::gateway::{Gateway, GatewayBuilder};
+use rmcp::{
+    ErrorData, RoleServer, ServerHandler,
+    model::{
+        CallToolRequestParams, CallToolResult, GetPromptRequestParams, GetPromptResult,
+        ListPromptsResult, ListResourceTemplatesResult, ListResourcesResult, ListToolsResult,
+        PaginatedRequestParams, ReadResourceRequestParams, ReadResourceResult, ServerInfo,
+        SetLevelRequestParams, SubscribeRequestParams, UnsubscribeRequestParams,
+    },
+    service::{NotificationContext, RequestContext},
+};
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+
+type BuilderFn = Arc<dyn Fn() -> GatewayBuilder + Send + Sync>;
+
+/// An MCP server that builds its own [`Gateway`] for the session it serves.
+///
+/// The gateway, and with it every upstream connection and child process,
+/// is started once the session is initialized and stopped when the
+/// session ends. Clones share the builder but not the gateway, so serve
+/// one clone per downstream session.
+pub struct SessionGateway {
+    builder: BuilderFn,
+    gateway: OnceCell<Gateway>,
+}
+
+impl SessionGateway {
+    /// Build each session's gateway from `builder`.
+    pub fn new(builder: impl Fn() -> GatewayBuilder + Send + Sync + 'static) -> Self {
+        Self {
+            builder: Arc::new(builder),
+            gateway: OnceCell::new(),
+        }
+    }
+
+    /// The session's gateway, started on first use.
+    async fn gateway(&self) -> Result<&Gateway, ErrorData> {
+        self.gateway
+            .get_or_try_init(|| (self.builder)().build())
+            .await
+            .map_err(|e| ErrorData::internal_error(format!("failed to start gateway: {e}"), None))
+    }
+}
+
+impl Clone for SessionGateway {
+    fn clone(&self) -> Self {
+        Self {
+            builder: self.builder.clone(),
+            gateway: OnceCell::new(),
+        }
+    }
+}
+
+impl ServerHandler for SessionGateway {
+    fn get_info(&self) -> ServerInfo {
+        match self.gateway.get() {
+            Some(gateway) => gateway.get_info(),
+            None => (self.builder)().info(),
+        }
+    }
+
+    async fn on_initialized(&self, context: NotificationContext<RoleServer>) {
+        match self.gateway().await {
+            Ok(gateway) => gateway.on_initialized(context).await,
+            Err(e) => tracing::error!("{}", e.message),
+        }
+    }
+
+    async fn list_tools(
+        &self,
+        request: Option<PaginatedRequestParams>,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, ErrorData> {
+        self.gateway().await?.list_tools(request, context).await
+    }
+
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParams,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, ErrorData> {
+        self.gateway().await?.call_tool(request, context).await
+    }
+
+    async fn list_prompts(
+        &self,
+        request: Option<PaginatedRequestParams>,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ListPromptsResult, ErrorData> {
+        self.gateway().await?.list_prompts(request, context).await
+    }
+
+    async fn get_prompt(
+        &self,
+        request: GetPromptRequestParams,
+        context: RequestContext<RoleServer>,
+    ) -> Result<GetPromptResult, ErrorData> {
+        self.gateway().await?.get_prompt(request, context).await
+    }
+
+    async fn list_resources(
+        &self,
+        request: Option<PaginatedRequestParams>,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ListResourcesResult, ErrorData> {
+        self.gateway().await?.list_resources(request, context).await
+    }
+
+    async fn list_resource_templates(
+        &self,
+        request: Option<PaginatedRequestParams>,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ListResourceTemplatesResult, ErrorData> {
+        self.gateway()
+            .await?
+            .list_resource_templates(request, context)
+            .await
+    }
+
+    async fn read_resource(
+        &self,
+        request: ReadResourceRequestParams,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ReadResourceResult, ErrorData> {
+        self.gateway().await?.read_resource(request, context).await
+    }
+
+    async fn subscribe(
+        &self,
+        request: SubscribeRequestParams,
+        context: RequestContext<RoleServer>,
+    ) -> Result<(), ErrorData> {
+        self.gateway().await?.subscribe(request, context).await
+    }
+
+    async fn unsubscribe(
+        &self,
+        request: UnsubscribeRequestParams,
+        context: RequestContext<RoleServer>,
+    ) -> Result<(), ErrorData> {
+        self.gateway().await?.unsubscribe(request, context).await
+    }
+
+    async fn set_level(
+        &self,
+        request: SetLevelRequestParams,
+        context: RequestContext<RoleServer>,
+    ) -> Result<(), ErrorData> {
+        self.gateway().await?.set_level(request, context).await
+    }
+
+    async fn on_roots_list_changed(&self, context: NotificationContext<RoleServer>) {
+        if let Some(gateway) = self.gateway.get() {
+            gateway.on_roots_list_changed(context).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SessionGateway;
+    use crate::{Gateway, GatewayConfig, upstream::UpstreamConfig};
+    use rmcp::{
+        RoleClient, ServerHandler, ServiceExt,
+        handler::server::router::tool::ToolRouter,
+        model::{CallToolRequestParams, ServerCapabilities, ServerInfo},
+        service::RunningService,
+        tool, tool_handler, tool_router,
+        transport::{
+            StreamableHttpClientTransport,
+            streamable_http_server::session::local::LocalSessionManager,
+        },
+    };
+    use rmcp_axum::server::McpServerBuilder;
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    /// Counts the calls made on its own session.
+    #[derive(Clone)]
+    struct Counter {
+        calls: Arc<AtomicUsize>,
+        tool_router: ToolRouter<Self>,
+    }
+
+    #[tool_router]
+    impl Counter {
+        fn new() -> Self {
+            Self {
+                calls: Arc::default(),
+                tool_router: Self::tool_router(),
+            }
+        }
+
+        #[tool(description = "Count calls")]
+        async fn count(&self) -> String {
+            (self.calls.fetch_add(1, Ordering::SeqCst) + 1).to_string()
+        }
+    }
+
+    #[tool_handler]
+    impl ServerHandler for Counter {
+        fn get_info(&self) -> ServerInfo {
+            ServerInfo {
+                capabilities: ServerCapabilities::builder().enable_tools().build(),
+                ..Default::default()
+            }
+        }
+    }
+
+    /// Serve `router` on a free local port.
+    async fn serve(router: axum::Router) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/mcp", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        url
+    }
+
+    async fn client(url: &str) -> RunningService<RoleClient, ()> {
+        ().serve(StreamableHttpClientTransport::from_uri(url))
+            .await
+            .unwrap()
+    }
+
+    async fn count(client: &RunningService<RoleClient, ()>) -> String {
+        let result = client
+            .call_tool(CallToolRequestParams {
+                meta: None,
+                name: "count".into(),
+                arguments: None,
+                task: None,
+            })
+            .await
+            .unwrap();
+        result.content[0].as_text().unwrap().text.clone()
+    }
+
+    /// An upstream counter, and a count of its open sessions.
+    async fn upstream() -> (UpstreamConfig, Arc<LocalSessionManager>) {
+        let sessions = Arc::new(LocalSessionManager::default());
+        let url = serve(
+            McpServerBuilder::new(Counter::new)
+                .session_manager(sessions.clone())
+                .build(),
+        )
+        .await;
+        (UpstreamConfig::http("counter", url), sessions)
+    }
+
+    #[tokio::test]
+    async fn shared_bridge_shares_the_upstream() {
+        let (upstream, sessions) = upstream().await;
+        let gateway = Gateway::builder()
+            .config(GatewayConfig::bridge(upstream))
+            .build()
+            .await
+            .unwrap();
+        let url = serve(McpServerBuilder::new(move || gateway.clone()).build()).await;
+
+        let (a, b) = (client(&url).await, client(&url).await);
+        assert_eq!(count(&a).await, "1");
+        assert_eq!(count(&b).await, "2");
+        assert_eq!(sessions.sessions.read().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn session_gateways_connect_their_own_upstream() {
+        let (upstream, sessions) = upstream().await;
+        let config = GatewayConfig::bridge(upstream);
+        let session = SessionGateway::new(move || Gateway::builder().config(config.clone()));
+        let url = serve(McpServerBuilder::new(move || session.clone()).build()).await;
+
+        let (a, b) = (client(&url).await, client(&url).await);
+        assert_eq!(count(&a).await, "1");
+        assert_eq!(count(&b).await, "1");
+        assert_eq!(count(&a).await, "2");
+        assert_eq!(sessions.sessions.read().await.len(), 2);
+    }
+}
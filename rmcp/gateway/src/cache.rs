@@ -0,0 +1,310 @@
+//! Caching of tool results.
+//!
+//! Caching is opt-in: without a `[cache]` section every call goes to the
+//! upstream. With one, successful results of cacheable tools are kept for
+//! their TTL, keyed by upstream, tool and arguments. Tools are cacheable if
+//! a rule gives them a TTL, or else if they are annotated `readOnlyHint`.
+//! `idempotentHint` alone is not enough, since serving a cached result
+//! would skip the call's side effects. Upstreams with per-principal or
+//! per-session connections are never cached, as their results may depend
+//! on the session.
+//!
+//! ```toml
+//! [cache]
+//! max_entries = 1024     # least recently used entries are evicted
+//! read_only_ttl = 60     # seconds, for tools annotated `readOnlyHint`
+//!
+//! [[cache.rules]]
+//! tools = ["get_current_time"]
+//! ttl = 30
+//!
+//! [[cache.rules]]
+//! tools = ["fs.*"]
+//! ttl = 0                # never cache
+//! ```
+
+use crate::{health::secs, policy::Glob};
+use rmcp::model::{CallToolResult, JsonObject, Tool};
+use serde::Deserialize;
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    /// Most results kept at once. Defaults to 1024.
+    pub max_entries: usize,
+    /// How long to keep results of tools annotated `readOnlyHint` that no
+    /// rule matches. Zero disables caching by annotation. Defaults to 60
+    /// seconds.
+    #[serde(deserialize_with = "secs")]
+    pub read_only_ttl: Duration,
+    /// TTLs by tool, checked in order before annotations.
+    pub rules: Vec<CacheRule>,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            max_entries: 1024,
+            read_only_ttl: Duration::from_secs(60),
+            rules: Vec::new(),
+        }
+    }
+}
+
+/// Caches matching tools for `ttl`.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CacheRule {
+    /// Globs on exposed tool names, e.g. `fs.read_*`.
+    pub tools: Vec<Glob>,
+    /// Zero disables caching of matching tools.
+    #[serde(deserialize_with = "secs")]
+    pub ttl: Duration,
+}
+
+impl CacheConfig {
+    /// How long to cache results of `tool`, if at all.
+    pub fn ttl(&self, tool: &Tool) -> Option<Duration> {
+        let rule = self
+            .rules
+            .iter()
+            .find(|rule| rule.tools.iter().any(|glob| glob.matches(&tool.name)));
+        let read_only = tool
+            .annotations
+            .as_ref()
+            .is_some_and(|a| a.read_only_hint == Some(true));
+        let ttl = match rule {
+            Some(rule) => rule.ttl,
+            None if read_only => self.read_only_ttl,
+            None => Duration::ZERO,
+        };
+        (!ttl.is_zero()).then_some(ttl)
+    }
+}
+
+/// Cache hit and miss counters.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Calls answered from the cache.
+    pub hits: u64,
+    /// Calls to cacheable tools that went to the upstream.
+    pub misses: u64,
+    /// Results evicted to stay within `max_entries`.
+    pub evictions: u64,
+    /// Results currently cached.
+    pub entries: usize,
+}
+
+/// A call, identified by its upstream, upstream tool name and arguments.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct Key {
+    upstream: String,
+    tool: String,
+    /// Arguments serialized with sorted keys.
+    arguments: String,
+}
+
+impl Key {
+    pub fn new(upstream: &str, tool: &str, arguments: Option<&JsonObject>) -> Self {
+        let arguments = arguments
+            .map(|args| canonical(&Value::Object(args.clone())).to_string())
+            .unwrap_or_default();
+        Self {
+            upstream: upstream.into(),
+            tool: tool.into(),
+            arguments,
+        }
+    }
+}
+
+/// `value` with object keys inserted in sorted order, so equal arguments
+/// serialize equally however they were ordered.
+fn canonical(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by_key(|(key, _)| *key);
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key.clone(), canonical(value)))
+                    .collect(),
+            )
+        }
+        Value::Array(values) => Value::Array(values.iter().map(canonical).collect()),
+        value => value.clone(),
+    }
+}
+
+/// Size-bounded LRU cache of tool results with per-entry expiry.
+#[derive(Default)]
+pub(crate) struct Cache {
+    state: Mutex<CacheState>,
+}
+
+#[derive(Default)]
+struct CacheState {
+    entries: HashMap<Key, Entry>,
+    /// Keys by last use, oldest first.
+    recency: BTreeMap<u64, Key>,
+    tick: u64,
+    stats: CacheStats,
+}
+
+struct Entry {
+    result: CallToolResult,
+    expires: Instant,
+    used: u64,
+}
+
+impl Cache {
+    /// The cached result of `key`, counting a hit or a miss.
+    pub fn get(&self, key: &Key) -> Option<CallToolResult> {
+        let mut state = self.state();
+        let state = &mut *state;
+        let Some(entry) = state.entries.get_mut(key) else {
+            state.stats.misses += 1;
+            return None;
+        };
+        state.recency.remove(&entry.used);
+        if Instant::now() >= entry.expires {
+            state.entries.remove(key);
+            state.stats.misses += 1;
+            return None;
+        }
+        state.tick += 1;
+        entry.used = state.tick;
+        state.recency.insert(entry.used, key.clone());
+        state.stats.hits += 1;
+        Some(entry.result.clone())
+    }
+
+    /// Cache `result` for `ttl`, evicting the least recently used results
+    /// beyond `max_entries`.
+    pub fn insert(&self, key: Key, result: CallToolResult, ttl: Duration, max_entries: usize) {
+        let mut state = self.state();
+        state.tick += 1;
+        let entry = Entry {
+            result,
+            expires: Instant::now() + ttl,
+            used: state.tick,
+        };
+        state.recency.insert(entry.used, key.clone());
+        if let Some(previous) = state.entries.insert(key, entry) {
+            state.recency.remove(&previous.used);
+        }
+        while state.entries.len() > max_entries {
+            let Some((_, oldest)) = state.recency.pop_first() else {
+                break;
+            };
+            state.entries.remove(&oldest);
+            state.stats.evictions += 1;
+        }
+    }
+
+    pub fn clear(&self) {
+        let mut state = self.state();
+        state.entries.clear();
+        state.recency.clear();
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let state = self.state();
+        CacheStats {
+            entries: state.entries.len(),
+            ..state.stats
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, CacheState> {
+        self.state.lock().expect("cache poisoned")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cache, CacheConfig, CacheStats, Key};
+    use rmcp::model::{CallToolResult, Content, Tool, ToolAnnotations};
+    use serde_json::json;
+    use std::{sync::Arc, time::Duration};
+
+    fn key(arguments: serde_json::Value) -> Key {
+        Key::new("time", "get_current_time", arguments.as_object())
+    }
+
+    fn result(text: &str) -> CallToolResult {
+        CallToolResult::success(vec![Content::text(text)])
+    }
+
+    #[test]
+    fn keys_ignore_argument_order() {
+        assert_eq!(
+            key(json!({ "a": 1, "b": { "c": 2, "d": 3 } })),
+            key(json!({ "b": { "d": 3, "c": 2 }, "a": 1 }))
+        );
+        assert_ne!(key(json!({ "a": 1 })), key(json!({ "a": 2 })));
+    }
+
+    #[test]
+    fn evicts_least_recently_used_and_expired() {
+        let cache = Cache::default();
+        let ttl = Duration::from_secs(60);
+        cache.insert(key(json!({ "n": 1 })), result("1"), ttl, 2);
+        cache.insert(key(json!({ "n": 2 })), result("2"), ttl, 2);
+        assert!(cache.get(&key(json!({ "n": 1 }))).is_some());
+        cache.insert(key(json!({ "n": 3 })), result("3"), ttl, 2);
+        assert!(cache.get(&key(json!({ "n": 2 }))).is_none());
+        assert!(cache.get(&key(json!({ "n": 1 }))).is_some());
+
+        cache.insert(key(json!({ "n": 4 })), result("4"), Duration::ZERO, 2);
+        assert!(cache.get(&key(json!({ "n": 4 }))).is_none());
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                hits: 2,
+                misses: 2,
+                evictions: 2,
+                entries: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn ttl_from_rules_then_annotations() {
+        let config: CacheConfig = toml::from_str(
+            r#"
+            read_only_ttl = 10
+            rules = [
+                { tools = ["get_current_time"], ttl = 30 },
+                { tools = ["fs.*"], ttl = 0 },
+            ]
+            "#,
+        )
+        .unwrap();
+        let tool = |name: &str, read_only: bool| {
+            let tool = Tool::new(name.to_string(), "", Arc::default());
+            if read_only {
+                tool.annotate(ToolAnnotations::new().read_only(true))
+            } else {
+                tool
+            }
+        };
+        let secs = |s| Some(Duration::from_secs(s));
+        assert_eq!(config.ttl(&tool("get_current_time", false)), secs(30));
+        assert_eq!(config.ttl(&tool("search", true)), secs(10));
+        assert_eq!(config.ttl(&tool("search", false)), None);
+        assert_eq!(config.ttl(&tool("fs.read_file", true)), None);
+    }
+
+    #[test]
+    fn rejects_invalid_globs() {
+        let config = r#"rules = [{ tools = ["fs.[read"], ttl = 30 }]"#;
+        assert!(toml::from_str::<CacheConfig>(config).is_err());
+    }
+}
@@ -0,0 +1,206 @@
+//! The merged view of all upstreams, and routing back to their owners.
+
+use crate::{
+    composite::CompositeTool,
+    transform::ToolTransform,
+    upstream::{Listing, Upstream},
+};
+use rmcp::model::{Prompt, Resource, ResourceTemplate, Tool};
+use serde::Deserialize;
+use std::{collections::HashMap, sync::Arc};
+
+/// When to prefix exposed tool and prompt names with the upstream prefix.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Prefixing {
+    /// Always expose `prefix.name`.
+    Always,
+    /// Prefix only names offered by more than one upstream.
+    #[default]
+    OnCollision,
+    /// Never prefix; the first upstream offering a name wins.
+    Never,
+}
+
+/// Where a merged tool or prompt is served.
+pub(crate) struct Route {
+    pub upstream: Arc<Upstream>,
+    /// The name on the upstream.
+    pub name: String,
+    /// How calls to a transformed tool map to the upstream's arguments.
+    pub transform: Option<ToolTransform>,
+}
+
+#[derive(Default)]
+pub(crate) struct Catalog {
+    pub tools: Vec<Tool>,
+    pub prompts: Vec<Prompt>,
+    pub resources: Vec<Resource>,
+    pub templates: Vec<ResourceTemplate>,
+    tool_routes: HashMap<String, Route>,
+    prompt_routes: HashMap<String, Route>,
+    composites: HashMap<String, CompositeTool>,
+    resource_routes: HashMap<String, Arc<Upstream>>,
+    /// Literal prefix of each URI template, longest first.
+    template_routes: Vec<(String, Arc<Upstream>)>,
+}
+
+impl Catalog {
+    /// Merge upstream listings, in upstream order, apply `transforms` by
+    /// prefixed tool name and add `composites` after the upstream tools.
+    pub fn build(
+        listings: Vec<(Arc<Upstream>, Listing)>,
+        prefixing: Prefixing,
+        separator: &str,
+        transforms: &HashMap<String, ToolTransform>,
+        composites: &HashMap<String, CompositeTool>,
+    ) -> Self {
+        let tool_counts = counts(
+            listings
+                .iter()
+                .flat_map(|(_, l)| l.tools.iter().map(|t| t.name.as_ref())),
+        );
+        let prompt_counts = counts(
+            listings
+                .iter()
+                .flat_map(|(_, l)| l.prompts.iter().map(|p| p.name.as_str())),
+        );
+
+        let mut catalog = Self::default();
+        for (upstream, listing) in listings {
+            let exposed = |name: &str, counts: &HashMap<String, usize>| match prefixing {
+                Prefixing::Always => format!("{}{separator}{name}", upstream.prefix()),
+                Prefixing::OnCollision if counts[name] > 1 => {
+                    format!("{}{separator}{name}", upstream.prefix())
+                }
+                _ => name.to_string(),
+            };
+
+            for mut tool in listing.tools {
+                let upstream_name = tool.name.to_string();
+                tool.name = exposed(&tool.name, &tool_counts).into();
+                let transform = transforms.get(tool.name.as_ref()).cloned();
+                if let Some(transform) = &transform {
+                    transform.apply(&mut tool);
+                }
+                let name = tool.name.to_string();
+                if catalog.tool_routes.contains_key(&name) {
+                    tracing::warn!(
+                        upstream = upstream.name(),
+                        tool = name,
+                        "duplicate tool hidden"
+                    );
+                    continue;
+                }
+                let route = Route {
+                    upstream: upstream.clone(),
+                    name: upstream_name,
+                    transform,
+                };
+                catalog.tool_routes.insert(name, route);
+                catalog.tools.push(tool);
+            }
+
+            for mut prompt in listing.prompts {
+                let name = exposed(&prompt.name, &prompt_counts);
+                if catalog.prompt_routes.contains_key(&name) {
+                    tracing::warn!(
+                        upstream = upstream.name(),
+                        prompt = name,
+                        "duplicate prompt hidden"
+                    );
+                    continue;
+                }
+                let route = Route {
+                    upstream: upstream.clone(),
+                    name: std::mem::replace(&mut prompt.name, name.clone()),
+                    transform: None,
+                };
+                catalog.prompt_routes.insert(name, route);
+                catalog.prompts.push(prompt);
+            }
+
+            // URIs identify resources globally, so they are never rewritten.
+            for resource in listing.resources {
+                if catalog.resource_routes.contains_key(&resource.uri) {
+                    tracing::warn!(
+                        upstream = upstream.name(),
+                        uri = resource.uri,
+                        "duplicate resource hidden"
+                    );
+                    continue;
+                }
+                catalog
+                    .resource_routes
+                    .insert(resource.uri.clone(), upstream.clone());
+                catalog.resources.push(resource);
+            }
+
+            for template in listing.templates {
+                let literal = template
+                    .uri_template
+                    .split('{')
+                    .next()
+                    .unwrap_or_default()
+                    .to_string();
+                catalog.template_routes.push((literal, upstream.clone()));
+                catalog.templates.push(template);
+            }
+        }
+        // Steps call upstream tools only, so composites can't nest.
+        let upstream_tools = catalog.tools.len();
+        let mut names: Vec<_> = composites.keys().collect();
+        names.sort();
+        for name in names {
+            if catalog.tool_routes.contains_key(name) {
+                tracing::warn!(tool = name, "composite tool hidden by upstream tool");
+                continue;
+            }
+            let composite = &composites[name];
+            match composite.tool(name, &catalog.tools[..upstream_tools]) {
+                Ok(tool) => {
+                    catalog.composites.insert(name.clone(), composite.clone());
+                    catalog.tools.push(tool);
+                }
+                Err(e) => tracing::warn!(tool = name, "composite tool hidden: {e}"),
+            }
+        }
+
+        // Stable, so equal prefixes keep upstream order.
+        catalog
+            .template_routes
+            .sort_by_key(|(literal, _)| std::cmp::Reverse(literal.len()));
+        catalog
+    }
+
+    pub fn tool(&self, name: &str) -> Option<&Route> {
+        self.tool_routes.get(name)
+    }
+
+    pub fn composite(&self, name: &str) -> Option<&CompositeTool> {
+        self.composites.get(name)
+    }
+
+    pub fn prompt(&self, name: &str) -> Option<&Route> {
+        self.prompt_routes.get(name)
+    }
+
+    /// The upstream serving `uri`, by exact resource URI or else by the
+    /// longest matching URI template prefix.
+    pub fn resource(&self, uri: &str) -> Option<&Arc<Upstream>> {
+        self.resource_routes.get(uri).or_else(|| {
+            self.template_routes
+                .iter()
+                .find(|(literal, _)| uri.starts_with(literal.as_str()))
+                .map(|(_, upstream)| upstream)
+        })
+    }
+}
+
+fn counts<'a>(names: impl Iterator<Item = &'a str>) -> HashMap<String, usize> {
+    let mut counts = HashMap::new();
+    for name in names {
+        *counts.entry(name.to_string()).or_default() += 1;
+    }
+    counts
+}
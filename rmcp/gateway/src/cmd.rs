@@ -0,0 +1,285 @@
+//! Command-line interface for running gateways and bridges.
+
+use crate::{
+    Gateway, GatewayConfig,
+    bridge::SessionGateway,
+    error::Error,
+    upstream::{Transport, UpstreamConfig},
+};
+use clap::{Args, Parser, Subcommand};
+use rmcp::{ServerHandler, ServiceExt};
+use rmcp_axum::{
+    auth::{
+        AuthLayer, BearerAuth,
+        jwt::JwtValidator,
+        oauth::{OAuthClaims, ProtectedResource, ProtectedResourceMetadata},
+    },
+    origin::OriginLayer,
+    server::McpServerBuilder,
+};
+use std::{net::SocketAddr, path::PathBuf, time::Duration};
+use tokio_util::sync::CancellationToken;
+
+/// How often `serve --watch` checks the configuration file.
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Aggregate MCP servers, or bridge one between stdio and streamable HTTP.
+///
+/// Usage:
+///   rmcp-gateway serve --config gateway.toml --listen 0.0.0.0:8080
+///   rmcp-gateway bridge --listen 0.0.0.0:8080 -- wmcp-time
+///   rmcp-gateway bridge --auth TOKEN -- https://example.com/mcp
+#[derive(Parser, Debug)]
+#[command(name = "rmcp-gateway", version, about, subcommand_negates_reqs = true)]
+pub struct App {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Serve the upstreams of a configuration file as one server.
+    Serve {
+        /// Gateway configuration, TOML or JSON.
+        #[arg(long, value_name = "PATH")]
+        config: PathBuf,
+
+        /// Reload the configuration whenever the file changes.
+        #[arg(long)]
+        watch: bool,
+
+        #[command(flatten)]
+        http: HttpArgs,
+    },
+    /// Serve a single server over the other transport.
+    Bridge {
+        /// Start the server once per HTTP session instead of sharing one.
+        #[arg(long, requires = "listen")]
+        per_session: bool,
+
+        /// Bearer token for a remote target.
+        #[arg(long = "auth", value_name = "TOKEN")]
+        auth: Option<String>,
+
+        /// Extra header for a remote target (repeatable).
+        #[arg(long = "header", value_name = "NAME=VALUE")]
+        headers: Vec<String>,
+
+        #[command(flatten)]
+        http: HttpArgs,
+
+        /// Target MCP server (URL or command after `--`).
+        #[arg(required = true, num_args = 1.., last = true)]
+        target: Vec<String>,
+    },
+}
+
+/// Options for serving over streamable HTTP instead of stdio.
+#[derive(Args, Debug)]
+pub struct HttpArgs {
+    /// Serve streamable HTTP on this address instead of stdio.
+    #[arg(long, value_name = "ADDR")]
+    pub listen: Option<SocketAddr>,
+
+    /// Path of the MCP endpoint.
+    #[arg(long, default_value = "/mcp", requires = "listen")]
+    pub path: String,
+
+    /// Require bearer tokens: JWTs signed by a key from this JWKS URL.
+    #[arg(long, value_name = "URL", requires_all = ["listen", "resource"])]
+    pub jwks_url: Option<String>,
+
+    /// Require tokens issued by this issuer.
+    #[arg(long, value_name = "ISSUER", requires = "jwks_url")]
+    pub issuer: Option<String>,
+
+    /// Canonical URI of this server. Tokens must be issued for it (their
+    /// audience), and its Protected Resource Metadata is served.
+    #[arg(long, value_name = "URL", requires = "jwks_url")]
+    pub resource: Option<String>,
+
+    /// Authorization server named in the metadata (repeatable).
+    #[arg(
+        long = "authorization-server",
+        value_name = "URL",
+        requires = "resource"
+    )]
+    pub authorization_servers: Vec<String>,
+
+    /// Scope named in the metadata and in 401 challenges (repeatable).
+    #[arg(long = "scope", value_name = "SCOPE", requires = "resource")]
+    pub scopes: Vec<String>,
+
+    /// Browser origin allowed to call the server (repeatable). Loopback
+    /// origins are always allowed on loopback addresses.
+    #[arg(long = "allow-origin", value_name = "ORIGIN", requires = "listen")]
+    pub allow_origins: Vec<String>,
+}
+
+impl App {
+    /// Parse CLI arguments and execute the corresponding command.
+    pub async fn run() -> Result<(), Error> {
+        let app = App::parse();
+        // Logs go to stderr, as stdout may be the MCP transport.
+        tracing_subscriber::fmt()
+            .with_writer(std::io::stderr)
+            .with_env_filter(
+                tracing_subscriber::EnvFilter::try_from_default_env()
+                    .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("rmcp_gateway=info")),
+            )
+            .init();
+
+        match app.command {
+            Command::Serve {
+                config: path,
+                watch,
+                http,
+            } => {
+                let config = GatewayConfig::load(&path).await?;
+                let gateway = Gateway::builder()
+                    .config(config)
+                    .claims::<OAuthClaims>()
+                    .build()
+                    .await?;
+                if watch {
+                    gateway.watch(path, WATCH_INTERVAL);
+                }
+                serve(move || gateway.clone(), http).await
+            }
+            Command::Bridge {
+                per_session,
+                auth,
+                headers,
+                http,
+                target,
+            } => {
+                let config = GatewayConfig::bridge(upstream(target, auth, headers)?);
+                if per_session {
+                    let session = SessionGateway::new(move || {
+                        Gateway::builder()
+                            .config(config.clone())
+                            .claims::<OAuthClaims>()
+                    });
+                    serve(move || session.clone(), http).await
+                } else {
+                    let gateway = Gateway::builder()
+                        .config(config)
+                        .claims::<OAuthClaims>()
+                        .build()
+                        .await?;
+                    serve(move || gateway.clone(), http).await
+                }
+            }
+        }
+    }
+}
+
+/// The upstream a bridge serves: a URL, or a command and its arguments.
+fn upstream(
+    target: Vec<String>,
+    auth: Option<String>,
+    headers: Vec<String>,
+) -> Result<UpstreamConfig, Error> {
+    let first = &target[0];
+    if first.starts_with("http://") || first.starts_with("https://") {
+        let headers = headers
+            .into_iter()
+            .map(|header| match header.split_once('=') {
+                Some((name, value)) => Ok((name.to_string(), value.to_string())),
+                None => Err(Error::InvalidArg(header)),
+            })
+            .collect::<Result<_, _>>()?;
+        let mut config = UpstreamConfig::http("remote", first);
+        config.transport = Transport::Http {
+            url: first.clone(),
+            headers,
+            auth,
+        };
+        Ok(config)
+    } else {
+        let name = std::path::Path::new(first)
+            .file_name()
+            .map_or(first.clone(), |name| name.to_string_lossy().into_owned());
+        Ok(UpstreamConfig::stdio(name, first, &target[1..]))
+    }
+}
+
+/// Serve handlers from `factory` over stdio, or over streamable HTTP when
+/// `--listen` is given, until interrupted.
+async fn serve<S, F>(factory: F, http: HttpArgs) -> Result<(), Error>
+where
+    S: ServerHandler,
+    F: Fn() -> S + Send + Sync + 'static,
+{
+    let Some(addr) = http.listen else {
+        let service = factory()
+            .serve(rmcp::transport::stdio())
+            .await
+            .map_err(Box::new)?;
+        service.waiting().await.map_err(std::io::Error::other)?;
+        return Ok(());
+    };
+
+    // Validating `Origin` keeps pages on rebound domains away from local
+    // servers.
+    let origins = if addr.ip().is_loopback() {
+        http.allow_origins
+            .into_iter()
+            .fold(OriginLayer::localhost(), OriginLayer::allow_origin)
+    } else {
+        OriginLayer::new(http.allow_origins)
+    };
+    let shutdown = CancellationToken::new();
+    let mut builder = McpServerBuilder::new(factory)
+        .path(http.path)
+        .origins(origins)
+        .shutdown(shutdown.clone());
+    if let Some(jwks_url) = http.jwks_url {
+        let mut validator = JwtValidator::from_jwks_url(jwks_url);
+        if let Some(issuer) = http.issuer {
+            validator = validator.issuer(issuer);
+        }
+        // Tokens minted for other services must not be accepted here.
+        if let Some(resource) = &http.resource {
+            validator = validator.audience(resource.clone());
+        }
+        let validator = validator
+            .build()
+            .await
+            .map_err(|e| Error::Auth(e.to_string()))?;
+        let auth = AuthLayer::new(BearerAuth::new(validator));
+        builder = match http.resource {
+            Some(resource) => {
+                let mut resource = ProtectedResource::new(ProtectedResourceMetadata {
+                    resource,
+                    authorization_servers: http.authorization_servers,
+                    scopes_supported: (!http.scopes.is_empty()).then(|| http.scopes.clone()),
+                    bearer_methods_supported: Some(vec!["header".into()]),
+                    ..Default::default()
+                })
+                .map_err(|e| Error::Auth(e.to_string()))?;
+                if !http.scopes.is_empty() {
+                    resource = resource.default_scope(http.scopes.join(" "));
+                }
+                builder
+                    .protected_resource(resource.clone())
+                    .auth(auth.with_protected_resource(resource))
+            }
+            None => builder.auth(auth),
+        };
+    }
+
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    tracing::info!("listening on {addr}");
+    tokio::spawn({
+        let shutdown = shutdown.clone();
+        async move {
+            tokio::signal::ctrl_c().await.ok();
+            shutdown.cancel();
+        }
+    });
+    axum::serve(listener, builder.build())
+        .with_graceful_shutdown(shutdown.cancelled_owned())
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,643 @@
+//! Tools the gateway implements itself, by calling upstream tools in turn.
+//!
+//! Composite tools are keyed by the name they are exposed under. Each step
+//! calls a tool by its exposed name, with arguments that may contain
+//! `{{ ... }}` templates over the composite's own arguments (`args.<name>`)
+//! and the outputs of earlier steps (`steps.<id>`). A string that is a
+//! single template takes the template's value as is; otherwise values are
+//! interpolated as text.
+//!
+//! ```toml
+//! [composites.snapshot_dir]
+//! description = "Read every file below a directory matching a pattern."
+//! defaults = { pattern = "**" }
+//!
+//! [[composites.snapshot_dir.steps]]
+//! id = "tree"
+//! tool = "directory_tree"
+//! arguments = { path = "{{ args.path }}" }
+//!
+//! [[composites.snapshot_dir.steps]]
+//! tool = "read_multiple_files"
+//! arguments = { paths = "{{ steps.tree | files(args.path) | glob(args.pattern) }}" }
+//! ```
+//!
+//! Values can be piped through filters:
+//!
+//! - `files(base)`: the file paths of a directory tree of `{ name, children }`
+//!   nodes, below `base`;
+//! - `lines`: the non-empty lines of a string;
+//! - `glob(pattern)`: the strings of a list matching `pattern`;
+//! - `join(separator)`: a list of strings joined into one;
+//! - `json`: the value serialized as JSON text.
+//!
+//! A step's output is its structured content, else its text parsed as
+//! JSON, else its text. The input schema is generated: parameters passed
+//! whole to a step argument take that argument's schema, others are
+//! strings, and `parameters` overrides either. The composite returns the
+//! last step's result, or the first error result.
+
+use rmcp::model::{CallToolResult, JsonObject, Tool};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::sync::Arc;
+
+/// A tool calling other tools in turn.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CompositeTool {
+    /// Description of the tool. Defaults to naming the tools it calls.
+    #[serde(default)]
+    pub description: Option<String>,
+    /// JSON Schemas of parameters, by name, replacing generated ones.
+    #[serde(default)]
+    pub parameters: JsonObject,
+    /// Values for parameters the caller leaves out. They become optional
+    /// and document their default in the schema.
+    #[serde(default)]
+    pub defaults: JsonObject,
+    /// Tool calls, run in order.
+    pub steps: Vec<Step>,
+}
+
+/// One call of a composite tool.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Step {
+    /// Name later steps refer to the output by, as `steps.<id>`. Defaults
+    /// to the tool name.
+    #[serde(default)]
+    pub id: Option<String>,
+    /// Exposed name of the tool to call.
+    pub tool: String,
+    /// Arguments, with `{{ ... }}` templates in strings.
+    #[serde(default)]
+    pub arguments: JsonObject,
+}
+
+impl CompositeTool {
+    /// The tool to expose as `name`, with an input schema generated from
+    /// the steps' arguments and the schemas of `tools` they call.
+    pub(crate) fn tool(&self, name: &str, tools: &[Tool]) -> Result<Tool, String> {
+        if self.steps.is_empty() {
+            return Err("no steps".into());
+        }
+        let mut whole = Vec::new();
+        let mut used = Vec::new();
+        for step in &self.steps {
+            let tool = tools
+                .iter()
+                .find(|tool| tool.name == step.tool)
+                .ok_or_else(|| format!("unknown tool `{}`", step.tool))?;
+            let schemas = tool.input_schema.get("properties");
+            for (argument, value) in &step.arguments {
+                if let Value::String(template) = value
+                    && let [Piece::Expr(expr)] = &pieces(template)?[..]
+                    && expr.filters.is_empty()
+                    && let Some(parameter) = expr.value.parameter()
+                    && let Some(schema) = schemas.and_then(|s| s.get(argument))
+                {
+                    whole.push((parameter.to_string(), schema.clone()));
+                }
+                parameters(value, &mut used)?;
+            }
+        }
+
+        let mut properties = JsonObject::new();
+        for (parameter, schema) in whole {
+            properties.entry(parameter).or_insert(schema);
+        }
+        for parameter in used {
+            properties
+                .entry(parameter)
+                .or_insert_with(|| json!({ "type": "string" }));
+        }
+        properties.extend(self.parameters.clone());
+        let mut required = Vec::new();
+        for (parameter, schema) in &mut properties {
+            match self.defaults.get(parameter) {
+                Some(default) => {
+                    if let Value::Object(schema) = schema {
+                        schema.insert("default".into(), default.clone());
+                    }
+                }
+                None => required.push(parameter.clone()),
+            }
+        }
+        required.sort();
+
+        let description = self.description.clone().unwrap_or_else(|| {
+            let tools: Vec<_> = self.steps.iter().map(|s| format!("`{}`", s.tool)).collect();
+            format!("Calls {}.", tools.join(", then "))
+        });
+        let Value::Object(schema) = json!({
+            "type": "object",
+            "properties": properties,
+            "required": required,
+        }) else {
+            unreachable!("schema is an object");
+        };
+        Ok(Tool::new(name.to_string(), description, Arc::new(schema)))
+    }
+
+    /// The caller's arguments with defaults filled in.
+    pub(crate) fn arguments(&self, arguments: Option<JsonObject>) -> JsonObject {
+        let mut arguments = arguments.unwrap_or_default();
+        for (name, value) in &self.defaults {
+            arguments
+                .entry(name.clone())
+                .or_insert_with(|| value.clone());
+        }
+        arguments
+    }
+}
+
+impl Step {
+    pub fn id(&self) -> &str {
+        self.id.as_deref().unwrap_or(&self.tool)
+    }
+
+    /// Render the step's arguments from the composite's `args` and the
+    /// outputs of earlier `steps`, by id.
+    pub(crate) fn arguments(
+        &self,
+        args: &JsonObject,
+        steps: &JsonObject,
+    ) -> Result<JsonObject, String> {
+        let scope = json!({ "args": args, "steps": steps });
+        self.arguments
+            .iter()
+            .map(|(name, value)| Ok((name.clone(), render(value, &scope)?)))
+            .collect()
+    }
+}
+
+/// What later steps see of a step's result: its structured content, else
+/// its text parsed as JSON, else its text.
+pub(crate) fn output(result: &CallToolResult) -> Value {
+    if let Some(structured) = &result.structured_content {
+        return structured.clone();
+    }
+    let text = result
+        .content
+        .iter()
+        .filter_map(|content| content.as_text())
+        .map(|content| content.text.as_str())
+        .collect::<Vec<_>>()
+        .join("\n");
+    serde_json::from_str(&text).unwrap_or(Value::String(text))
+}
+
+/// `value` with the templates in its strings rendered in `scope`.
+fn render(value: &Value, scope: &Value) -> Result<Value, String> {
+    match value {
+        Value::String(template) => {
+            let pieces = pieces(template)?;
+            if let [Piece::Expr(expr)] = &pieces[..] {
+                return expr.eval(scope);
+            }
+            let mut text = String::new();
+            for piece in pieces {
+                match piece {
+                    Piece::Text(t) => text.push_str(t),
+                    Piece::Expr(expr) => match expr.eval(scope)? {
+                        Value::String(s) => text.push_str(&s),
+                        value => text.push_str(&value.to_string()),
+                    },
+                }
+            }
+            Ok(Value::String(text))
+        }
+        Value::Array(values) => values.iter().map(|v| render(v, scope)).collect(),
+        Value::Object(map) => map
+            .iter()
+            .map(|(key, value)| Ok((key.clone(), render(value, scope)?)))
+            .collect(),
+        value => Ok(value.clone()),
+    }
+}
+
+/// Names of the composite's parameters the templates in `value` use.
+fn parameters(value: &Value, out: &mut Vec<String>) -> Result<(), String> {
+    match value {
+        Value::String(template) => {
+            for piece in pieces(template)? {
+                if let Piece::Expr(expr) = piece {
+                    let operands = std::iter::once(&expr.value)
+                        .chain(expr.filters.iter().flat_map(|(_, args)| args));
+                    out.extend(operands.filter_map(Operand::parameter).map(String::from));
+                }
+            }
+        }
+        Value::Array(values) => values.iter().try_for_each(|v| parameters(v, out))?,
+        Value::Object(map) => map.values().try_for_each(|v| parameters(v, out))?,
+        _ => {}
+    }
+    Ok(())
+}
+
+enum Piece<'a> {
+    Text(&'a str),
+    Expr(Expr),
+}
+
+/// Split a string into text and `{{ ... }}` expressions.
+fn pieces(template: &str) -> Result<Vec<Piece<'_>>, String> {
+    let mut pieces = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        if start > 0 {
+            pieces.push(Piece::Text(&rest[..start]));
+        }
+        let end = rest[start..]
+            .find("}}")
+            .ok_or_else(|| format!("unclosed `{{{{` in `{template}`"))?
+            + start;
+        let expr = Parser::parse(&rest[start + 2..end])
+            .map_err(|e| format!("{e} in `{}`", &rest[start..end + 2]))?;
+        pieces.push(Piece::Expr(expr));
+        rest = &rest[end + 2..];
+    }
+    if !rest.is_empty() {
+        pieces.push(Piece::Text(rest));
+    }
+    Ok(pieces)
+}
+
+/// A value piped through filters, e.g. `steps.tree | files(args.path)`.
+#[derive(Debug, PartialEq)]
+struct Expr {
+    value: Operand,
+    filters: Vec<(String, Vec<Operand>)>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Operand {
+    /// `args.path` or `steps.tree.children[0]`.
+    Path(Vec<Segment>),
+    /// `'text'` or `"text"`.
+    Literal(String),
+}
+
+#[derive(Debug, PartialEq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+impl Expr {
+    fn eval(&self, scope: &Value) -> Result<Value, String> {
+        let mut value = self.value.eval(scope)?;
+        for (name, args) in &self.filters {
+            let args = args
+                .iter()
+                .map(|arg| arg.eval(scope))
+                .collect::<Result<Vec<_>, _>>()?;
+            value = filter(name, value, &args)?;
+        }
+        Ok(value)
+    }
+}
+
+impl Operand {
+    fn eval(&self, scope: &Value) -> Result<Value, String> {
+        let path = match self {
+            Operand::Literal(text) => return Ok(Value::String(text.clone())),
+            Operand::Path(path) => path,
+        };
+        path.iter()
+            .try_fold(scope, |value, segment| match segment {
+                Segment::Key(key) => value.get(key),
+                Segment::Index(index) => value.get(index),
+            })
+            .cloned()
+            .ok_or_else(|| {
+                let path: String = path
+                    .iter()
+                    .enumerate()
+                    .map(|(i, segment)| match segment {
+                        Segment::Key(key) if i == 0 => key.clone(),
+                        Segment::Key(key) => format!(".{key}"),
+                        Segment::Index(index) => format!("[{index}]"),
+                    })
+                    .collect();
+                format!("`{path}` is not set")
+            })
+    }
+
+    /// The composite parameter this refers to, for `args.<name>...`.
+    fn parameter(&self) -> Option<&str> {
+        match self {
+            Operand::Path(path) => match &path[..] {
+                [Segment::Key(root), Segment::Key(name), ..] if root == "args" => Some(name),
+                _ => None,
+            },
+            Operand::Literal(_) => None,
+        }
+    }
+}
+
+fn filter(name: &str, value: Value, args: &[Value]) -> Result<Value, String> {
+    let arg = |i: usize| {
+        args.get(i)
+            .and_then(Value::as_str)
+            .ok_or_else(|| format!("`{name}` needs a string argument"))
+    };
+    let strings = |value: &Value| -> Result<Vec<String>, String> {
+        value
+            .as_array()
+            .and_then(|values| {
+                values
+                    .iter()
+                    .map(|v| v.as_str().map(String::from))
+                    .collect()
+            })
+            .ok_or_else(|| format!("`{name}` needs a list of strings"))
+    };
+    match name {
+        "files" => {
+            let base = args.first().and_then(Value::as_str).unwrap_or_default();
+            let base = base.trim_end_matches('/');
+            let mut paths = Vec::new();
+            match &value {
+                Value::Array(nodes) => nodes.iter().for_each(|n| files(n, base, &mut paths)),
+                root => match root.get("children").and_then(Value::as_array) {
+                    Some(nodes) => nodes.iter().for_each(|n| files(n, base, &mut paths)),
+                    None => files(root, base, &mut paths),
+                },
+            }
+            Ok(paths.into_iter().map(Value::String).collect())
+        }
+        "lines" => {
+            let text = value.as_str().ok_or("`lines` needs a string")?;
+            Ok(text
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(|line| Value::String(line.into()))
+                .collect())
+        }
+        "glob" => {
+            let pattern = glob::Pattern::new(arg(0)?).map_err(|e| e.to_string())?;
+            Ok(strings(&value)?
+                .into_iter()
+                .filter(|s| pattern.matches(s))
+                .map(Value::String)
+                .collect())
+        }
+        "join" => Ok(Value::String(strings(&value)?.join(arg(0)?))),
+        "json" => Ok(Value::String(value.to_string())),
+        _ => Err(format!("unknown filter `{name}`")),
+    }
+}
+
+/// Paths of the files of a directory tree node, below `base`.
+fn files(node: &Value, base: &str, out: &mut Vec<String>) {
+    let Some(name) = node.get("name").and_then(Value::as_str) else {
+        return;
+    };
+    let path = match base {
+        "" => name.to_string(),
+        base => format!("{base}/{name}"),
+    };
+    match node.get("children").and_then(Value::as_array) {
+        Some(children) => children.iter().for_each(|child| files(child, &path, out)),
+        None => out.push(path),
+    }
+}
+
+/// Parser of the expressions between `{{` and `}}`.
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn parse(source: &str) -> Result<Expr, String> {
+        let mut parser = Parser {
+            chars: source.chars().collect(),
+            pos: 0,
+        };
+        let value = parser.operand()?;
+        let mut filters = Vec::new();
+        while parser.eat('|') {
+            let name = parser.name()?;
+            let mut args = Vec::new();
+            if parser.eat('(') && !parser.eat(')') {
+                loop {
+                    args.push(parser.operand()?);
+                    if parser.eat(')') {
+                        break;
+                    }
+                    parser.expect(',')?;
+                }
+            }
+            filters.push((name, args));
+        }
+        parser.skip_whitespace();
+        match parser.peek() {
+            Some(c) => Err(format!("unexpected `{c}`")),
+            None => Ok(Expr { value, filters }),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    /// Skip whitespace and consume `c`, if next.
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        let next = self.peek() == Some(c);
+        if next {
+            self.pos += 1;
+        }
+        next
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(format!("expected `{c}`"))
+        }
+    }
+
+    /// Consume characters while `f` holds.
+    fn take(&mut self, f: impl Fn(char) -> bool) -> String {
+        let start = self.pos;
+        while self.peek().is_some_and(&f) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    fn name(&mut self) -> Result<String, String> {
+        self.skip_whitespace();
+        let name = self.take(|c| c.is_alphanumeric() || c == '_' || c == '-');
+        if name.is_empty() {
+            return Err("expected a name".into());
+        }
+        Ok(name)
+    }
+
+    fn operand(&mut self) -> Result<Operand, String> {
+        self.skip_whitespace();
+        if let Some(quote @ ('\'' | '"')) = self.peek() {
+            self.pos += 1;
+            let text = self.take(|c| c != quote);
+            if !self.eat(quote) {
+                return Err("unterminated string".into());
+            }
+            return Ok(Operand::Literal(text));
+        }
+        let mut path = vec![Segment::Key(self.name()?)];
+        loop {
+            match self.peek() {
+                Some('.') => {
+                    self.pos += 1;
+                    path.push(Segment::Key(self.name()?));
+                }
+                Some('[') => {
+                    self.pos += 1;
+                    let index = self.take(|c| c.is_ascii_digit());
+                    let index = index.parse().map_err(|_| "expected an index")?;
+                    path.push(Segment::Index(index));
+                    self.expect(']')?;
+                }
+                _ => return Ok(Operand::Path(path)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CompositeTool, Step};
+    use rmcp::model::Tool;
+    use serde_json::{Value, json};
+    use std::sync::Arc;
+
+    fn object(value: Value) -> serde_json::Map<String, Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    fn snapshot() -> CompositeTool {
+        toml::from_str(
+            r#"
+            defaults = { pattern = "**" }
+
+            [[steps]]
+            id = "tree"
+            tool = "directory_tree"
+            arguments = { path = "{{ args.path }}" }
+
+            [[steps]]
+            tool = "read_multiple_files"
+            arguments = { paths = "{{ steps.tree | files(args.path) | glob(args.pattern) }}" }
+            "#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn renders_templates() {
+        let args = object(json!({ "path": "/srv/", "pattern": "**/*.rs", "n": 2 }));
+        let steps = object(json!({
+            "tree": {
+                "name": "srv",
+                "children": [
+                    { "name": "src", "children": [{ "name": "lib.rs" }, { "name": "notes.md" }] },
+                    { "name": "build.rs" },
+                ],
+            },
+            "search_files": "/srv/a\n/srv/b\n",
+        }));
+        let step = |arguments: Value| Step {
+            tool: "t".into(),
+            arguments: object(arguments),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            snapshot().steps[1].arguments(&args, &steps),
+            Ok(object(
+                json!({ "paths": ["/srv/src/lib.rs", "/srv/build.rs"] })
+            ))
+        );
+        assert_eq!(
+            step(json!({
+                "title": "{{ args.n }} files below {{ args.path }}",
+                "first": "{{ steps.tree.children[0].name }}",
+                "found": ["{{ steps.search_files | lines | join(', ') }}"],
+                "raw": "{{ args | json }}",
+                "depth": 3,
+            }))
+            .arguments(&args, &steps),
+            Ok(object(json!({
+                "title": "2 files below /srv/",
+                "first": "src",
+                "found": ["/srv/a, /srv/b"],
+                "raw": r#"{"n":2,"path":"/srv/","pattern":"**/*.rs"}"#,
+                "depth": 3,
+            })))
+        );
+        assert_eq!(
+            step(json!({ "x": "{{ args.missing }}" })).arguments(&args, &steps),
+            Err("`args.missing` is not set".into())
+        );
+        assert_eq!(
+            step(json!({ "x": "{{ args.path | upper }}" })).arguments(&args, &steps),
+            Err("unknown filter `upper`".into())
+        );
+        assert!(
+            step(json!({ "x": "{{ args.path" }))
+                .arguments(&args, &steps)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn generates_input_schema() {
+        let tool = |name: &str, properties: Value| {
+            let schema = json!({ "type": "object", "properties": properties });
+            Tool::new(name.to_string(), "", Arc::new(object(schema)))
+        };
+        let tools = [
+            tool(
+                "directory_tree",
+                json!({ "path": { "type": "string", "description": "Root." } }),
+            ),
+            tool(
+                "read_multiple_files",
+                json!({ "paths": { "type": "array" } }),
+            ),
+        ];
+        let composite = snapshot().tool("snapshot_dir", &tools).unwrap();
+
+        assert_eq!(
+            composite.description.as_deref(),
+            Some("Calls `directory_tree`, then `read_multiple_files`.")
+        );
+        assert_eq!(
+            Value::Object(composite.input_schema.as_ref().clone()),
+            json!({
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "description": "Root." },
+                    "pattern": { "type": "string", "default": "**" },
+                },
+                "required": ["path"],
+            })
+        );
+        assert_eq!(
+            snapshot().tool("snapshot_dir", &tools[..1]),
+            Err("unknown tool `read_multiple_files`".into())
+        );
+    }
+}
@@ -0,0 +1,220 @@
+//! Declarative gateway configuration, in TOML or JSON.
+//!
+//! ```toml
+//! prefixing = "on_collision"
+//!
+//! [[upstreams]]
+//! name = "fs"
+//! command = "wmcp-filesystem"
+//! args = ["/srv/shared"]
+//! env = { RUST_LOG = "info" }
+//! tools = { exclude = ["write_*"] }
+//! sessions = "per_session"   # or "shared", "per_principal"
+//! max_connections = 32
+//! max_concurrency = 16
+//! queue_timeout = 5
+//! request_timeout = 120
+//! connect_timeout = 10
+//!
+//! [[upstreams]]
+//! name = "search"
+//! prefix = "web"
+//! url = "https://search.example.com/mcp"
+//! headers = { "x-tenant" = "acme" }
+//! auth = "secret-token"
+//!
+//! [[policy.rules]]
+//! scopes = ["files:read"]
+//! tools = ["read_*"]
+//! ```
+//!
+//! See [`policy`](crate::policy) for access rules,
+//! [`health`](crate::health) for the `[health]` section,
+//! [`cache`](crate::cache) for the `[cache]` section,
+//! [`transform`](crate::transform) for `[transforms]` and
+//! [`composite`](crate::composite) for `[composites]`.
+
+use crate::{
+    cache::CacheConfig, catalog::Prefixing, composite::CompositeTool, error::Error,
+    health::HealthConfig, policy::Policy, transform::ToolTransform, upstream::UpstreamConfig,
+};
+use serde::Deserialize;
+use std::{collections::HashMap, path::Path};
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GatewayConfig {
+    /// When to prefix exposed names. Defaults to `on_collision`.
+    #[serde(default)]
+    pub prefixing: Prefixing,
+    /// Separator between prefix and name. Defaults to `.`.
+    #[serde(default = "default_separator")]
+    pub separator: String,
+    #[serde(default)]
+    pub upstreams: Vec<UpstreamConfig>,
+    /// Which callers may call which tools.
+    #[serde(default)]
+    pub policy: Policy,
+    /// Health checking and circuit breaking of upstreams.
+    #[serde(default)]
+    pub health: HealthConfig,
+    /// Caching of tool results. Without it, nothing is cached.
+    #[serde(default)]
+    pub cache: Option<CacheConfig>,
+    /// Rewrites of exposed tools, by prefixed tool name.
+    #[serde(default)]
+    pub transforms: HashMap<String, ToolTransform>,
+    /// Tools calling other tools in turn, by exposed name.
+    #[serde(default)]
+    pub composites: HashMap<String, CompositeTool>,
+}
+
+fn default_separator() -> String {
+    ".".into()
+}
+
+impl Default for GatewayConfig {
+    fn default() -> Self {
+        Self {
+            prefixing: Prefixing::default(),
+            separator: default_separator(),
+            upstreams: Vec::new(),
+            policy: Policy::default(),
+            health: HealthConfig::default(),
+            cache: None,
+            transforms: HashMap::new(),
+            composites: HashMap::new(),
+        }
+    }
+}
+
+impl GatewayConfig {
+    /// Load a `.json` file as JSON, and anything else as TOML.
+    pub async fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let text = tokio::fs::read_to_string(path).await?;
+        if path.extension().is_some_and(|ext| ext == "json") {
+            Self::from_json(&text)
+        } else {
+            Self::from_toml(&text)
+        }
+    }
+
+    /// Pass the single `upstream` through with its names unchanged, to
+    /// bridge it to another transport.
+    pub fn bridge(upstream: UpstreamConfig) -> Self {
+        Self {
+            prefixing: Prefixing::Never,
+            upstreams: vec![upstream],
+            ..Default::default()
+        }
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self, Error> {
+        Ok(toml::from_str(text)?)
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, Error> {
+        Ok(serde_json::from_str(text)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GatewayConfig;
+    use crate::{
+        catalog::Prefixing,
+        upstream::{SessionStrategy, ToolFilter, Transport},
+    };
+    use std::time::Duration;
+
+    #[test]
+    fn parses_toml() {
+        let config = GatewayConfig::from_toml(
+            r#"
+            prefixing = "always"
+
+            [[upstreams]]
+            name = "fs"
+            command = "wmcp-filesystem"
+            args = ["/srv"]
+            tools = { include = ["read_*", "list_directory"], exclude = ["read_media_file"] }
+            sessions = "per_principal"
+            max_concurrency = 8
+            queue_timeout = 2.5
+            request_timeout = 0
+
+            [[upstreams]]
+            name = "search"
+            prefix = "web"
+            url = "https://search.example.com/mcp"
+            headers = { "x-tenant" = "acme" }
+
+            [[composites.snapshot.steps]]
+            tool = "fs.directory_tree"
+            arguments = { path = "{{ args.path }}" }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.prefixing, Prefixing::Always);
+        assert_eq!(config.separator, ".");
+        let [fs, search] = &config.upstreams[..] else {
+            panic!("expected two upstreams");
+        };
+        assert!(
+            matches!(&fs.transport, Transport::Stdio { command, args, .. }
+            if command == "wmcp-filesystem" && args == &["/srv"])
+        );
+        assert!(fs.tools.allows("read_file") && fs.tools.allows("list_directory"));
+        assert!(!fs.tools.allows("read_media_file") && !fs.tools.allows("write_file"));
+        assert_eq!(fs.sessions, SessionStrategy::PerPrincipal);
+        assert_eq!(fs.max_concurrency, Some(8));
+        assert_eq!(fs.queue_timeout, Duration::from_millis(2500));
+        assert_eq!(fs.request_timeout, Duration::ZERO);
+        assert_eq!(search.request_timeout, Duration::from_secs(60));
+        assert_eq!(search.sessions, SessionStrategy::Shared);
+        assert_eq!(search.pool_size, 1);
+        assert_eq!(search.prefix.as_deref(), Some("web"));
+        assert!(
+            matches!(&search.transport, Transport::Http { headers, auth: None, .. }
+            if headers["x-tenant"] == "acme")
+        );
+        assert_eq!(search.tools, ToolFilter::default());
+        let steps = &config.composites["snapshot"].steps;
+        assert_eq!(steps[0].id(), "fs.directory_tree");
+        assert_eq!(steps[0].arguments["path"], "{{ args.path }}");
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        for text in [
+            "prefix = \"always\"",
+            "[[upstreams]]\nname = \"fs\"\ncommand = \"fs\"\nsesions = \"per_session\"",
+            "[[upstreams]]\nname = \"fs\"\ncommand = \"fs\"\ntools = { exlude = [\"write_*\"] }",
+            "[health]\ntimout = 1",
+        ] {
+            assert!(GatewayConfig::from_toml(text).is_err(), "{text}");
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_tool_globs() {
+        let text =
+            "[[upstreams]]\nname = \"fs\"\ncommand = \"fs\"\ntools = { exclude = [\"write_[\"] }";
+        assert!(GatewayConfig::from_toml(text).is_err());
+    }
+
+    #[test]
+    fn parses_json() {
+        let config = GatewayConfig::from_json(
+            r#"{"upstreams": [{"name": "time", "command": "wmcp-time", "env": {"TZ": "UTC"}}]}"#,
+        )
+        .unwrap();
+        assert_eq!(config.prefixing, Prefixing::OnCollision);
+        assert!(
+            matches!(&config.upstreams[0].transport, Transport::Stdio { env, .. }
+            if env["TZ"] == "UTC")
+        );
+    }
+}
@@ -0,0 +1,57 @@
+//! Unified error types for the rmcp-gateway library.
+
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("upstream `{upstream}` failed to initialize: {source}")]
+    ClientInit {
+        upstream: String,
+        #[source]
+        source: Box<rmcp::service::ClientInitializeError>,
+    },
+
+    #[error("upstream `{upstream}` failed to start: {source}")]
+    Spawn {
+        upstream: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("upstream `{upstream}` did not initialize within {timeout:?}")]
+    ConnectTimeout {
+        upstream: String,
+        timeout: std::time::Duration,
+    },
+
+    #[error("upstream `{upstream}` error: {source}")]
+    Upstream {
+        upstream: String,
+        #[source]
+        source: rmcp::ServiceError,
+    },
+
+    #[error("upstream `{upstream}` has an invalid header: {header}")]
+    InvalidHeader { upstream: String, header: String },
+
+    #[error("duplicate upstream name: {0}")]
+    DuplicateUpstream(String),
+
+    #[error("failed to serve: {0}")]
+    ServerInit(#[from] Box<rmcp::service::ServerInitializeError>),
+
+    #[error("auth setup error: {0}")]
+    Auth(String),
+
+    #[error("invalid argument (expected NAME=VALUE): {0}")]
+    InvalidArg(String),
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("TOML error: {0}")]
+    Toml(#[from] toml::de::Error),
+}
@@ -0,0 +1,2332 @@
+//! [`Gateway`], the MCP server that merges its upstreams.
+
+use crate::{
+    cache::{self, Cache, CacheConfig, CacheStats},
+    catalog::{Catalog, Prefixing},
+    composite::{self, CompositeTool},
+    config::GatewayConfig,
+    error::Error,
+    health::{Backoff, HealthConfig},
+    policy::{Caller, Policy},
+    relay::Downstream,
+    transform::ToolTransform,
+    upstream::{SessionStrategy, Upstream, UpstreamConfig},
+};
+use futures::future::join_all;
+use rmcp::{
+    ErrorData, RoleServer, ServerHandler, ServiceError,
+    model::{
+        CallToolRequest, CallToolRequestParams, CallToolResult, ClientRequest, Content,
+        GetPromptRequest, GetPromptRequestParams, GetPromptResult, Implementation, JsonObject,
+        ListPromptsResult, ListResourceTemplatesResult, ListResourcesResult, ListToolsResult,
+        PaginatedRequestParams, PromptsCapability, ReadResourceRequest, ReadResourceRequestParams,
+        ReadResourceResult, ResourcesCapability, ServerCapabilities, ServerInfo, ServerResult,
+        SetLevelRequestParams, SubscribeRequest, SubscribeRequestParams, ToolsCapability,
+        UnsubscribeRequest, UnsubscribeRequestParams,
+    },
+    service::{NotificationContext, Peer, RequestContext},
+};
+use rmcp_axum::auth::{Principal, ScopedClaims};
+use std::future::Future;
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, OnceLock, RwLock, Weak},
+    time::{Duration, Instant, SystemTime},
+};
+use tokio::task::JoinHandle;
+
+/// An MCP server presenting the tools, prompts and resources of several
+/// upstream servers as one.
+///
+/// Tool and prompt names are prefixed according to [`Prefixing`], e.g.
+/// `fs.read_file`, and calls are routed to the upstream that owns the name.
+/// Resources keep their URIs and are routed by URI.
+///
+/// Clones share upstreams but not the connections dedicated to a session
+/// (see [`SessionStrategy`]), so serve one clone per downstream session.
+/// Changes to the merged surface, from [`Gateway::refresh`] or
+/// [`Gateway::reload`], are announced to every session with
+/// `notifications/*/list_changed`. Progress, cancellation, log messages,
+/// resource updates and requests upstreams send themselves are relayed
+/// between sessions and upstreams, see [`relay`](crate::relay).
+///
+/// ```rust,ignore
+/// use rmcp::ServiceExt;
+/// use rmcp_gateway::{Gateway, upstream::UpstreamConfig};
+///
+/// let gateway = Gateway::builder()
+///     .upstream(UpstreamConfig::stdio("fs", "wmcp-filesystem", ["/srv"]))
+///     .upstream(UpstreamConfig::http("search", "https://search.example.com/mcp"))
+///     .build()
+///     .await?;
+/// gateway.serve(rmcp::transport::stdio()).await?.waiting().await?;
+/// ```
+pub struct Gateway {
+    inner: Arc<Inner>,
+    session: Session,
+}
+
+/// Upstream connections dedicated to one downstream session. Dropped, and
+/// so closed, with it.
+#[derive(Default)]
+struct Session {
+    /// The session, once it sent a request.
+    downstream: OnceLock<Arc<Downstream>>,
+    /// Per-session connections, by upstream name.
+    connections: tokio::sync::Mutex<HashMap<String, Arc<Upstream>>>,
+    /// Per-principal connections the session keeps open, by upstream name.
+    principals: Mutex<HashMap<String, Arc<Upstream>>>,
+}
+
+struct Inner {
+    state: RwLock<State>,
+    /// Serializes refreshes and reloads.
+    update: tokio::sync::Mutex<()>,
+    /// Downstream sessions to notify of changes.
+    peers: Mutex<Vec<Peer<RoleServer>>>,
+    server_info: Implementation,
+    caller: Option<CallerFn>,
+    cache: Cache,
+    /// Per-principal connections, alive while a session holds them. Each
+    /// slot is locked while connecting, so other principals aren't held up.
+    principals: Mutex<HashMap<(String, String), PrincipalSlot>>,
+}
+
+struct State {
+    upstreams: Vec<Arc<Upstream>>,
+    prefixing: Prefixing,
+    separator: String,
+    policy: Arc<Policy>,
+    health: HealthConfig,
+    cache: Option<CacheConfig>,
+    transforms: HashMap<String, ToolTransform>,
+    composites: HashMap<String, CompositeTool>,
+    catalog: Arc<Catalog>,
+}
+
+/// A per-principal connection, locked while it is being opened.
+type PrincipalSlot = Arc<tokio::sync::Mutex<Weak<Upstream>>>;
+
+/// Identifies the caller from the extensions of its HTTP request.
+type CallerFn = Arc<dyn Fn(&http::Extensions) -> Caller + Send + Sync>;
+
+/// Builder for [`Gateway`].
+pub struct GatewayBuilder {
+    configs: Vec<UpstreamConfig>,
+    upstreams: Vec<Upstream>,
+    prefixing: Prefixing,
+    separator: String,
+    policy: Policy,
+    health: HealthConfig,
+    cache: Option<CacheConfig>,
+    transforms: HashMap<String, ToolTransform>,
+    composites: HashMap<String, CompositeTool>,
+    server_info: Implementation,
+    caller: Option<CallerFn>,
+}
+
+impl Clone for Gateway {
+    fn clone(&self) -> Self {
+        Self::new(self.inner.clone())
+    }
+}
+
+impl Gateway {
+    /// A gateway with a fresh session.
+    fn new(inner: Arc<Inner>) -> Self {
+        Self {
+            inner,
+            session: Session::default(),
+        }
+    }
+
+    pub fn builder() -> GatewayBuilder {
+        GatewayBuilder {
+            configs: Vec::new(),
+            upstreams: Vec::new(),
+            prefixing: Prefixing::default(),
+            separator: ".".into(),
+            policy: Policy::default(),
+            health: HealthConfig::default(),
+            cache: None,
+            transforms: HashMap::new(),
+            composites: HashMap::new(),
+            server_info: Implementation::from_build_env(),
+            caller: None,
+        }
+    }
+
+    /// Upstreams in configuration order.
+    pub fn upstreams(&self) -> Vec<Arc<Upstream>> {
+        self.state().upstreams.clone()
+    }
+
+    /// Hit and miss counters of the result cache.
+    pub fn cache_stats(&self) -> CacheStats {
+        self.inner.cache.stats()
+    }
+
+    /// Re-list every healthy upstream and rebuild the merged catalog.
+    pub async fn refresh(&self) -> Result<(), Error> {
+        let _update = self.inner.update.lock().await;
+        self.rebuild(false).await
+    }
+
+    /// Apply a new configuration without interrupting downstream sessions.
+    ///
+    /// Upstreams are matched by name: new ones are connected, removed ones
+    /// are dropped once their in-flight calls finish, and ones whose
+    /// configuration changed are restarted. Attached upstreams are kept.
+    /// An upstream that fails to connect is logged and keeps running with
+    /// its previous configuration, or is added unavailable so that health
+    /// checks connect it later.
+    pub async fn reload(&self, config: GatewayConfig) -> Result<(), Error> {
+        let _update = self.inner.update.lock().await;
+        let mut names = HashSet::new();
+        if let Some(upstream) = config
+            .upstreams
+            .iter()
+            .find(|u| !names.insert(u.name.as_str()))
+        {
+            return Err(Error::DuplicateUpstream(upstream.name.clone()));
+        }
+
+        let current: HashMap<_, _> = self
+            .upstreams()
+            .into_iter()
+            .map(|u| (u.name().to_string(), u))
+            .collect();
+        let health = &config.health;
+        let connects = config.upstreams.iter().map(|config| {
+            let current = current.get(&config.name).cloned();
+            async move {
+                match current {
+                    Some(upstream) if upstream.config() == Some(config) => Some(upstream),
+                    current => match Upstream::connect(config).await {
+                        Ok(upstream) => {
+                            tracing::info!(upstream = config.name, "connected upstream");
+                            Some(Arc::new(upstream))
+                        }
+                        Err(e) => {
+                            tracing::error!("failed to reload upstream: {e}");
+                            current
+                                .or_else(|| Some(Arc::new(Upstream::unavailable(config, health))))
+                        }
+                    },
+                }
+            }
+        });
+        let mut upstreams: Vec<_> = join_all(connects).await.into_iter().flatten().collect();
+        // Attached upstreams aren't described by any configuration.
+        upstreams.extend(
+            current
+                .into_values()
+                .filter(|u| u.config().is_none() && !names.contains(u.name())),
+        );
+
+        let policy_changed = {
+            let mut state = self.inner.state.write().expect("gateway state poisoned");
+            state.upstreams = upstreams;
+            state.prefixing = config.prefixing;
+            state.separator = config.separator;
+            let policy_changed = *state.policy != config.policy;
+            state.policy = Arc::new(config.policy);
+            state.health = config.health;
+            state.transforms = config.transforms;
+            state.composites = config.composites;
+            if state.cache != config.cache {
+                state.cache = config.cache;
+                self.inner.cache.clear();
+            }
+            policy_changed
+        };
+        // Policies decide which tools each session sees.
+        self.rebuild(policy_changed).await
+    }
+
+    /// Reload the configuration at `path` whenever it changes.
+    ///
+    /// Checks the file's modification time every `poll_interval`. Invalid
+    /// configurations are logged and ignored. The task stops once every
+    /// clone of the gateway is dropped.
+    pub fn watch(&self, path: impl Into<PathBuf>, poll_interval: Duration) -> JoinHandle<()> {
+        let path = path.into();
+        let modified = modified(&path);
+        tokio::spawn(watch_config(
+            Arc::downgrade(&self.inner),
+            path,
+            modified,
+            poll_interval,
+        ))
+    }
+
+    /// List the healthy upstreams, swap in the new catalog and notify
+    /// downstream sessions of what changed.
+    ///
+    /// Upstreams that fail to list are left out and their breaker opened.
+    async fn rebuild(&self, tools_changed: bool) -> Result<(), Error> {
+        let (upstreams, prefixing, separator, health, transforms, composites) = {
+            let state = self.state();
+            (
+                state.upstreams.clone(),
+                state.prefixing,
+                state.separator.clone(),
+                state.health.clone(),
+                state.transforms.clone(),
+                state.composites.clone(),
+            )
+        };
+        let health = &health;
+        let listings = upstreams
+            .into_iter()
+            .filter(|upstream| upstream.is_healthy())
+            .map(|upstream| async move {
+                match upstream.listing().await {
+                    Ok(listing) => Some((upstream, listing)),
+                    Err(e) => {
+                        tracing::warn!("leaving out unhealthy upstream: {e}");
+                        upstream.breaker.trip(health);
+                        None
+                    }
+                }
+            });
+        let listings = join_all(listings).await.into_iter().flatten().collect();
+        let catalog = Arc::new(Catalog::build(
+            listings,
+            prefixing,
+            &separator,
+            &transforms,
+            &composites,
+        ));
+        let previous = std::mem::replace(
+            &mut self
+                .inner
+                .state
+                .write()
+                .expect("gateway state poisoned")
+                .catalog,
+            catalog.clone(),
+        );
+        self.notify_changes(&previous, &catalog, tools_changed)
+            .await;
+        Ok(())
+    }
+
+    async fn notify_changes(&self, previous: &Catalog, catalog: &Catalog, tools_changed: bool) {
+        let tools = tools_changed || previous.tools != catalog.tools;
+        let prompts = previous.prompts != catalog.prompts;
+        let resources =
+            previous.resources != catalog.resources || previous.templates != catalog.templates;
+        if !(tools || prompts || resources) {
+            return;
+        }
+
+        let peers = {
+            let mut peers = self.inner.peers.lock().expect("gateway peers poisoned");
+            peers.retain(|peer| !peer.is_transport_closed());
+            peers.clone()
+        };
+        for peer in peers {
+            let mut result = Ok(());
+            if tools {
+                result = result.and(peer.notify_tool_list_changed().await);
+            }
+            if prompts {
+                result = result.and(peer.notify_prompt_list_changed().await);
+            }
+            if resources {
+                result = result.and(peer.notify_resource_list_changed().await);
+            }
+            if let Err(e) = result {
+                tracing::debug!("failed to notify downstream session: {e}");
+            }
+        }
+    }
+
+    fn state(&self) -> std::sync::RwLockReadGuard<'_, State> {
+        self.inner.state.read().expect("gateway state poisoned")
+    }
+
+    fn catalog(&self) -> Arc<Catalog> {
+        self.state().catalog.clone()
+    }
+
+    fn policy(&self) -> Arc<Policy> {
+        self.state().policy.clone()
+    }
+
+    /// Send `request` to `upstream` through its circuit breaker.
+    async fn forward<T>(
+        &self,
+        upstream: &Upstream,
+        request: impl Future<Output = Result<T, ServiceError>>,
+    ) -> Result<T, ErrorData> {
+        if !upstream.breaker.allows() {
+            return Err(ErrorData::internal_error(
+                format!("upstream `{}` is unavailable", upstream.name()),
+                None,
+            ));
+        }
+        let Ok(_slot) = upstream.slot().await else {
+            return Err(busy(upstream));
+        };
+        let health = self.state().health.clone();
+        let result = request.await;
+        // MCP errors are answers, and cancellations the caller's doing, so
+        // neither counts as a failure.
+        let changed = match &result {
+            Ok(_) | Err(ServiceError::McpError(_) | ServiceError::Cancelled { .. }) => {
+                upstream.breaker.success()
+            }
+            Err(_) => upstream.breaker.failure(&health),
+        };
+        if changed {
+            tracing::warn!(
+                upstream = upstream.name(),
+                healthy = upstream.is_healthy(),
+                "upstream health changed"
+            );
+            let gateway = self.clone();
+            tokio::spawn(async move { gateway.refresh().await });
+        }
+        result.map_err(|err| upstream_error(upstream, err))
+    }
+
+    /// Forward `request` from the session of `context` to `upstream` over
+    /// `connection`, one of its connections.
+    async fn request(
+        &self,
+        upstream: &Upstream,
+        connection: &Upstream,
+        request: ClientRequest,
+        context: &RequestContext<RoleServer>,
+    ) -> Result<ServerResult, ErrorData> {
+        let session = self.downstream(context);
+        self.forward(upstream, connection.request(request, &session, context))
+            .await
+    }
+
+    /// The session of `context`, as seen from upstreams.
+    fn downstream(&self, context: &RequestContext<RoleServer>) -> Arc<Downstream> {
+        self.session
+            .downstream
+            .get_or_init(|| Arc::new(Downstream::new(context.peer.clone())))
+            .clone()
+    }
+
+    /// Ping every upstream, update their breakers, restart unhealthy
+    /// configured upstreams whose backoff has elapsed, and rebuild the
+    /// catalog if anything changed.
+    async fn check_health(&self, restarts: &mut HashMap<String, Backoff>) {
+        let health = self.state().health.clone();
+        let upstreams = self.upstreams();
+        let checks = upstreams.iter().map(|upstream| async {
+            if upstream.is_closed() {
+                return upstream.breaker.trip(&health);
+            }
+            match upstream.ping(health.timeout).await {
+                Ok(()) => upstream.breaker.success(),
+                Err(e) => {
+                    tracing::debug!(upstream = upstream.name(), "ping failed: {e}");
+                    upstream.breaker.failure(&health)
+                }
+            }
+        });
+        let mut changed = join_all(checks).await.into_iter().any(|changed| changed);
+
+        let mut replacements = Vec::new();
+        for upstream in &upstreams {
+            let Some(config) = upstream.config().filter(|_| !upstream.is_healthy()) else {
+                if restarts
+                    .get(upstream.name())
+                    .is_some_and(|backoff| backoff.settled(&health))
+                {
+                    restarts.remove(upstream.name());
+                }
+                continue;
+            };
+            let backoff = restarts
+                .entry(upstream.name().to_string())
+                .or_insert_with(|| Backoff::new(&health));
+            if Instant::now() < backoff.next {
+                continue;
+            }
+            backoff.attempted(&health);
+            match Upstream::connect(config).await {
+                Ok(restarted) => {
+                    tracing::info!(upstream = upstream.name(), "restarted upstream");
+                    replacements.push((upstream.clone(), Arc::new(restarted)));
+                }
+                Err(e) => tracing::warn!("failed to restart upstream: {e}"),
+            }
+        }
+        restarts.retain(|name, _| upstreams.iter().any(|u| u.name() == name));
+
+        let _update = self.inner.update.lock().await;
+        if !replacements.is_empty() {
+            let mut state = self.inner.state.write().expect("gateway state poisoned");
+            for (old, new) in replacements {
+                // Unless a reload replaced it meanwhile.
+                if let Some(slot) = state.upstreams.iter_mut().find(|u| Arc::ptr_eq(u, &old)) {
+                    *slot = new;
+                    changed = true;
+                }
+            }
+        }
+        if changed && let Err(e) = self.rebuild(false).await {
+            tracing::error!("failed to rebuild catalog: {e}");
+        }
+    }
+
+    /// The connection to send `caller`'s requests to `upstream` over,
+    /// according to its [`SessionStrategy`].
+    async fn connection(
+        &self,
+        upstream: &Arc<Upstream>,
+        caller: &Caller,
+    ) -> Result<Arc<Upstream>, ErrorData> {
+        let Some(config) = upstream.config() else {
+            return Ok(upstream.clone());
+        };
+        // Connections made before a reload or restart are replaced.
+        let usable = |connection: &Arc<Upstream>| {
+            connection.config() == Some(config) && !connection.is_closed()
+        };
+        let connect = || async {
+            let Ok(slot) = upstream.connection_slot().await else {
+                return Err(busy(upstream));
+            };
+            let connection = Upstream::dedicated(config, slot)
+                .await
+                .map_err(|e| ErrorData::internal_error(e.to_string(), None))?;
+            tracing::debug!(upstream = config.name, "opened dedicated connection");
+            Ok::<_, ErrorData>(Arc::new(connection))
+        };
+
+        match (config.sessions, &caller.principal) {
+            (SessionStrategy::Shared, _) => Ok(upstream.clone()),
+            (SessionStrategy::PerPrincipal, Some(principal)) => {
+                let slot = {
+                    let mut principals = self
+                        .inner
+                        .principals
+                        .lock()
+                        .expect("gateway principals poisoned");
+                    principals.retain(|_, slot| {
+                        Arc::strong_count(slot) > 1
+                            || slot.try_lock().is_ok_and(|c| c.strong_count() > 0)
+                    });
+                    principals
+                        .entry((config.name.clone(), principal.clone()))
+                        .or_default()
+                        .clone()
+                };
+                let mut slot = slot.lock().await;
+                let connection = match slot.upgrade() {
+                    Some(connection) if usable(&connection) => connection,
+                    _ => {
+                        // Free the slot of a stale connection first.
+                        self.session
+                            .principals
+                            .lock()
+                            .expect("gateway session poisoned")
+                            .remove(&config.name);
+                        let connection = connect().await?;
+                        *slot = Arc::downgrade(&connection);
+                        connection
+                    }
+                };
+                self.session
+                    .principals
+                    .lock()
+                    .expect("gateway session poisoned")
+                    .insert(config.name.clone(), connection.clone());
+                Ok(connection)
+            }
+            // Anonymous callers of per-principal upstreams get their own.
+            _ => {
+                let mut connections = self.session.connections.lock().await;
+                match connections.get(&config.name) {
+                    Some(connection) if usable(connection) => Ok(connection.clone()),
+                    _ => {
+                        connections.remove(&config.name);
+                        let connection = connect().await?;
+                        connections.insert(config.name.clone(), connection.clone());
+                        Ok(connection)
+                    }
+                }
+            }
+        }
+    }
+
+    /// Call a tool served by an upstream, as `caller`.
+    async fn call_upstream_tool(
+        &self,
+        request: CallToolRequestParams,
+        caller: &Caller,
+        context: &RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let (catalog, policy, cache) = {
+            let state = self.state();
+            (
+                state.catalog.clone(),
+                state.policy.clone(),
+                state.cache.clone(),
+            )
+        };
+        let route = catalog.tool(&request.name);
+        let arguments = match route.and_then(|route| route.transform.as_ref()) {
+            Some(transform) => transform.arguments(request.arguments),
+            None => request.arguments,
+        };
+        // Checked before the lookup, so denied callers can't probe for tools.
+        if let Err(denial) = policy.check(caller, &request.name, arguments.as_ref()) {
+            tracing::info!(principal = caller.principal, tool = %request.name, "denied: {denial}");
+            return Ok(CallToolResult::error(vec![Content::text(denial)]));
+        }
+        let route = route.ok_or_else(|| ErrorData::invalid_params("tool not found", None))?;
+        // Dedicated connections may answer from per-session state.
+        let shared = route
+            .upstream
+            .config()
+            .is_none_or(|config| config.sessions == SessionStrategy::Shared);
+        let cached = cache.filter(|_| shared).and_then(|config| {
+            let tool = catalog.tools.iter().find(|t| t.name == request.name)?;
+            let key = cache::Key::new(route.upstream.name(), &route.name, arguments.as_ref());
+            Some((key, config.ttl(tool)?, config.max_entries))
+        });
+        if let Some((key, ..)) = &cached
+            && let Some(result) = self.inner.cache.get(key)
+        {
+            return Ok(result);
+        }
+
+        let request = CallToolRequestParams {
+            name: route.name.clone().into(),
+            arguments,
+            ..request
+        };
+        let upstream = &route.upstream;
+        let connection = self.connection(upstream, caller).await?;
+        let request = ClientRequest::CallToolRequest(CallToolRequest::new(request));
+        let ServerResult::CallToolResult(result) = self
+            .request(upstream, &connection, request, context)
+            .await?
+        else {
+            return Err(unexpected(upstream));
+        };
+        if let Some((key, ttl, max_entries)) = cached
+            && result.is_error != Some(true)
+        {
+            self.inner
+                .cache
+                .insert(key, result.clone(), ttl, max_entries);
+        }
+        Ok(result)
+    }
+
+    /// Run the steps of `composite` in turn, each checked against the
+    /// policy like a call of its own. Stops at the first error result.
+    async fn call_composite(
+        &self,
+        composite: &CompositeTool,
+        request: CallToolRequestParams,
+        caller: &Caller,
+        context: &RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let arguments = composite.arguments(request.arguments.clone());
+        let policy = self.policy();
+        if let Err(denial) = policy.check(caller, &request.name, Some(&arguments)) {
+            tracing::info!(principal = caller.principal, tool = %request.name, "denied: {denial}");
+            return Ok(CallToolResult::error(vec![Content::text(denial)]));
+        }
+        let mut outputs = JsonObject::new();
+        let mut result = CallToolResult::success(Vec::new());
+        for step in &composite.steps {
+            let step_arguments = match step.arguments(&arguments, &outputs) {
+                Ok(step_arguments) => step_arguments,
+                Err(e) => {
+                    let error = format!("step `{}`: {e}", step.id());
+                    return Ok(CallToolResult::error(vec![Content::text(error)]));
+                }
+            };
+            let request = CallToolRequestParams {
+                name: step.tool.clone().into(),
+                arguments: Some(step_arguments),
+                ..request.clone()
+            };
+            result = self.call_upstream_tool(request, caller, context).await?;
+            if result.is_error == Some(true) {
+                break;
+            }
+            outputs.insert(step.id().into(), composite::output(&result));
+        }
+        Ok(result)
+    }
+
+    /// The caller of a request, from the HTTP request it arrived in.
+    fn caller(&self, context: &RequestContext<RoleServer>) -> Caller {
+        let parts = context.extensions.get::<http::request::Parts>();
+        match (&self.inner.caller, parts) {
+            (Some(caller), Some(parts)) => caller(&parts.extensions),
+            _ => Caller::default(),
+        }
+    }
+}
+
+async fn supervise(inner: Weak<Inner>) {
+    let mut restarts = HashMap::new();
+    loop {
+        let Some(interval) = inner.upgrade().map(|inner| {
+            let gateway = Gateway::new(inner);
+            gateway.state().health.interval
+        }) else {
+            return;
+        };
+        tokio::time::sleep(interval).await;
+        // Stop once the gateway is gone.
+        let Some(inner) = inner.upgrade() else {
+            return;
+        };
+        Gateway::new(inner).check_health(&mut restarts).await;
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+async fn watch_config(
+    inner: Weak<Inner>,
+    path: PathBuf,
+    mut last: Option<SystemTime>,
+    poll_interval: Duration,
+) {
+    loop {
+        tokio::time::sleep(poll_interval).await;
+        // Stop once the gateway is gone.
+        let Some(inner) = inner.upgrade() else {
+            return;
+        };
+        let current = modified(&path);
+        if current == last {
+            continue;
+        }
+        last = current;
+        let gateway = Gateway::new(inner);
+        let result = match GatewayConfig::load(&path).await {
+            Ok(config) => gateway.reload(config).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(()) => tracing::info!("reloaded {}", path.display()),
+            Err(e) => tracing::error!("failed to reload {}: {e}", path.display()),
+        }
+    }
+}
+
+impl GatewayBuilder {
+    /// Use the upstreams and naming of `config`.
+    pub fn config(mut self, config: GatewayConfig) -> Self {
+        self.configs.extend(config.upstreams);
+        self.prefixing = config.prefixing;
+        self.separator = config.separator;
+        self.policy = config.policy;
+        self.health = config.health;
+        self.cache = config.cache;
+        self.transforms = config.transforms;
+        self.composites = config.composites;
+        self
+    }
+
+    /// Connect to the upstream described by `config`.
+    pub fn upstream(mut self, config: UpstreamConfig) -> Self {
+        self.configs.push(config);
+        self
+    }
+
+    /// Add an already connected upstream.
+    ///
+    /// Attached upstreams come after the configured ones.
+    pub fn attach(mut self, upstream: Upstream) -> Self {
+        self.upstreams.push(upstream);
+        self
+    }
+
+    /// When to prefix exposed names. Defaults to [`Prefixing::OnCollision`].
+    pub fn prefixing(mut self, prefixing: Prefixing) -> Self {
+        self.prefixing = prefixing;
+        self
+    }
+
+    /// Separator between prefix and name. Defaults to `.`.
+    pub fn separator(mut self, separator: impl Into<String>) -> Self {
+        self.separator = separator.into();
+        self
+    }
+
+    /// Restrict which callers may call which tools. Defaults to allowing
+    /// everything.
+    pub fn policy(mut self, policy: Policy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// How upstreams are health checked and restarted.
+    pub fn health(mut self, health: HealthConfig) -> Self {
+        self.health = health;
+        self
+    }
+
+    /// Rewrite the tool exposed as `tool`, by its prefixed name.
+    pub fn transform(mut self, tool: impl Into<String>, transform: ToolTransform) -> Self {
+        self.transforms.insert(tool.into(), transform);
+        self
+    }
+
+    /// Expose `composite` as the tool `name`.
+    pub fn composite(mut self, name: impl Into<String>, composite: CompositeTool) -> Self {
+        self.composites.insert(name.into(), composite);
+        self
+    }
+
+    /// Cache results of idempotent tools. Defaults to no caching.
+    pub fn cache(mut self, cache: CacheConfig) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Identify callers by their claims of type `C`, as inserted by
+    /// rmcp-axum's [`AuthLayer`](rmcp_axum::auth::AuthLayer) when the gateway
+    /// is served over streamable HTTP.
+    ///
+    /// Without this, or without claims, callers are anonymous to the
+    /// [`Policy`].
+    pub fn claims<C>(mut self) -> Self
+    where
+        C: Principal + ScopedClaims + Clone + Send + Sync + 'static,
+    {
+        self.caller = Some(Arc::new(|extensions: &http::Extensions| {
+            extensions
+                .get::<C>()
+                .map(|claims| Caller {
+                    principal: Some(claims.principal().into()),
+                    scopes: claims.scopes().to_vec(),
+                })
+                .unwrap_or_default()
+        }));
+        self
+    }
+
+    /// Name and version the gateway reports to clients.
+    pub fn server_info(mut self, server_info: Implementation) -> Self {
+        self.server_info = server_info;
+        self
+    }
+
+    /// What the built gateway will announce to clients.
+    pub(crate) fn info(&self) -> ServerInfo {
+        info(self.server_info.clone())
+    }
+
+    /// Connect to all upstreams concurrently, list their contents and start
+    /// health checking them.
+    ///
+    /// Upstreams that fail to connect start out unhealthy and are
+    /// reconnected by the health checks.
+    pub async fn build(self) -> Result<Gateway, Error> {
+        let connects = self.configs.iter().map(|config| async {
+            Upstream::connect(config).await.unwrap_or_else(|e| {
+                tracing::error!("failed to connect upstream: {e}");
+                Upstream::unavailable(config, &self.health)
+            })
+        });
+        let mut upstreams = join_all(connects).await;
+        upstreams.extend(self.upstreams);
+
+        let mut names = HashSet::new();
+        if let Some(upstream) = upstreams.iter().find(|u| !names.insert(u.name())) {
+            return Err(Error::DuplicateUpstream(upstream.name().into()));
+        }
+
+        let gateway = Gateway::new(Arc::new(Inner {
+            state: RwLock::new(State {
+                upstreams: upstreams.into_iter().map(Arc::new).collect(),
+                prefixing: self.prefixing,
+                separator: self.separator,
+                policy: Arc::new(self.policy),
+                health: self.health,
+                cache: self.cache,
+                transforms: self.transforms,
+                composites: self.composites,
+                catalog: Arc::default(),
+            }),
+            update: tokio::sync::Mutex::default(),
+            peers: Mutex::default(),
+            server_info: self.server_info,
+            caller: self.caller,
+            cache: Cache::default(),
+            principals: Mutex::default(),
+        }));
+        gateway.refresh().await?;
+        tokio::spawn(supervise(Arc::downgrade(&gateway.inner)));
+        Ok(gateway)
+    }
+}
+
+/// What a gateway announces to clients: every capability, since reloads
+/// may add upstreams that provide it.
+pub(crate) fn info(server_info: Implementation) -> ServerInfo {
+    ServerInfo {
+        capabilities: ServerCapabilities {
+            tools: Some(ToolsCapability {
+                list_changed: Some(true),
+            }),
+            prompts: Some(PromptsCapability {
+                list_changed: Some(true),
+            }),
+            resources: Some(ResourcesCapability {
+                subscribe: Some(true),
+                list_changed: Some(true),
+            }),
+            logging: Some(JsonObject::new()),
+            ..Default::default()
+        },
+        server_info,
+        ..Default::default()
+    }
+}
+
+/// A response of the wrong type from `upstream`.
+fn unexpected(upstream: &Upstream) -> ErrorData {
+    upstream_error(upstream, ServiceError::UnexpectedResponse)
+}
+
+/// Pass upstream MCP errors through and report everything else as internal.
+fn upstream_error(upstream: &Upstream, err: ServiceError) -> ErrorData {
+    match err {
+        ServiceError::McpError(err) => err,
+        err => ErrorData::internal_error(format!("upstream `{}`: {err}", upstream.name()), None),
+    }
+}
+
+/// A request or connection slot of `upstream` didn't free up in time.
+fn busy(upstream: &Upstream) -> ErrorData {
+    ErrorData::internal_error(format!("upstream `{}` is busy", upstream.name()), None)
+}
+
+impl ServerHandler for Gateway {
+    fn get_info(&self) -> ServerInfo {
+        info(self.inner.server_info.clone())
+    }
+
+    async fn on_initialized(&self, context: NotificationContext<RoleServer>) {
+        let mut peers = self.inner.peers.lock().expect("gateway peers poisoned");
+        peers.retain(|peer| !peer.is_transport_closed());
+        peers.push(context.peer);
+    }
+
+    async fn list_tools(
+        &self,
+        _request: Option<PaginatedRequestParams>,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, ErrorData> {
+        let caller = self.caller(&context);
+        let policy = self.policy();
+        let tools = self
+            .catalog()
+            .tools
+            .iter()
+            .filter(|tool| policy.allows_tool(&caller, &tool.name))
+            .cloned()
+            .collect();
+        Ok(ListToolsResult::with_all_items(tools))
+    }
+
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParams,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let caller = self.caller(&context);
+        let catalog = self.catalog();
+        match catalog.composite(&request.name) {
+            Some(composite) => {
+                self.call_composite(composite, request, &caller, &context)
+                    .await
+            }
+            None => self.call_upstream_tool(request, &caller, &context).await,
+        }
+    }
+
+    async fn list_prompts(
+        &self,
+        _request: Option<PaginatedRequestParams>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListPromptsResult, ErrorData> {
+        Ok(ListPromptsResult::with_all_items(
+            self.catalog().prompts.clone(),
+        ))
+    }
+
+    async fn get_prompt(
+        &self,
+        request: GetPromptRequestParams,
+        context: RequestContext<RoleServer>,
+    ) -> Result<GetPromptResult, ErrorData> {
+        let catalog = self.catalog();
+        let route = catalog
+            .prompt(&request.name)
+            .ok_or_else(|| ErrorData::invalid_params("prompt not found", None))?;
+        let request = GetPromptRequestParams {
+            name: route.name.clone(),
+            ..request
+        };
+        let upstream = &route.upstream;
+        let connection = self.connection(upstream, &self.caller(&context)).await?;
+        let request = ClientRequest::GetPromptRequest(GetPromptRequest::new(request));
+        match self
+            .request(upstream, &connection, request, &context)
+            .await?
+        {
+            ServerResult::GetPromptResult(result) => Ok(result),
+            _ => Err(unexpected(upstream)),
+        }
+    }
+
+    async fn list_resources(
+        &self,
+        _request: Option<PaginatedRequestParams>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListResourcesResult, ErrorData> {
+        Ok(ListResourcesResult::with_all_items(
+            self.catalog().resources.clone(),
+        ))
+    }
+
+    async fn list_resource_templates(
+        &self,
+        _request: Option<PaginatedRequestParams>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListResourceTemplatesResult, ErrorData> {
+        Ok(ListResourceTemplatesResult::with_all_items(
+            self.catalog().templates.clone(),
+        ))
+    }
+
+    async fn read_resource(
+        &self,
+        request: ReadResourceRequestParams,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ReadResourceResult, ErrorData> {
+        let catalog = self.catalog();
+        let upstream = catalog.resource(&request.uri).ok_or_else(|| {
+            ErrorData::resource_not_found(format!("resource not found: {}", request.uri), None)
+        })?;
+        let connection = self.connection(upstream, &self.caller(&context)).await?;
+        let request = ClientRequest::ReadResourceRequest(ReadResourceRequest::new(request));
+        match self
+            .request(upstream, &connection, request, &context)
+            .await?
+        {
+            ServerResult::ReadResourceResult(result) => Ok(result),
+            _ => Err(unexpected(upstream)),
+        }
+    }
+
+    /// Subscribes the upstream on behalf of its first subscribing session.
+    async fn subscribe(
+        &self,
+        request: SubscribeRequestParams,
+        context: RequestContext<RoleServer>,
+    ) -> Result<(), ErrorData> {
+        let catalog = self.catalog();
+        let upstream = catalog.resource(&request.uri).ok_or_else(|| {
+            ErrorData::resource_not_found(format!("resource not found: {}", request.uri), None)
+        })?;
+        let connection = self.connection(upstream, &self.caller(&context)).await?;
+        let session = self.downstream(&context);
+        if !connection.routes().subscribe(&request.uri, &session) {
+            return Ok(());
+        }
+        let uri = request.uri.clone();
+        let request = ClientRequest::SubscribeRequest(SubscribeRequest::new(request));
+        let result = self.request(upstream, &connection, request, &context).await;
+        if result.is_err() {
+            connection.routes().unsubscribe(&uri, &session);
+        }
+        result.map(drop)
+    }
+
+    /// Unsubscribes the upstream once its last subscribing session left.
+    async fn unsubscribe(
+        &self,
+        request: UnsubscribeRequestParams,
+        context: RequestContext<RoleServer>,
+    ) -> Result<(), ErrorData> {
+        let catalog = self.catalog();
+        let upstream = catalog.resource(&request.uri).ok_or_else(|| {
+            ErrorData::resource_not_found(format!("resource not found: {}", request.uri), None)
+        })?;
+        let connection = self.connection(upstream, &self.caller(&context)).await?;
+        if !connection
+            .routes()
+            .unsubscribe(&request.uri, &self.downstream(&context))
+        {
+            return Ok(());
+        }
+        let request = ClientRequest::UnsubscribeRequest(UnsubscribeRequest::new(request));
+        self.request(upstream, &connection, request, &context)
+            .await
+            .map(drop)
+    }
+
+    /// Log messages from upstreams are filtered by the gateway, per session.
+    async fn set_level(
+        &self,
+        request: SetLevelRequestParams,
+        context: RequestContext<RoleServer>,
+    ) -> Result<(), ErrorData> {
+        self.downstream(&context).set_level(request.level);
+        Ok(())
+    }
+
+    /// Passed on to the upstream connections the session uses, which then
+    /// ask it for its roots again.
+    async fn on_roots_list_changed(&self, _context: NotificationContext<RoleServer>) {
+        let Some(session) = self.session.downstream.get() else {
+            return;
+        };
+        let mut connections = self.upstreams();
+        connections.extend(self.session.connections.lock().await.values().cloned());
+        connections.extend(
+            self.session
+                .principals
+                .lock()
+                .expect("gateway session poisoned")
+                .values()
+                .cloned(),
+        );
+        for connection in connections {
+            if connection.routes().serves(session) {
+                connection.notify_roots_list_changed().await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Gateway;
+    use crate::{
+        cache::{CacheConfig, CacheStats},
+        catalog::Prefixing,
+        composite::CompositeTool,
+        config::GatewayConfig,
+        health::HealthConfig,
+        policy::{Glob, Policy},
+        relay::Relay,
+        transform::ToolTransform,
+        upstream::{SessionStrategy, ToolFilter, Transport, Upstream, UpstreamConfig},
+    };
+    use rmcp::{
+        ClientHandler, ErrorData, RoleClient, RoleServer, ServerHandler, ServiceError, ServiceExt,
+        handler::server::{router::tool::ToolRouter, wrapper::Parameters},
+        model::{
+            AnnotateAble, CallToolRequest, CallToolRequestParams, ClientCapabilities, ClientInfo,
+            ClientRequest, ListResourcesResult, ListRootsResult, LoggingLevel,
+            LoggingMessageNotificationParam, PaginatedRequestParams, ProgressNotificationParam,
+            ProgressToken, RawResource, ResourceUpdatedNotificationParam, Root, ServerCapabilities,
+            ServerInfo, SubscribeRequestParams,
+        },
+        schemars::JsonSchema,
+        service::{NotificationContext, Peer, PeerRequestOptions, RequestContext, RunningService},
+        tool, tool_handler, tool_router,
+        transport::{
+            StreamableHttpClientTransport,
+            streamable_http_client::StreamableHttpClientTransportConfig,
+            streamable_http_server::session::{SessionManager, local::LocalSessionManager},
+        },
+    };
+    use rmcp_axum::{
+        auth::{AuthLayer, BearerAuth, Validator, oauth::OAuthClaims},
+        server::McpServerBuilder,
+    };
+    use serde::Deserialize;
+    use serde_json::{Value, json};
+    use std::{
+        collections::HashMap,
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
+        time::Duration,
+    };
+    use tokio::sync::mpsc;
+
+    #[derive(Clone)]
+    struct Files {
+        tool_router: ToolRouter<Self>,
+    }
+
+    #[tool_router]
+    impl Files {
+        fn new() -> Self {
+            Self {
+                tool_router: Self::tool_router(),
+            }
+        }
+
+        #[tool(description = "Read a file")]
+        async fn read_file(&self) -> String {
+            "fs:read_file".into()
+        }
+
+        #[tool(description = "Repository status")]
+        async fn status(&self) -> String {
+            "fs:status".into()
+        }
+    }
+
+    #[tool_handler]
+    impl ServerHandler for Files {
+        fn get_info(&self) -> ServerInfo {
+            ServerInfo {
+                capabilities: ServerCapabilities::builder().enable_tools().build(),
+                ..Default::default()
+            }
+        }
+    }
+
+    #[derive(Deserialize, JsonSchema)]
+    #[schemars(crate = "rmcp::schemars")]
+    struct TreeRequest {
+        /// Root directory.
+        path: String,
+    }
+
+    #[derive(Deserialize, JsonSchema)]
+    #[schemars(crate = "rmcp::schemars")]
+    struct ReadRequest {
+        paths: Vec<String>,
+    }
+
+    /// A filesystem server with a fixed tree below any path.
+    #[derive(Clone)]
+    struct Tree {
+        tool_router: ToolRouter<Self>,
+    }
+
+    #[tool_router]
+    impl Tree {
+        fn new() -> Self {
+            Self {
+                tool_router: Self::tool_router(),
+            }
+        }
+
+        #[tool(description = "Directory tree as JSON")]
+        async fn directory_tree(&self, Parameters(request): Parameters<TreeRequest>) -> String {
+            let name = request.path.rsplit('/').next().unwrap_or_default();
+            json!({
+                "name": name,
+                "type": "directory",
+                "children": [
+                    { "name": "src", "type": "directory", "children": [
+                        { "name": "lib.rs", "type": "file" },
+                        { "name": "notes.md", "type": "file" },
+                    ] },
+                    { "name": "build.rs", "type": "file" },
+                ],
+            })
+            .to_string()
+        }
+
+        #[tool(description = "Read files")]
+        async fn read_multiple_files(
+            &self,
+            Parameters(request): Parameters<ReadRequest>,
+        ) -> String {
+            request.paths.join(",")
+        }
+    }
+
+    #[tool_handler]
+    impl ServerHandler for Tree {
+        fn get_info(&self) -> ServerInfo {
+            ServerInfo {
+                capabilities: ServerCapabilities::builder().enable_tools().build(),
+                ..Default::default()
+            }
+        }
+    }
+
+    #[derive(Clone)]
+    struct Git {
+        tool_router: ToolRouter<Self>,
+    }
+
+    #[tool_router]
+    impl Git {
+        fn new() -> Self {
+            Self {
+                tool_router: Self::tool_router(),
+            }
+        }
+
+        #[tool(description = "Repository status")]
+        async fn status(&self) -> String {
+            "git:status".into()
+        }
+    }
+
+    #[tool_handler]
+    impl ServerHandler for Git {
+        fn get_info(&self) -> ServerInfo {
+            ServerInfo {
+                capabilities: ServerCapabilities::builder().enable_tools().build(),
+                ..Default::default()
+            }
+        }
+    }
+
+    #[derive(Clone)]
+    struct Clock {
+        ticks: Arc<AtomicUsize>,
+        tool_router: ToolRouter<Self>,
+    }
+
+    #[tool_router]
+    impl Clock {
+        fn new() -> Self {
+            Self {
+                ticks: Arc::default(),
+                tool_router: Self::tool_router(),
+            }
+        }
+
+        #[tool(description = "Read the clock", annotations(read_only_hint = true))]
+        async fn now(&self) -> String {
+            self.ticks.fetch_add(1, Ordering::SeqCst).to_string()
+        }
+
+        #[tool(description = "Advance the clock")]
+        async fn tick(&self) -> String {
+            self.ticks.fetch_add(1, Ordering::SeqCst).to_string()
+        }
+    }
+
+    #[tool_handler]
+    impl ServerHandler for Clock {
+        fn get_info(&self) -> ServerInfo {
+            ServerInfo {
+                capabilities: ServerCapabilities::builder().enable_tools().build(),
+                ..Default::default()
+            }
+        }
+    }
+
+    /// Talks to its client on its own: reports progress, logs, asks for
+    /// roots and announces updates of `work://status`.
+    #[derive(Clone)]
+    struct Worker {
+        cancelled: mpsc::UnboundedSender<()>,
+        subscribed: mpsc::UnboundedSender<Peer<RoleServer>>,
+        tool_router: ToolRouter<Self>,
+    }
+
+    #[tool_router]
+    impl Worker {
+        fn new() -> (
+            Self,
+            mpsc::UnboundedReceiver<()>,
+            mpsc::UnboundedReceiver<Peer<RoleServer>>,
+        ) {
+            let (cancelled, cancellations) = mpsc::unbounded_channel();
+            let (subscribed, subscribers) = mpsc::unbounded_channel();
+            let worker = Self {
+                cancelled,
+                subscribed,
+                tool_router: Self::tool_router(),
+            };
+            (worker, cancellations, subscribers)
+        }
+
+        #[tool(description = "Work until cancelled")]
+        async fn work(&self, context: RequestContext<RoleServer>) -> String {
+            let progress = ProgressNotificationParam {
+                progress_token: context.meta.get_progress_token().unwrap(),
+                progress: 1.0,
+                total: None,
+                message: Some("started".into()),
+            };
+            context.peer.notify_progress(progress).await.unwrap();
+            let log = LoggingMessageNotificationParam {
+                level: LoggingLevel::Info,
+                logger: None,
+                data: json!("working"),
+            };
+            context.peer.notify_logging_message(log).await.unwrap();
+            context.ct.cancelled().await;
+            let _ = self.cancelled.send(());
+            "cancelled".into()
+        }
+
+        #[tool(description = "List the client's roots")]
+        async fn roots(&self, peer: Peer<RoleServer>) -> String {
+            match peer.list_roots().await {
+                Ok(result) => result.roots[0].uri.clone(),
+                Err(e) => e.to_string(),
+            }
+        }
+    }
+
+    #[tool_handler]
+    impl ServerHandler for Worker {
+        fn get_info(&self) -> ServerInfo {
+            ServerInfo {
+                capabilities: ServerCapabilities::builder()
+                    .enable_tools()
+                    .enable_logging()
+                    .enable_resources()
+                    .enable_resources_subscribe()
+                    .build(),
+                ..Default::default()
+            }
+        }
+
+        async fn list_resources(
+            &self,
+            _request: Option<PaginatedRequestParams>,
+            _context: RequestContext<RoleServer>,
+        ) -> Result<ListResourcesResult, ErrorData> {
+            let status = RawResource::new("work://status", "status").no_annotation();
+            Ok(ListResourcesResult::with_all_items(vec![status]))
+        }
+
+        async fn subscribe(
+            &self,
+            _request: SubscribeRequestParams,
+            context: RequestContext<RoleServer>,
+        ) -> Result<(), ErrorData> {
+            let _ = self.subscribed.send(context.peer);
+            Ok(())
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Event {
+        Progress(ProgressToken, Option<String>),
+        Log(Option<String>, Value),
+        Updated(String),
+    }
+
+    /// Downstream client with roots, reporting what it is sent.
+    struct Events(mpsc::UnboundedSender<Event>);
+
+    impl ClientHandler for Events {
+        async fn list_roots(
+            &self,
+            _context: RequestContext<RoleClient>,
+        ) -> Result<ListRootsResult, ErrorData> {
+            let root = Root {
+                uri: "file:///work".into(),
+                name: None,
+            };
+            Ok(ListRootsResult { roots: vec![root] })
+        }
+
+        async fn on_progress(
+            &self,
+            params: ProgressNotificationParam,
+            _context: NotificationContext<RoleClient>,
+        ) {
+            let _ = self
+                .0
+                .send(Event::Progress(params.progress_token, params.message));
+        }
+
+        async fn on_logging_message(
+            &self,
+            params: LoggingMessageNotificationParam,
+            _context: NotificationContext<RoleClient>,
+        ) {
+            let _ = self.0.send(Event::Log(params.logger, params.data));
+        }
+
+        async fn on_resource_updated(
+            &self,
+            params: ResourceUpdatedNotificationParam,
+            _context: NotificationContext<RoleClient>,
+        ) {
+            let _ = self.0.send(Event::Updated(params.uri));
+        }
+
+        fn get_info(&self) -> ClientInfo {
+            ClientInfo {
+                capabilities: ClientCapabilities::builder().enable_roots().build(),
+                ..Default::default()
+            }
+        }
+    }
+
+    /// Run `server` in-process and connect to it over a duplex pipe.
+    async fn connect<S: ServerHandler>(server: S) -> RunningService<RoleClient, ()> {
+        connect_as((), server).await
+    }
+
+    async fn connect_as<C: ClientHandler, S: ServerHandler>(
+        client: C,
+        server: S,
+    ) -> RunningService<RoleClient, C> {
+        let (client_io, server_io) = tokio::io::duplex(4096);
+        tokio::spawn(async move {
+            let service = server.serve(server_io).await.unwrap();
+            service.waiting().await.unwrap();
+        });
+        client.serve(client_io).await.unwrap()
+    }
+
+    /// Run `server` in-process as the upstream `name`.
+    async fn upstream<S: ServerHandler>(name: &str, server: S) -> Upstream {
+        Upstream::from_service(name, connect_as(Relay::default(), server).await)
+    }
+
+    async fn gateway(prefixing: Prefixing) -> RunningService<RoleClient, ()> {
+        let gateway = Gateway::builder()
+            .attach(upstream("fs", Files::new()).await)
+            .attach(upstream("git", Git::new()).await)
+            .prefixing(prefixing)
+            .build()
+            .await
+            .unwrap();
+        connect(gateway).await
+    }
+
+    async fn tool_names(client: &Peer<RoleClient>) -> Vec<String> {
+        let mut names: Vec<_> = client
+            .list_all_tools()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|t| t.name.into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// The text of a tool result, as `Err` for tool errors.
+    async fn call(client: &Peer<RoleClient>, name: &str) -> Result<String, String> {
+        call_with(client, name, None).await
+    }
+
+    async fn call_with(
+        client: &Peer<RoleClient>,
+        name: &str,
+        arguments: Option<Value>,
+    ) -> Result<String, String> {
+        let result = client
+            .call_tool(CallToolRequestParams {
+                meta: None,
+                name: name.to_string().into(),
+                arguments: arguments.and_then(|a| a.as_object().cloned()),
+                task: None,
+            })
+            .await
+            .map_err(|err| err.to_string())?;
+        let text = result.content[0].as_text().unwrap().text.clone();
+        if result.is_error == Some(true) {
+            Err(text)
+        } else {
+            Ok(text)
+        }
+    }
+
+    #[tokio::test]
+    async fn prefixes_colliding_names_and_routes_calls() {
+        let client = gateway(Prefixing::OnCollision).await;
+        assert_eq!(
+            tool_names(&client).await,
+            ["fs.status", "git.status", "read_file"]
+        );
+        assert_eq!(call(&client, "git.status").await.unwrap(), "git:status");
+        assert_eq!(call(&client, "fs.status").await.unwrap(), "fs:status");
+        assert_eq!(call(&client, "read_file").await.unwrap(), "fs:read_file");
+        assert!(call(&client, "status").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn prefixing_modes() {
+        let client = gateway(Prefixing::Always).await;
+        assert_eq!(
+            tool_names(&client).await,
+            ["fs.read_file", "fs.status", "git.status"]
+        );
+
+        let client = gateway(Prefixing::Never).await;
+        assert_eq!(tool_names(&client).await, ["read_file", "status"]);
+        assert_eq!(call(&client, "status").await.unwrap(), "fs:status");
+    }
+
+    #[tokio::test]
+    async fn transforms_rename_tools() {
+        let gateway = Gateway::builder()
+            .attach(upstream("fs", Files::new()).await)
+            .attach(upstream("git", Git::new()).await)
+            .transform(
+                "git.status",
+                ToolTransform {
+                    name: Some("git_status".into()),
+                    description: Some("Status of the work tree".into()),
+                    ..Default::default()
+                },
+            )
+            .build()
+            .await
+            .unwrap();
+        let client = connect(gateway).await;
+        assert_eq!(
+            tool_names(&client).await,
+            ["fs.status", "git_status", "read_file"]
+        );
+        let tools = client.list_all_tools().await.unwrap();
+        let tool = tools.iter().find(|t| t.name == "git_status").unwrap();
+        assert_eq!(tool.description.as_deref(), Some("Status of the work tree"));
+        assert_eq!(call(&client, "git_status").await.unwrap(), "git:status");
+        assert!(call(&client, "git.status").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn composite_tools_chain_calls() {
+        let snapshot: CompositeTool = toml::from_str(
+            r#"
+            defaults = { pattern = "**" }
+
+            [[steps]]
+            id = "tree"
+            tool = "directory_tree"
+            arguments = { path = "{{ args.path }}" }
+
+            [[steps]]
+            tool = "read_multiple_files"
+            arguments = { paths = "{{ steps.tree | files(args.path) | glob(args.pattern) }}" }
+            "#,
+        )
+        .unwrap();
+        let gateway = Gateway::builder()
+            .attach(upstream("fs", Tree::new()).await)
+            .composite("snapshot_dir", snapshot)
+            .build()
+            .await
+            .unwrap();
+        let client = connect(gateway).await;
+        assert_eq!(
+            tool_names(&client).await,
+            ["directory_tree", "read_multiple_files", "snapshot_dir"]
+        );
+        let tools = client.list_all_tools().await.unwrap();
+        let tool = tools.iter().find(|t| t.name == "snapshot_dir").unwrap();
+        assert_eq!(
+            tool.input_schema["properties"]["path"],
+            json!({ "type": "string", "description": "Root directory." })
+        );
+        assert_eq!(tool.input_schema["required"], json!(["path"]));
+
+        assert_eq!(
+            call_with(&client, "snapshot_dir", Some(json!({ "path": "/srv" }))).await,
+            Ok("/srv/src/lib.rs,/srv/src/notes.md,/srv/build.rs".into())
+        );
+        let arguments = json!({ "path": "/srv", "pattern": "*.rs" });
+        assert_eq!(
+            call_with(&client, "snapshot_dir", Some(arguments)).await,
+            Ok("/srv/src/lib.rs,/srv/build.rs".into())
+        );
+        assert_eq!(
+            call(&client, "snapshot_dir").await,
+            Err("step `tree`: `args.path` is not set".into())
+        );
+    }
+
+    /// Serve `factory` over streamable HTTP on a free local port.
+    async fn serve_http<S: ServerHandler>(factory: fn() -> S) -> String {
+        serve_router(McpServerBuilder::new(factory).build()).await
+    }
+
+    async fn serve_router(router: axum::Router) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/mcp", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        url
+    }
+
+    /// Downstream client reporting `notifications/tools/list_changed`.
+    struct Changes(mpsc::UnboundedSender<()>);
+
+    impl ClientHandler for Changes {
+        async fn on_tool_list_changed(&self, _context: NotificationContext<RoleClient>) {
+            let _ = self.0.send(());
+        }
+    }
+
+    #[tokio::test]
+    async fn reload_swaps_upstreams_and_notifies_sessions() {
+        let fs = UpstreamConfig::http("fs", serve_http(Files::new).await);
+        let git = UpstreamConfig::http("git", serve_http(Git::new).await);
+        let gateway = Gateway::builder()
+            .upstream(fs.clone())
+            .build()
+            .await
+            .unwrap();
+
+        let (tx, mut changes) = mpsc::unbounded_channel();
+        let (client_io, server_io) = tokio::io::duplex(4096);
+        let server = gateway.clone();
+        tokio::spawn(async move {
+            let service = server.serve(server_io).await.unwrap();
+            service.waiting().await.unwrap();
+        });
+        let client = Changes(tx).serve(client_io).await.unwrap();
+        assert_eq!(tool_names(&client).await, ["read_file", "status"]);
+
+        gateway
+            .reload(GatewayConfig {
+                upstreams: vec![fs.clone(), git],
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        changes.recv().await.unwrap();
+        assert_eq!(
+            tool_names(&client).await,
+            ["fs.status", "git.status", "read_file"]
+        );
+        assert_eq!(call(&client, "git.status").await.unwrap(), "git:status");
+
+        // Drop `git` and filter `fs` through a watched config file.
+        let path = std::env::temp_dir().join(format!("rmcp-gateway-{}.json", std::process::id()));
+        let config = serde_json::json!({
+            "upstreams": [{ "name": "fs", "url": url(&fs), "tools": { "exclude": ["status"] } }],
+        });
+        let watcher = gateway.watch(&path, Duration::from_millis(10));
+        std::fs::write(&path, config.to_string()).unwrap();
+        changes.recv().await.unwrap();
+        assert_eq!(tool_names(&client).await, ["read_file"]);
+        assert!(call(&client, "fs.status").await.is_err());
+
+        watcher.abort();
+        std::fs::remove_file(path).unwrap();
+        let [upstream] = &gateway.upstreams()[..] else {
+            panic!("expected one upstream");
+        };
+        assert_eq!(
+            upstream.config().unwrap().tools,
+            ToolFilter {
+                include: Vec::new(),
+                exclude: vec![Glob::new("status").unwrap()],
+            }
+        );
+    }
+
+    fn url(config: &UpstreamConfig) -> &str {
+        match &config.transport {
+            Transport::Http { url, .. } => url,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Accepts `<sub>:<scope>,<scope>...` as the token.
+    #[derive(Clone)]
+    struct Tokens;
+
+    impl Validator for Tokens {
+        type Claims = OAuthClaims;
+        type Error = String;
+
+        async fn validate(&self, token: &str) -> Result<OAuthClaims, String> {
+            let (sub, scope) = token.split_once(':').ok_or("malformed")?;
+            let mut claims = OAuthClaims::default();
+            claims.sub = sub.into();
+            claims.scope = scope.split(',').map(String::from).collect();
+            Ok(claims)
+        }
+    }
+
+    #[tokio::test]
+    async fn enforces_policy_for_authenticated_callers() {
+        let policy: Policy = serde_json::from_value(serde_json::json!({
+            "rules": [
+                { "scopes": ["files:read"], "tools": ["read_*"] },
+                { "principals": ["admin"] },
+            ],
+        }))
+        .unwrap();
+        let gateway = Gateway::builder()
+            .attach(upstream("fs", Files::new()).await)
+            .policy(policy)
+            .claims::<OAuthClaims>()
+            .build()
+            .await
+            .unwrap();
+        let url = serve_router(
+            McpServerBuilder::new(move || gateway.clone())
+                .auth(AuthLayer::new(BearerAuth::new(Tokens)))
+                .build(),
+        )
+        .await;
+        let client = |token: &str| {
+            let config =
+                StreamableHttpClientTransportConfig::with_uri(url.as_str()).auth_header(token);
+            ().serve(StreamableHttpClientTransport::from_config(config))
+        };
+
+        let reader = client("alice:files:read").await.unwrap();
+        assert_eq!(tool_names(&reader).await, ["read_file"]);
+        assert_eq!(call(&reader, "read_file").await.unwrap(), "fs:read_file");
+        assert_eq!(
+            call(&reader, "status").await.unwrap_err(),
+            "not allowed to call tool `status`"
+        );
+
+        let admin = client("admin:").await.unwrap();
+        assert_eq!(tool_names(&admin).await, ["read_file", "status"]);
+        assert_eq!(call(&admin, "status").await.unwrap(), "fs:status");
+    }
+
+    fn health() -> HealthConfig {
+        HealthConfig {
+            failure_threshold: 1,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn leaves_out_dead_upstreams_and_fails_fast() {
+        let (client_io, server_io) = tokio::io::duplex(4096);
+        let (server, client) = tokio::join!(
+            Files::new().serve(server_io),
+            Relay::default().serve(client_io)
+        );
+        let gateway = Gateway::builder()
+            .attach(Upstream::from_service("fs", client.unwrap()))
+            .attach(upstream("git", Git::new()).await)
+            .health(health())
+            .build()
+            .await
+            .unwrap();
+        let downstream = connect(gateway.clone()).await;
+        assert_eq!(
+            tool_names(&downstream).await,
+            ["fs.status", "git.status", "read_file"]
+        );
+
+        server.unwrap().cancel().await.unwrap();
+        gateway.check_health(&mut HashMap::new()).await;
+        assert_eq!(tool_names(&downstream).await, ["status"]);
+        assert_eq!(call(&downstream, "status").await.unwrap(), "git:status");
+
+        let fs = gateway.upstreams()[0].clone();
+        assert!(!fs.is_healthy());
+        let request = CallToolRequestParams {
+            meta: None,
+            name: "read_file".into(),
+            arguments: None,
+            task: None,
+        };
+        let err = gateway
+            .forward(&fs, fs.peer().unwrap().call_tool(request))
+            .await
+            .unwrap_err();
+        assert_eq!(err.message, "upstream `fs` is unavailable");
+    }
+
+    #[tokio::test]
+    async fn restarts_unhealthy_configured_upstreams() {
+        let sessions = Arc::new(LocalSessionManager::default());
+        let url = serve_router(
+            McpServerBuilder::new(Files::new)
+                .session_manager(sessions.clone())
+                .build(),
+        )
+        .await;
+        let gateway = Gateway::builder()
+            .upstream(UpstreamConfig::http("fs", url))
+            .health(health())
+            .build()
+            .await
+            .unwrap();
+        let before = gateway.upstreams()[0].clone();
+
+        // The upstream forgets its sessions, as if it restarted.
+        let ids: Vec<_> = sessions.sessions.read().await.keys().cloned().collect();
+        for id in ids {
+            sessions.close_session(&id).await.unwrap();
+        }
+        let mut restarts = HashMap::new();
+        gateway.check_health(&mut restarts).await;
+
+        let after = gateway.upstreams()[0].clone();
+        assert!(!Arc::ptr_eq(&before, &after));
+        assert!(after.is_healthy());
+        let downstream = connect(gateway.clone()).await;
+        assert_eq!(
+            call(&downstream, "read_file").await.unwrap(),
+            "fs:read_file"
+        );
+
+        // Crashing again right after the restart waits out the backoff.
+        let ids: Vec<_> = sessions.sessions.read().await.keys().cloned().collect();
+        for id in ids {
+            sessions.close_session(&id).await.unwrap();
+        }
+        gateway.check_health(&mut restarts).await;
+        let again = gateway.upstreams()[0].clone();
+        assert!(Arc::ptr_eq(&after, &again));
+        assert!(!again.is_healthy());
+    }
+
+    #[tokio::test]
+    async fn forgets_closed_sessions() {
+        let gateway = Gateway::builder()
+            .attach(upstream("git", Git::new()).await)
+            .build()
+            .await
+            .unwrap();
+        let peers = || gateway.inner.peers.lock().unwrap().clone();
+
+        let first = connect(gateway.clone()).await;
+        tool_names(&first).await;
+        first.cancel().await.unwrap();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            // Until the session has registered and its transport closed.
+            while !peers()
+                .first()
+                .is_some_and(|peer| peer.is_transport_closed())
+            {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .unwrap();
+
+        let second = connect(gateway.clone()).await;
+        tool_names(&second).await;
+        assert_eq!(peers().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn starts_without_unreachable_upstreams() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        let gateway = Gateway::builder()
+            .upstream(UpstreamConfig::http("fs", format!("http://{addr}/mcp")))
+            .attach(upstream("git", Git::new()).await)
+            .health(health())
+            .build()
+            .await
+            .unwrap();
+        let downstream = connect(gateway.clone()).await;
+        let fs = gateway.upstreams()[0].clone();
+        assert!(!fs.is_healthy());
+        assert!(fs.peer().is_none());
+        assert_eq!(fs.capabilities(), ServerCapabilities::default());
+        assert_eq!(tool_names(&downstream).await, ["status"]);
+
+        let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
+        let router = McpServerBuilder::new(Files::new).build();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        gateway.check_health(&mut HashMap::new()).await;
+        assert!(gateway.upstreams()[0].is_healthy());
+        assert_eq!(
+            call(&downstream, "read_file").await.unwrap(),
+            "fs:read_file"
+        );
+    }
+
+    #[tokio::test]
+    async fn reload_adds_unreachable_upstreams_unavailable() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        let gateway = Gateway::builder()
+            .attach(upstream("git", Git::new()).await)
+            .build()
+            .await
+            .unwrap();
+        let downstream = connect(gateway.clone()).await;
+        gateway
+            .reload(GatewayConfig {
+                upstreams: vec![UpstreamConfig::http("fs", format!("http://{addr}/mcp"))],
+                health: health(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let names: Vec<_> = gateway
+            .upstreams()
+            .iter()
+            .map(|u| u.name().to_string())
+            .collect();
+        assert_eq!(names, ["fs", "git"]);
+        assert!(!gateway.upstreams()[0].is_healthy());
+        assert_eq!(tool_names(&downstream).await, ["status"]);
+
+        let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
+        let router = McpServerBuilder::new(Files::new).build();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        gateway.check_health(&mut HashMap::new()).await;
+        assert!(gateway.upstreams()[0].is_healthy());
+        assert_eq!(
+            call(&downstream, "read_file").await.unwrap(),
+            "fs:read_file"
+        );
+    }
+
+    #[tokio::test]
+    async fn gives_up_on_upstreams_that_never_initialize() {
+        let silent = UpstreamConfig::stdio("silent", "sleep", ["1000"])
+            .connect_timeout(Duration::from_millis(100));
+        let start = async {
+            Gateway::builder()
+                .upstream(silent)
+                .attach(upstream("git", Git::new()).await)
+                .health(health())
+                .build()
+                .await
+                .unwrap()
+        };
+        let gateway = tokio::time::timeout(Duration::from_secs(5), start)
+            .await
+            .expect("startup waits for a silent upstream");
+        let downstream = connect(gateway.clone()).await;
+        assert!(!gateway.upstreams()[0].is_healthy());
+        assert_eq!(tool_names(&downstream).await, ["status"]);
+
+        tokio::time::timeout(
+            Duration::from_secs(5),
+            gateway.check_health(&mut HashMap::new()),
+        )
+        .await
+        .expect("health checks wait for a silent upstream");
+        assert!(!gateway.upstreams()[0].is_healthy());
+    }
+
+    #[tokio::test]
+    async fn caches_read_only_tools() {
+        let gateway = Gateway::builder()
+            .attach(upstream("clock", Clock::new()).await)
+            .cache(CacheConfig::default())
+            .build()
+            .await
+            .unwrap();
+        let client = connect(gateway.clone()).await;
+
+        assert_eq!(call(&client, "now").await.unwrap(), "0");
+        assert_eq!(call(&client, "tick").await.unwrap(), "1");
+        assert_eq!(call(&client, "now").await.unwrap(), "0");
+        assert_eq!(call(&client, "tick").await.unwrap(), "2");
+        assert_eq!(
+            gateway.cache_stats(),
+            CacheStats {
+                hits: 1,
+                misses: 1,
+                evictions: 0,
+                entries: 1,
+            }
+        );
+
+        // Changing the cache configuration drops cached results.
+        gateway
+            .reload(GatewayConfig {
+                cache: Some(CacheConfig {
+                    read_only_ttl: Duration::ZERO,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(call(&client, "now").await.unwrap(), "3");
+        assert_eq!(gateway.cache_stats().entries, 0);
+    }
+
+    #[tokio::test]
+    async fn session_strategies() {
+        let url = serve_http(Clock::new).await;
+        let gateway = |sessions| {
+            Gateway::builder()
+                .upstream(UpstreamConfig::http("clock", url.as_str()).sessions(sessions))
+                .claims::<OAuthClaims>()
+                .build()
+        };
+
+        // Every upstream connection has its own clock.
+        let shared = gateway(SessionStrategy::Shared).await.unwrap();
+        let (a, b) = (connect(shared.clone()).await, connect(shared).await);
+        assert_eq!(call(&a, "tick").await.unwrap(), "0");
+        assert_eq!(call(&b, "tick").await.unwrap(), "1");
+
+        let per_session = gateway(SessionStrategy::PerSession).await.unwrap();
+        let (a, b) = (
+            connect(per_session.clone()).await,
+            connect(per_session).await,
+        );
+        assert_eq!(call(&a, "tick").await.unwrap(), "0");
+        assert_eq!(call(&a, "tick").await.unwrap(), "1");
+        assert_eq!(call(&b, "tick").await.unwrap(), "0");
+
+        let per_principal = gateway(SessionStrategy::PerPrincipal).await.unwrap();
+        let url = serve_router(
+            McpServerBuilder::new(move || per_principal.clone())
+                .auth(AuthLayer::new(BearerAuth::new(Tokens)))
+                .build(),
+        )
+        .await;
+        let client = |token: &str| {
+            let config =
+                StreamableHttpClientTransportConfig::with_uri(url.as_str()).auth_header(token);
+            ().serve(StreamableHttpClientTransport::from_config(config))
+        };
+        let alice = client("alice:").await.unwrap();
+        let alice_again = client("alice:").await.unwrap();
+        let bob = client("bob:").await.unwrap();
+        // Concurrent first calls share the one connection being opened.
+        let (first, second) = tokio::join!(call(&alice, "tick"), call(&alice_again, "tick"));
+        let mut ticks = [first.unwrap(), second.unwrap()];
+        ticks.sort();
+        assert_eq!(ticks, ["0", "1"]);
+        assert_eq!(call(&bob, "tick").await.unwrap(), "0");
+    }
+
+    #[tokio::test]
+    async fn limits_dedicated_connections() {
+        let url = serve_http(Clock::new).await;
+        let gateway = Gateway::builder()
+            .upstream(
+                UpstreamConfig::http("clock", url)
+                    .sessions(SessionStrategy::PerSession)
+                    .max_connections(1, Duration::from_millis(50)),
+            )
+            .build()
+            .await
+            .unwrap();
+        let first = connect(gateway.clone()).await;
+        let second = connect(gateway).await;
+
+        assert_eq!(call(&first, "tick").await.unwrap(), "0");
+        let err = call(&second, "tick").await.unwrap_err();
+        assert!(err.contains("upstream `clock` is busy"), "{err}");
+
+        // Closing the first session frees its connection.
+        first.cancel().await.unwrap();
+        let tick = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                if let Ok(tick) = call(&second, "tick").await {
+                    return tick;
+                }
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(tick, "0");
+    }
+
+    #[tokio::test]
+    async fn queues_calls_beyond_max_concurrency() {
+        let url = serve_http(Clock::new).await;
+        let gateway = Gateway::builder()
+            .upstream(
+                UpstreamConfig::http("clock", url).max_concurrency(1, Duration::from_millis(50)),
+            )
+            .build()
+            .await
+            .unwrap();
+        let client = connect(gateway.clone()).await;
+
+        let upstream = gateway.upstreams()[0].clone();
+        let (taken, slot_taken) = tokio::sync::oneshot::channel();
+        let holder = tokio::spawn({
+            let gateway = gateway.clone();
+            async move {
+                let request = async {
+                    let _ = taken.send(());
+                    std::future::pending::<Result<(), ServiceError>>().await
+                };
+                gateway.forward(&upstream, request).await
+            }
+        });
+        slot_taken.await.unwrap();
+        let err = call(&client, "tick").await.unwrap_err();
+        assert!(err.contains("upstream `clock` is busy"), "{err}");
+
+        holder.abort();
+        let _ = holder.await;
+        assert_eq!(call(&client, "tick").await.unwrap(), "0");
+    }
+
+    #[tokio::test]
+    async fn times_out_hung_upstreams() {
+        let url = serve_http(|| Worker::new().0).await;
+        let work = UpstreamConfig::http("work", url).request_timeout(Duration::from_millis(50));
+        let gateway = Gateway::builder()
+            .upstream(work)
+            .health(health())
+            .build()
+            .await
+            .unwrap();
+        let client = connect(gateway.clone()).await;
+
+        let err = call(&client, "work").await.unwrap_err();
+        assert!(err.contains("request timeout"), "{err}");
+        // The timeout counted as a failure and opened the breaker.
+        assert!(!gateway.upstreams()[0].is_healthy());
+    }
+
+    #[tokio::test]
+    async fn relays_progress_logs_roots_and_cancellation() {
+        let (worker, mut cancellations, _) = Worker::new();
+        let gateway = Gateway::builder()
+            .attach(upstream("work", worker).await)
+            .build()
+            .await
+            .unwrap();
+        let (tx, mut events) = mpsc::unbounded_channel();
+        let client = connect_as(Events(tx), gateway).await;
+
+        let request = ClientRequest::CallToolRequest(CallToolRequest::new(CallToolRequestParams {
+            meta: None,
+            name: "work".into(),
+            arguments: None,
+            task: None,
+        }));
+        let handle = client
+            .send_cancellable_request(request, PeerRequestOptions::no_options())
+            .await
+            .unwrap();
+        let received = [events.recv().await.unwrap(), events.recv().await.unwrap()];
+        // Progress arrives under the client's own token.
+        let progress = Event::Progress(handle.progress_token.clone(), Some("started".into()));
+        assert!(received.contains(&progress), "{received:?}");
+        let log = Event::Log(Some("work".into()), json!("working"));
+        assert!(received.contains(&log), "{received:?}");
+
+        handle.cancel(None).await.unwrap();
+        cancellations.recv().await.unwrap();
+
+        assert_eq!(call(&client, "roots").await.unwrap(), "file:///work");
+    }
+
+    #[tokio::test]
+    async fn relays_resource_updates_to_subscribers() {
+        let (worker, _, mut subscriptions) = Worker::new();
+        let gateway = Gateway::builder()
+            .attach(upstream("work", worker).await)
+            .build()
+            .await
+            .unwrap();
+        let (tx, mut events) = mpsc::unbounded_channel();
+        let subscriber = connect_as(Events(tx), gateway.clone()).await;
+        let (tx, mut other_events) = mpsc::unbounded_channel();
+        let other = connect_as(Events(tx), gateway).await;
+        assert_eq!(call(&other, "roots").await.unwrap(), "file:///work");
+
+        let uri = "work://status".to_string();
+        let params = |uri: &String| SubscribeRequestParams {
+            meta: None,
+            uri: uri.clone(),
+        };
+        subscriber.subscribe(params(&uri)).await.unwrap();
+        let upstream = subscriptions.recv().await.unwrap();
+        // Only the first subscription reaches the upstream.
+        subscriber.subscribe(params(&uri)).await.unwrap();
+        assert!(subscriptions.try_recv().is_err());
+
+        let update = ResourceUpdatedNotificationParam { uri: uri.clone() };
+        upstream.notify_resource_updated(update).await.unwrap();
+        assert_eq!(events.recv().await.unwrap(), Event::Updated(uri));
+        assert!(other_events.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn logs_go_to_the_only_waiting_session() {
+        let (worker, mut cancellations, _) = Worker::new();
+        let gateway = Gateway::builder()
+            .attach(upstream("work", worker).await)
+            .build()
+            .await
+            .unwrap();
+        let (tx, mut first_events) = mpsc::unbounded_channel();
+        let first = connect_as(Events(tx), gateway.clone()).await;
+        let (tx, mut second_events) = mpsc::unbounded_channel();
+        let second = connect_as(Events(tx), gateway).await;
+        let work = || {
+            ClientRequest::CallToolRequest(CallToolRequest::new(CallToolRequestParams {
+                meta: None,
+                name: "work".into(),
+                arguments: None,
+                task: None,
+            }))
+        };
+
+        let handle = first
+            .send_cancellable_request(work(), PeerRequestOptions::no_options())
+            .await
+            .unwrap();
+        let mut received = vec![first_events.recv().await.unwrap()];
+        received.push(first_events.recv().await.unwrap());
+        let log = Event::Log(Some("work".into()), json!("working"));
+        assert!(received.contains(&log), "{received:?}");
+
+        // With both sessions waiting, the log could be either's.
+        let other = second
+            .send_cancellable_request(work(), PeerRequestOptions::no_options())
+            .await
+            .unwrap();
+        assert!(matches!(
+            second_events.recv().await.unwrap(),
+            Event::Progress(..)
+        ));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(second_events.try_recv().is_err());
+        assert!(first_events.try_recv().is_err());
+
+        handle.cancel(None).await.unwrap();
+        other.cancel(None).await.unwrap();
+        cancellations.recv().await.unwrap();
+        cancellations.recv().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn upstream_requests_go_to_the_only_waiting_session() {
+        let (worker, mut cancellations, _) = Worker::new();
+        let gateway = Gateway::builder()
+            .attach(upstream("work", worker).await)
+            .build()
+            .await
+            .unwrap();
+        let (tx, mut events) = mpsc::unbounded_channel();
+        let busy = connect_as(Events(tx), gateway.clone()).await;
+        let (tx, _other_events) = mpsc::unbounded_channel();
+        let other = connect_as(Events(tx), gateway).await;
+
+        let request = ClientRequest::CallToolRequest(CallToolRequest::new(CallToolRequestParams {
+            meta: None,
+            name: "work".into(),
+            arguments: None,
+            task: None,
+        }));
+        let handle = busy
+            .send_cancellable_request(request, PeerRequestOptions::no_options())
+            .await
+            .unwrap();
+        events.recv().await.unwrap();
+        // Either session's roots could be meant while both wait.
+        let roots = call(&other, "roots").await.unwrap();
+        assert!(roots.contains("several downstream sessions"), "{roots}");
+
+        handle.cancel(None).await.unwrap();
+        cancellations.recv().await.unwrap();
+        assert_eq!(call(&other, "roots").await.unwrap(), "file:///work");
+    }
+}
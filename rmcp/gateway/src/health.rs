@@ -0,0 +1,225 @@
+//! Upstream health: circuit breaking, ping checks and restarts.
+//!
+//! Every upstream has a circuit breaker that opens after
+//! `failure_threshold` consecutive failed calls or pings. While it is open,
+//! calls to the upstream fail fast and its tools, prompts and resources are
+//! left out of the merged lists. The gateway pings every upstream each
+//! `interval`, reconnects unhealthy upstreams it has a configuration for
+//! with exponential backoff, and closes the breaker once the upstream
+//! answers again. The backoff also spaces out restarts of an upstream that
+//! keeps crashing, until it stays up for `max_backoff`.
+//!
+//! ```toml
+//! [health]
+//! interval = 10          # seconds between pings
+//! timeout = 5            # seconds to wait for a ping
+//! failure_threshold = 3
+//! cooldown = 30          # seconds an open breaker fails fast
+//! initial_backoff = 1    # first restart delay, doubled per failed restart
+//! max_backoff = 60
+//! ```
+
+use serde::{Deserialize, Deserializer, de::Error as _};
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HealthConfig {
+    /// How often to ping upstreams. Defaults to 10 seconds.
+    #[serde(deserialize_with = "secs")]
+    pub interval: Duration,
+    /// How long to wait for a ping. Defaults to 5 seconds.
+    #[serde(deserialize_with = "secs")]
+    pub timeout: Duration,
+    /// Consecutive failures that open the breaker. Defaults to 3.
+    pub failure_threshold: u32,
+    /// How long an open breaker fails calls fast before letting one
+    /// through. Defaults to 30 seconds.
+    #[serde(deserialize_with = "secs")]
+    pub cooldown: Duration,
+    /// Delay before the second restart attempt, doubled after each failed
+    /// attempt. Defaults to 1 second.
+    #[serde(deserialize_with = "secs")]
+    pub initial_backoff: Duration,
+    /// Longest delay between restart attempts, and how long a restarted
+    /// upstream must stay healthy for the delay to reset. Defaults to 60
+    /// seconds.
+    #[serde(deserialize_with = "secs")]
+    pub max_backoff: Duration,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(10),
+            timeout: Duration::from_secs(5),
+            failure_threshold: 3,
+            cooldown: Duration::from_secs(30),
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Durations are configured in (fractional) seconds.
+pub(crate) fn secs<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    Duration::try_from_secs_f64(f64::deserialize(deserializer)?).map_err(D::Error::custom)
+}
+
+/// Consecutive-failure circuit breaker of an upstream.
+#[derive(Debug, Default)]
+pub(crate) struct CircuitBreaker {
+    state: Mutex<BreakerState>,
+}
+
+#[derive(Debug, Default)]
+struct BreakerState {
+    failures: u32,
+    /// Set while open; calls fail fast until then.
+    open_until: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    /// Whether a call may go through: the breaker is closed, or open past
+    /// its cooldown.
+    pub fn allows(&self) -> bool {
+        self.state().open_until.is_none_or(|t| Instant::now() >= t)
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.state().open_until.is_some()
+    }
+
+    /// Record a success. Returns whether this closed the breaker.
+    pub fn success(&self) -> bool {
+        let mut state = self.state();
+        state.failures = 0;
+        state.open_until.take().is_some()
+    }
+
+    /// Record a failure. Returns whether this opened the breaker.
+    pub fn failure(&self, config: &HealthConfig) -> bool {
+        let mut state = self.state();
+        state.failures += 1;
+        if state.failures < config.failure_threshold {
+            return false;
+        }
+        let opened = state.open_until.is_none();
+        state.open_until = Some(Instant::now() + config.cooldown);
+        opened
+    }
+
+    /// Open immediately, e.g. because the connection is gone. Returns
+    /// whether this opened the breaker.
+    pub fn trip(&self, config: &HealthConfig) -> bool {
+        let mut state = self.state();
+        state.failures = state.failures.max(config.failure_threshold);
+        let opened = state.open_until.is_none();
+        state.open_until = Some(Instant::now() + config.cooldown);
+        opened
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, BreakerState> {
+        self.state.lock().expect("circuit breaker poisoned")
+    }
+}
+
+/// Restart schedule of an unhealthy upstream.
+#[derive(Debug)]
+pub(crate) struct Backoff {
+    pub next: Instant,
+    delay: Duration,
+    /// When the last restart was attempted.
+    attempted: Instant,
+}
+
+impl Backoff {
+    /// Try immediately, then after `initial_backoff`.
+    pub fn new(config: &HealthConfig) -> Self {
+        let now = Instant::now();
+        Self {
+            next: now,
+            delay: config.initial_backoff,
+            attempted: now,
+        }
+    }
+
+    /// Schedule the next attempt after one, successful or not, so an
+    /// upstream that crashes again right away is restarted later each time.
+    pub fn attempted(&mut self, config: &HealthConfig) {
+        self.attempted = Instant::now();
+        self.next = self.attempted + self.delay;
+        self.delay = (self.delay * 2).min(config.max_backoff);
+    }
+
+    /// Whether the last restart was `max_backoff` ago, so a healthy
+    /// upstream can start over with `initial_backoff`.
+    pub fn settled(&self, config: &HealthConfig) -> bool {
+        self.attempted.elapsed() >= config.max_backoff
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Backoff, CircuitBreaker, HealthConfig};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn breaker_opens_after_threshold_and_closes_on_success() {
+        let config = HealthConfig {
+            failure_threshold: 2,
+            ..Default::default()
+        };
+        let breaker = CircuitBreaker::default();
+        assert!(!breaker.failure(&config));
+        assert!(breaker.allows());
+        assert!(breaker.failure(&config));
+        assert!(breaker.is_open() && !breaker.allows());
+        assert!(!breaker.failure(&config), "already open");
+        assert!(breaker.success());
+        assert!(!breaker.is_open() && breaker.allows());
+
+        let config = HealthConfig {
+            cooldown: Duration::ZERO,
+            ..config
+        };
+        assert!(breaker.trip(&config));
+        assert!(breaker.is_open() && breaker.allows(), "half-open");
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_max() {
+        let config = HealthConfig {
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(3),
+            ..Default::default()
+        };
+        let mut backoff = Backoff::new(&config);
+        assert!(backoff.next <= Instant::now());
+        let delays: Vec<_> = (0..4)
+            .map(|_| {
+                backoff.attempted(&config);
+                backoff.delay
+            })
+            .collect();
+        assert_eq!(delays, [2, 3, 3, 3].map(Duration::from_secs));
+        assert!(!backoff.settled(&config));
+
+        let config = HealthConfig {
+            max_backoff: Duration::ZERO,
+            ..config
+        };
+        assert!(backoff.settled(&config));
+    }
+
+    #[test]
+    fn parses_seconds() {
+        let config: HealthConfig = toml::from_str("interval = 0.5\nfailure_threshold = 1").unwrap();
+        assert_eq!(config.interval, Duration::from_millis(500));
+        assert_eq!(config.failure_threshold, 1);
+        assert_eq!(config.timeout, Duration::from_secs(5));
+    }
+}
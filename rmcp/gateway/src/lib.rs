@@ -1,14 +1,40 @@
-pub fn add(left: u64, right: u64) -> u64 {
-    left + right
-}
+//! rmcp-gateway: Aggregate several MCP servers behind one.
+//!
+//! A [`Gateway`] connects to upstream servers, stdio children or remote
+//! streamable HTTP servers, and serves their tools, prompts and resources
+//! as a single merged MCP server. Colliding names are disambiguated with
+//! the upstream prefix (`fs.read_file`, see [`Prefixing`]), and requests
+//! are routed to the upstream that owns them.
+//!
+//! Gateways can be driven by a TOML or JSON [`config::GatewayConfig`], and
+//! reloaded from it without dropping downstream sessions. A
+//! [`policy::Policy`] restricts which authenticated callers may call which
+//! tools, with which arguments. Unhealthy upstreams are cut off by a
+//! circuit breaker and restarted, see [`health`]. Results of read-only
+//! tools can be [`cache`]d, and tools can be renamed and their parameters
+//! pinned or hidden with a [`transform`]. New tools can be
+//! [`composite`]s of upstream tools, called in turn. Progress, cancellation, log
+//! messages, resource updates and requests from upstreams are [`relay`]ed
+//! to the right downstream session.
+//!
+//! A gateway over a single server [`bridge`]s it between transports: the
+//! `rmcp-gateway bridge` command serves a stdio server over streamable
+//! HTTP, or a remote server over stdio.
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+pub mod bridge;
+pub mod cache;
+mod catalog;
+pub mod cmd;
+pub mod composite;
+pub mod config;
+pub mod error;
+mod gateway;
+pub mod health;
+pub mod policy;
+pub mod relay;
+pub mod transform;
+pub mod upstream;
 
-    #[test]
-    fn it_works() {
-        let result = add(2, 2);
-        assert_eq!(result, 4);
-    }
-}
+pub use catalog::Prefixing;
+pub use config::GatewayConfig;
+pub use gateway::{Gateway, GatewayBuilder};
@@ -0,0 +1,299 @@
+//! Which callers may call which tools, and with what arguments.
+//!
+//! Callers are identified by the claims rmcp-axum's
+//! [`AuthLayer`](rmcp_axum::auth::AuthLayer) attaches to each request, see
+//! [`GatewayBuilder::claims`](crate::GatewayBuilder::claims). Without rules
+//! every call is allowed; with rules, a call is allowed only if some rule
+//! matches the caller, the tool and every constrained argument.
+//!
+//! Tool globs match exposed names. The `fs.` prefixes below assume
+//! `prefixing = "always"`; with the default `on_collision`, tools are only
+//! prefixed when their names collide.
+//!
+//! ```toml
+//! prefixing = "always"
+//!
+//! # Anyone with the `files:read` scope may read below /srv/shared.
+//! [[policy.rules]]
+//! scopes = ["files:read"]
+//! tools = ["fs.read_*", "fs.list_directory"]
+//! arguments = { path = { glob = "/srv/shared/**" } }
+//!
+//! # Agents may ask for the time in a few zones.
+//! [[policy.rules]]
+//! principals = ["agent-*"]
+//! tools = ["get_current_time"]
+//! arguments = { timezone = { enum = ["UTC", "Europe/Berlin"] } }
+//! ```
+
+use rmcp::model::JsonObject;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Policy {
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+}
+
+/// Allows matching callers to call matching tools.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    /// Globs on the caller's principal. Empty matches any caller.
+    #[serde(default)]
+    pub principals: Vec<Glob>,
+    /// Scopes the caller must all have been granted.
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    /// Globs on exposed tool names, e.g. `fs.read_*`. Empty matches any tool.
+    #[serde(default)]
+    pub tools: Vec<Glob>,
+    /// Constraints on argument values, by argument name.
+    ///
+    /// A constrained argument must be present. Array arguments must have
+    /// every element satisfy the constraint.
+    #[serde(default)]
+    pub arguments: HashMap<String, Constraint>,
+}
+
+/// A constraint on an argument value.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Constraint {
+    /// A string matching the glob, e.g. `/srv/shared/**`. Values with `..`
+    /// path segments never match.
+    Glob(Glob),
+    /// One of the listed values.
+    Enum(Vec<Value>),
+}
+
+/// A glob pattern, validated when the policy is loaded.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Glob(glob::Pattern);
+
+impl Glob {
+    pub fn new(pattern: &str) -> Result<Self, glob::PatternError> {
+        glob::Pattern::new(pattern).map(Self)
+    }
+
+    pub fn matches(&self, value: &str) -> bool {
+        self.0.matches(value)
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl TryFrom<String> for Glob {
+    type Error = glob::PatternError;
+
+    fn try_from(pattern: String) -> Result<Self, Self::Error> {
+        Self::new(&pattern)
+    }
+}
+
+/// The authenticated caller of a request.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Caller {
+    /// `None` for unauthenticated callers.
+    pub principal: Option<String>,
+    pub scopes: Vec<String>,
+}
+
+impl Policy {
+    /// Whether `caller` may see `tool` at all, ignoring arguments.
+    pub fn allows_tool(&self, caller: &Caller, tool: &str) -> bool {
+        self.rules.is_empty() || self.rules.iter().any(|r| r.matches(caller, tool))
+    }
+
+    /// Check a call, returning why it was denied.
+    pub fn check(
+        &self,
+        caller: &Caller,
+        tool: &str,
+        arguments: Option<&JsonObject>,
+    ) -> Result<(), String> {
+        if self.rules.is_empty() {
+            return Ok(());
+        }
+        let mut rules = self.rules.iter().filter(|r| r.matches(caller, tool));
+        let Some(first) = rules.next() else {
+            return Err(format!("not allowed to call tool `{tool}`"));
+        };
+        let denial = match first.check_arguments(arguments) {
+            Ok(()) => return Ok(()),
+            Err(denial) => denial,
+        };
+        if rules.any(|r| r.check_arguments(arguments).is_ok()) {
+            Ok(())
+        } else {
+            Err(denial)
+        }
+    }
+}
+
+impl Rule {
+    fn matches(&self, caller: &Caller, tool: &str) -> bool {
+        let principal = self.principals.is_empty()
+            || caller
+                .principal
+                .as_deref()
+                .is_some_and(|p| self.principals.iter().any(|g| g.matches(p)));
+        let scopes = self.scopes.iter().all(|s| caller.scopes.contains(s));
+        let tool = self.tools.is_empty() || self.tools.iter().any(|g| g.matches(tool));
+        principal && scopes && tool
+    }
+
+    fn check_arguments(&self, arguments: Option<&JsonObject>) -> Result<(), String> {
+        for (name, constraint) in &self.arguments {
+            let value = arguments.and_then(|args| args.get(name));
+            let allowed = match value {
+                Some(Value::Array(values)) => values.iter().all(|v| constraint.allows(v)),
+                Some(value) => constraint.allows(value),
+                None => false,
+            };
+            if !allowed {
+                return Err(format!("argument `{name}` is not allowed"));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Constraint {
+    fn allows(&self, value: &Value) -> bool {
+        match (self, value) {
+            (Constraint::Glob(glob), Value::String(s)) => {
+                !s.split(['/', '\\']).any(|segment| segment == "..") && glob.matches(s)
+            }
+            (Constraint::Glob(_), _) => false,
+            (Constraint::Enum(values), value) => values.contains(value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Caller, Policy};
+    use serde_json::json;
+
+    fn policy() -> Policy {
+        serde_json::from_value(json!({
+            "rules": [
+                {
+                    "scopes": ["files:read"],
+                    "tools": ["fs.read_*"],
+                    "arguments": { "path": { "glob": "/srv/shared/**" } },
+                },
+                {
+                    "principals": ["agent-*"],
+                    "tools": ["get_current_time"],
+                    "arguments": { "timezone": { "enum": ["UTC", "Europe/Berlin"] } },
+                },
+            ],
+        }))
+        .unwrap()
+    }
+
+    fn caller(principal: &str, scopes: &[&str]) -> Caller {
+        Caller {
+            principal: Some(principal.into()),
+            scopes: scopes.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    fn args(value: serde_json::Value) -> serde_json::Map<String, serde_json::Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn matches_callers_and_tools() {
+        let policy = policy();
+        let reader = caller("alice", &["files:read"]);
+        assert!(policy.allows_tool(&reader, "fs.read_file"));
+        assert!(!policy.allows_tool(&reader, "fs.write_file"));
+        assert!(!policy.allows_tool(&reader, "get_current_time"));
+        assert!(policy.allows_tool(&caller("agent-7", &[]), "get_current_time"));
+        assert!(!policy.allows_tool(&Caller::default(), "get_current_time"));
+        assert!(Policy::default().allows_tool(&Caller::default(), "anything"));
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        let misspelled = [
+            json!({ "rules": [{ "principal": ["admin"] }] }),
+            json!({ "rules": [{ "scope": ["files:write"] }] }),
+            json!({ "rule": [] }),
+        ];
+        for policy in misspelled {
+            assert!(serde_json::from_value::<Policy>(policy).is_err());
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_globs() {
+        let invalid = [
+            json!({ "rules": [{ "tools": ["fs.[read"] }] }),
+            json!({ "rules": [{ "principals": ["***"] }] }),
+            json!({ "rules": [{ "arguments": { "path": { "glob": "/srv/[" } } }] }),
+        ];
+        for policy in invalid {
+            assert!(serde_json::from_value::<Policy>(policy).is_err());
+        }
+    }
+
+    #[test]
+    fn constrains_arguments() {
+        let policy = policy();
+        let reader = caller("alice", &["files:read"]);
+        let check = |tool, value| policy.check(&reader, tool, Some(&args(value)));
+
+        assert!(check("fs.read_file", json!({ "path": "/srv/shared/a/b.txt" })).is_ok());
+        assert!(check("fs.read_file", json!({ "path": "/etc/passwd" })).is_err());
+        assert!(
+            check(
+                "fs.read_file",
+                json!({ "path": "/srv/shared/../../etc/passwd" })
+            )
+            .is_err()
+        );
+        assert!(check("fs.read_file", json!({})).is_err());
+        assert!(
+            check(
+                "fs.read_files",
+                json!({ "path": ["/srv/shared/a", "/srv/shared/b"] })
+            )
+            .is_ok()
+        );
+        assert!(
+            check(
+                "fs.read_files",
+                json!({ "path": ["/srv/shared/a", "/tmp/b"] })
+            )
+            .is_err()
+        );
+        assert_eq!(
+            check("fs.write_file", json!({ "path": "/srv/shared/a" })),
+            Err("not allowed to call tool `fs.write_file`".into())
+        );
+
+        let agent = caller("agent-7", &[]);
+        let time = |tz: &str| {
+            policy.check(
+                &agent,
+                "get_current_time",
+                Some(&args(json!({ "timezone": tz }))),
+            )
+        };
+        assert!(time("Europe/Berlin").is_ok());
+        assert_eq!(
+            time("Asia/Tokyo"),
+            Err("argument `timezone` is not allowed".into())
+        );
+    }
+}
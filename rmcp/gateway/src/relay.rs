@@ -0,0 +1,480 @@
+//! Relaying between downstream sessions and upstream connections.
+//!
+//! Requests forwarded upstream get a progress token of the gateway's own,
+//! so tokens of sessions sharing a connection can't collide, and progress
+//! notifications are mapped back to the session's token. Request ids are
+//! assigned by each connection, and cancelling a downstream request
+//! cancels the upstream request it was forwarded as.
+//!
+//! What an upstream sends on its own is routed to the sessions that sent
+//! requests over the connection:
+//!
+//! - `notifications/resources/updated` goes to those subscribed to the
+//!   resource;
+//! - log messages, sampling, elicitation and roots requests go to the
+//!   session waiting for an answer from the upstream, if it is the only
+//!   one. With several sessions waiting, they could belong to any of their
+//!   calls, so log messages are dropped and requests fail rather than show
+//!   one session's data to another. Log messages also need the level the
+//!   session set with `logging/setLevel`.
+//!
+//! Sessions sharing a connection therefore miss log messages and can't
+//! serve upstream requests while other sessions' calls are in flight. Use
+//! per-session or per-principal connections where that matters, see
+//! [`SessionStrategy`](crate::upstream::SessionStrategy).
+
+use rmcp::{
+    ClientHandler, ErrorData, RoleClient, RoleServer, ServiceError,
+    model::{
+        CancelledNotification, CancelledNotificationParam, ClientCapabilities, ClientInfo,
+        ClientRequest, ClientResult, CreateElicitationRequest, CreateElicitationRequestParams,
+        CreateElicitationResult, CreateMessageRequest, CreateMessageRequestParams,
+        CreateMessageResult, ElicitationCapability, ErrorCode, Implementation, ListRootsRequest,
+        ListRootsResult, LoggingLevel, LoggingMessageNotificationParam, Meta, NumberOrString,
+        ProgressNotificationParam, ProgressToken, ResourceUpdatedNotificationParam,
+        RootsCapabilities, SamplingCapability, ServerRequest, ServerResult,
+    },
+    service::{
+        NotificationContext, Peer, PeerRequestOptions, RequestContext, RequestHandle, ServiceRole,
+    },
+};
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, Mutex, OnceLock, Weak,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+use tokio_util::sync::CancellationToken;
+
+/// Client handler of upstream connections, relaying what the upstream
+/// sends on its own to downstream sessions.
+///
+/// The gateway uses one for the connections it makes. Serve custom
+/// transports with one before attaching them with
+/// [`Upstream::from_service`](crate::upstream::Upstream::from_service).
+#[derive(Clone, Default)]
+pub struct Relay {
+    routes: Arc<Routes>,
+}
+
+impl Relay {
+    pub(crate) fn routes(&self) -> &Arc<Routes> {
+        &self.routes
+    }
+}
+
+/// A downstream session, as seen from the upstreams it uses.
+pub(crate) struct Downstream {
+    peer: Peer<RoleServer>,
+    /// Least severe log level to forward, if set.
+    level: Mutex<Option<LoggingLevel>>,
+}
+
+impl Downstream {
+    pub fn new(peer: Peer<RoleServer>) -> Self {
+        Self {
+            peer,
+            level: Mutex::new(None),
+        }
+    }
+
+    pub fn set_level(&self, level: LoggingLevel) {
+        *self.level.lock().expect("session level poisoned") = Some(level);
+    }
+
+    fn logs(&self, level: LoggingLevel) -> bool {
+        self.level
+            .lock()
+            .expect("session level poisoned")
+            .is_none_or(|min| level as u8 >= min as u8)
+    }
+
+    fn supports(&self, capable: fn(&ClientCapabilities) -> bool) -> bool {
+        self.peer
+            .peer_info()
+            .is_some_and(|info| capable(&info.capabilities))
+    }
+}
+
+/// Which downstream sessions use the connections of one upstream.
+#[derive(Default)]
+pub(crate) struct Routes {
+    /// Name of the upstream, the logger of log messages that name none.
+    upstream: OnceLock<String>,
+    next_token: AtomicU64,
+    state: Mutex<RouteState>,
+}
+
+#[derive(Default)]
+struct RouteState {
+    /// Sessions that sent requests, least recently active first.
+    sessions: Vec<Active>,
+    /// Downstream progress tokens, by the token sent upstream.
+    progress: HashMap<ProgressToken, (Peer<RoleServer>, ProgressToken)>,
+    /// Subscribed sessions, by resource URI.
+    subscriptions: HashMap<String, Vec<Weak<Downstream>>>,
+}
+
+struct Active {
+    session: Weak<Downstream>,
+    /// Requests waiting for an answer.
+    in_flight: usize,
+}
+
+impl Routes {
+    pub fn set_upstream(&self, name: &str) {
+        let _ = self.upstream.set(name.into());
+    }
+
+    /// Send `request` over `peer` for `session`, relaying progress back
+    /// under the token of the downstream request in `context`, and cancel
+    /// it after `timeout`.
+    pub async fn forward(
+        &self,
+        peer: &Peer<RoleClient>,
+        request: ClientRequest,
+        session: &Arc<Downstream>,
+        context: &RequestContext<RoleServer>,
+        timeout: Option<Duration>,
+    ) -> Result<ServerResult, ServiceError> {
+        let id = self.next_token.fetch_add(1, Ordering::Relaxed);
+        let token = ProgressToken(NumberOrString::String(format!("gateway-{id}").into()));
+        let _active = self.enter(session, &token, context.meta.get_progress_token());
+        let mut meta = Meta::new();
+        meta.set_progress_token(token);
+        let options = PeerRequestOptions {
+            timeout: None,
+            meta: Some(meta),
+        };
+        let handle = peer.send_request_with_option(request, options).await?;
+        answer(handle, &context.ct, timeout).await
+    }
+
+    /// Send a request the upstream started to the session waiting for
+    /// it, which must support `capability`.
+    async fn request(
+        &self,
+        request: ServerRequest,
+        capability: &str,
+        capable: fn(&ClientCapabilities) -> bool,
+        context: &RequestContext<RoleClient>,
+    ) -> Result<ClientResult, ErrorData> {
+        let session = match &self.waiting()[..] {
+            [session] if session.supports(capable) => session.clone(),
+            [_] => {
+                let message = format!("downstream session does not support {capability}");
+                return Err(ErrorData::new(ErrorCode::METHOD_NOT_FOUND, message, None));
+            }
+            [] => {
+                let message = format!("no downstream session is waiting for {capability}");
+                return Err(ErrorData::invalid_request(message, None));
+            }
+            _ => {
+                let message = format!(
+                    "several downstream sessions share the connection, \
+                     {capability} needs a per-session connection"
+                );
+                return Err(ErrorData::invalid_request(message, None));
+            }
+        };
+        let result = match session
+            .peer
+            .send_request_with_option(request, PeerRequestOptions::no_options())
+            .await
+        {
+            Ok(handle) => answer(handle, &context.ct, None).await,
+            Err(err) => Err(err),
+        };
+        result.map_err(|err| match err {
+            ServiceError::McpError(err) => err,
+            err => ErrorData::internal_error(format!("downstream session: {err}"), None),
+        })
+    }
+
+    /// Mark `session` active with one more request in flight, until the
+    /// returned guard is dropped.
+    fn enter<'a>(
+        &'a self,
+        session: &Arc<Downstream>,
+        token: &ProgressToken,
+        downstream_token: Option<ProgressToken>,
+    ) -> Exit<'a> {
+        let mut state = self.state();
+        state
+            .sessions
+            .retain(|active| active.session.strong_count() > 0);
+        let in_flight = match state
+            .sessions
+            .iter()
+            .position(|active| ptr_eq(&active.session, session))
+        {
+            Some(index) => state.sessions.remove(index).in_flight,
+            None => 0,
+        };
+        state.sessions.push(Active {
+            session: Arc::downgrade(session),
+            in_flight: in_flight + 1,
+        });
+        if let Some(downstream_token) = downstream_token {
+            state
+                .progress
+                .insert(token.clone(), (session.peer.clone(), downstream_token));
+        }
+        Exit {
+            routes: self,
+            session: Arc::downgrade(session),
+            token: token.clone(),
+        }
+    }
+
+    /// Whether `session` sent requests over the connections.
+    pub fn serves(&self, session: &Arc<Downstream>) -> bool {
+        self.state()
+            .sessions
+            .iter()
+            .any(|active| ptr_eq(&active.session, session))
+    }
+
+    /// Sessions with requests in flight over the connections.
+    fn waiting(&self) -> Vec<Arc<Downstream>> {
+        self.state()
+            .sessions
+            .iter()
+            .filter(|active| active.in_flight > 0)
+            .filter_map(|active| active.session.upgrade())
+            .collect()
+    }
+
+    /// Subscribe `session` to `uri`. Returns whether it is the first
+    /// subscriber, so the upstream needs subscribing.
+    pub fn subscribe(&self, uri: &str, session: &Arc<Downstream>) -> bool {
+        let mut state = self.state();
+        let subscribers = state.subscriptions.entry(uri.into()).or_default();
+        subscribers.retain(|subscriber| subscriber.strong_count() > 0);
+        let first = subscribers.is_empty();
+        if !subscribers.iter().any(|s| ptr_eq(s, session)) {
+            subscribers.push(Arc::downgrade(session));
+        }
+        first
+    }
+
+    /// Unsubscribe `session` from `uri`. Returns whether it was the last
+    /// subscriber, so the upstream needs unsubscribing.
+    pub fn unsubscribe(&self, uri: &str, session: &Arc<Downstream>) -> bool {
+        let mut state = self.state();
+        let Some(subscribers) = state.subscriptions.get_mut(uri) else {
+            return false;
+        };
+        subscribers.retain(|s| s.strong_count() > 0 && !ptr_eq(s, session));
+        if !subscribers.is_empty() {
+            return false;
+        }
+        state.subscriptions.remove(uri);
+        true
+    }
+
+    fn subscribers(&self, uri: &str) -> Vec<Arc<Downstream>> {
+        self.state()
+            .subscriptions
+            .get(uri)
+            .into_iter()
+            .flatten()
+            .filter_map(Weak::upgrade)
+            .collect()
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, RouteState> {
+        self.state.lock().expect("routes poisoned")
+    }
+}
+
+/// Ends a request [`Routes::enter`] started.
+struct Exit<'a> {
+    routes: &'a Routes,
+    session: Weak<Downstream>,
+    token: ProgressToken,
+}
+
+impl Drop for Exit<'_> {
+    fn drop(&mut self) {
+        let mut state = self.routes.state();
+        state.progress.remove(&self.token);
+        if let Some(active) = state
+            .sessions
+            .iter_mut()
+            .find(|active| Weak::ptr_eq(&active.session, &self.session))
+        {
+            active.in_flight -= 1;
+        }
+    }
+}
+
+fn ptr_eq(weak: &Weak<Downstream>, session: &Arc<Downstream>) -> bool {
+    std::ptr::eq(weak.as_ptr(), Arc::as_ptr(session))
+}
+
+/// Wait for the answer to `handle`, cancelling the request at the peer
+/// once `ct` is cancelled or `timeout` passes.
+async fn answer<R: ServiceRole>(
+    handle: RequestHandle<R>,
+    ct: &CancellationToken,
+    timeout: Option<Duration>,
+) -> Result<R::PeerResp, ServiceError> {
+    let RequestHandle { rx, id, peer, .. } = handle;
+    let expired = async {
+        match timeout {
+            Some(timeout) => tokio::time::sleep(timeout).await,
+            None => std::future::pending().await,
+        }
+    };
+    let (reason, err) = tokio::select! {
+        response = rx => return response.map_err(|_| ServiceError::TransportClosed)?,
+        () = ct.cancelled() => (None, ServiceError::Cancelled { reason: None }),
+        () = expired => {
+            let timeout = timeout.unwrap_or_default();
+            (Some("timed out".into()), ServiceError::Timeout { timeout })
+        }
+    };
+    let notification = CancelledNotification {
+        params: CancelledNotificationParam {
+            request_id: id,
+            reason,
+        },
+        method: Default::default(),
+        extensions: Default::default(),
+    };
+    peer.send_notification(notification.into()).await?;
+    Err(err)
+}
+
+fn unexpected() -> ErrorData {
+    ErrorData::internal_error("unexpected response from downstream session", None)
+}
+
+impl ClientHandler for Relay {
+    async fn create_message(
+        &self,
+        params: CreateMessageRequestParams,
+        context: RequestContext<RoleClient>,
+    ) -> Result<CreateMessageResult, ErrorData> {
+        let request = ServerRequest::CreateMessageRequest(CreateMessageRequest::new(params));
+        let capable = |c: &ClientCapabilities| c.sampling.is_some();
+        match self
+            .routes
+            .request(request, "sampling", capable, &context)
+            .await?
+        {
+            ClientResult::CreateMessageResult(result) => Ok(*result),
+            _ => Err(unexpected()),
+        }
+    }
+
+    async fn list_roots(
+        &self,
+        context: RequestContext<RoleClient>,
+    ) -> Result<ListRootsResult, ErrorData> {
+        let request = ServerRequest::ListRootsRequest(ListRootsRequest::default());
+        let capable = |c: &ClientCapabilities| c.roots.is_some();
+        match self
+            .routes
+            .request(request, "roots", capable, &context)
+            .await?
+        {
+            ClientResult::ListRootsResult(result) => Ok(result),
+            _ => Err(unexpected()),
+        }
+    }
+
+    async fn create_elicitation(
+        &self,
+        params: CreateElicitationRequestParams,
+        context: RequestContext<RoleClient>,
+    ) -> Result<CreateElicitationResult, ErrorData> {
+        let request =
+            ServerRequest::CreateElicitationRequest(CreateElicitationRequest::new(params));
+        let capable = |c: &ClientCapabilities| c.elicitation.is_some();
+        match self
+            .routes
+            .request(request, "elicitation", capable, &context)
+            .await?
+        {
+            ClientResult::CreateElicitationResult(result) => Ok(result),
+            _ => Err(unexpected()),
+        }
+    }
+
+    async fn on_progress(
+        &self,
+        params: ProgressNotificationParam,
+        _context: NotificationContext<RoleClient>,
+    ) {
+        let route = self
+            .routes
+            .state()
+            .progress
+            .get(&params.progress_token)
+            .cloned();
+        let Some((peer, progress_token)) = route else {
+            return;
+        };
+        let params = ProgressNotificationParam {
+            progress_token,
+            ..params
+        };
+        if let Err(e) = peer.notify_progress(params).await {
+            tracing::debug!("failed to relay progress: {e}");
+        }
+    }
+
+    async fn on_logging_message(
+        &self,
+        mut params: LoggingMessageNotificationParam,
+        _context: NotificationContext<RoleClient>,
+    ) {
+        if params.logger.is_none() {
+            params.logger = self.routes.upstream.get().cloned();
+        }
+        // Like upstream requests, logs could concern any waiting session.
+        let [session] = &self.routes.waiting()[..] else {
+            tracing::debug!("dropped log message without a single waiting session");
+            return;
+        };
+        if !session.logs(params.level) {
+            return;
+        }
+        if let Err(e) = session.peer.notify_logging_message(params).await {
+            tracing::debug!("failed to relay log message: {e}");
+        }
+    }
+
+    async fn on_resource_updated(
+        &self,
+        params: ResourceUpdatedNotificationParam,
+        _context: NotificationContext<RoleClient>,
+    ) {
+        for session in self.routes.subscribers(&params.uri) {
+            if let Err(e) = session.peer.notify_resource_updated(params.clone()).await {
+                tracing::debug!("failed to relay resource update: {e}");
+            }
+        }
+    }
+
+    /// Everything downstream sessions may support, as requests are only
+    /// relayed to sessions that do.
+    fn get_info(&self) -> ClientInfo {
+        ClientInfo {
+            capabilities: ClientCapabilities {
+                roots: Some(RootsCapabilities {
+                    list_changed: Some(true),
+                }),
+                sampling: Some(SamplingCapability::default()),
+                elicitation: Some(ElicitationCapability::default()),
+                ..Default::default()
+            },
+            client_info: Implementation::from_build_env(),
+            ..Default::default()
+        }
+    }
+}
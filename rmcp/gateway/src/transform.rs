@@ -0,0 +1,172 @@
+//! Rewriting the tools the gateway exposes.
+//!
+//! Transforms are keyed by the tool's name after prefixing, and can rename
+//! the tool, replace its description, pin parameters to fixed values and
+//! hide them from the schema, or give parameters defaults. Calls are mapped
+//! back to the upstream's own name and arguments. Policies and cache rules
+//! see the renamed tool, and the arguments as sent upstream.
+//!
+//! ```toml
+//! [transforms."fs.read_text_file"]
+//! name = "read_shared_file"
+//! description = "Read a text file below /srv/shared."
+//! defaults = { head = 100 }   # used when the caller leaves them out
+//! hidden = ["tail"]           # removed from the schema and calls
+//!
+//! [transforms.get_current_time]
+//! pinned = { timezone = "Europe/Berlin" }
+//! ```
+
+use rmcp::model::{JsonObject, Tool};
+use serde::Deserialize;
+use serde_json::Value;
+use std::sync::Arc;
+
+/// How to rewrite one tool.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ToolTransform {
+    /// Name to expose the tool under.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Description replacing the upstream's.
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Parameters always sent with these values. They are removed from
+    /// the schema, and values from callers are overridden.
+    #[serde(default)]
+    pub pinned: JsonObject,
+    /// Values for parameters the caller leaves out. They become optional
+    /// and document their default in the schema.
+    #[serde(default)]
+    pub defaults: JsonObject,
+    /// Parameters removed from the schema. Values from callers are dropped.
+    #[serde(default)]
+    pub hidden: Vec<String>,
+}
+
+impl ToolTransform {
+    /// Rename `tool` and rewrite its description and input schema.
+    pub(crate) fn apply(&self, tool: &mut Tool) {
+        if let Some(name) = &self.name {
+            tool.name = name.clone().into();
+        }
+        if let Some(description) = &self.description {
+            tool.description = Some(description.clone().into());
+        }
+        if self.pinned.is_empty() && self.defaults.is_empty() && self.hidden.is_empty() {
+            return;
+        }
+
+        let mut schema = tool.input_schema.as_ref().clone();
+        if let Some(Value::Object(properties)) = schema.get_mut("properties") {
+            properties.retain(|name, _| !self.removes(name));
+            for (name, value) in &self.defaults {
+                if let Some(Value::Object(property)) = properties.get_mut(name) {
+                    property.insert("default".into(), value.clone());
+                }
+            }
+        }
+        if let Some(Value::Array(required)) = schema.get_mut("required") {
+            required.retain(|name| {
+                name.as_str()
+                    .is_none_or(|name| !self.removes(name) && !self.defaults.contains_key(name))
+            });
+        }
+        tool.input_schema = Arc::new(schema);
+    }
+
+    /// Map a caller's arguments to the upstream's.
+    pub(crate) fn arguments(&self, arguments: Option<JsonObject>) -> Option<JsonObject> {
+        if self.pinned.is_empty() && self.defaults.is_empty() && self.hidden.is_empty() {
+            return arguments;
+        }
+        let mut arguments = arguments.unwrap_or_default();
+        arguments.retain(|name, _| !self.hidden.contains(name));
+        for (name, value) in &self.defaults {
+            arguments
+                .entry(name.clone())
+                .or_insert_with(|| value.clone());
+        }
+        arguments.extend(self.pinned.clone());
+        Some(arguments)
+    }
+
+    /// Whether the parameter is hidden from callers.
+    fn removes(&self, name: &str) -> bool {
+        self.pinned.contains_key(name) || self.hidden.iter().any(|hidden| hidden == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ToolTransform;
+    use rmcp::model::Tool;
+    use serde_json::{Value, json};
+    use std::sync::Arc;
+
+    fn object(value: Value) -> serde_json::Map<String, Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    fn transform() -> ToolTransform {
+        toml::from_str(
+            r#"
+            name = "read_shared_file"
+            description = "Read a shared file."
+            pinned = { root = "/srv/shared" }
+            defaults = { head = 100 }
+            hidden = ["tail"]
+            "#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn rewrites_schema() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "root": { "type": "string" },
+                "path": { "type": "string" },
+                "head": { "type": "integer" },
+                "tail": { "type": "integer" },
+            },
+            "required": ["root", "path", "head"],
+        });
+        let mut tool = Tool::new("read_text_file", "Read a file.", Arc::new(object(schema)));
+        transform().apply(&mut tool);
+
+        assert_eq!(tool.name, "read_shared_file");
+        assert_eq!(tool.description.as_deref(), Some("Read a shared file."));
+        assert_eq!(
+            Value::Object(tool.input_schema.as_ref().clone()),
+            json!({
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string" },
+                    "head": { "type": "integer", "default": 100 },
+                },
+                "required": ["path"],
+            })
+        );
+    }
+
+    #[test]
+    fn maps_arguments() {
+        let transform = transform();
+        assert_eq!(
+            transform.arguments(Some(object(json!({ "path": "a", "tail": 5, "root": "/" })))),
+            Some(object(
+                json!({ "path": "a", "head": 100, "root": "/srv/shared" })
+            ))
+        );
+        assert_eq!(
+            transform.arguments(Some(object(json!({ "path": "a", "head": 5 })))),
+            Some(object(
+                json!({ "path": "a", "head": 5, "root": "/srv/shared" })
+            ))
+        );
+        assert_eq!(ToolTransform::default().arguments(None), None);
+    }
+}
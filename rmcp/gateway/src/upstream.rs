@@ -0,0 +1,605 @@
+//! Connections to the MCP servers behind the gateway.
+
+use crate::{
+    error::Error,
+    health::{CircuitBreaker, HealthConfig, secs},
+    policy::Glob,
+    relay::{Downstream, Relay, Routes},
+};
+use futures::future::{join_all, try_join_all};
+use http::{HeaderName, HeaderValue};
+use rmcp::{
+    RoleClient, RoleServer, ServiceExt,
+    model::{
+        ClientRequest, PingRequest, Prompt, Resource, ResourceTemplate, ServerCapabilities,
+        ServerResult, Tool,
+    },
+    service::{Peer, PeerRequestOptions, RequestContext, RunningService},
+    transport::{
+        StreamableHttpClientTransport, TokioChildProcess,
+        streamable_http_client::StreamableHttpClientTransportConfig,
+    },
+};
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+use tokio::{
+    process::Command,
+    sync::{OwnedSemaphorePermit, Semaphore, SemaphorePermit},
+};
+
+/// How to reach an upstream server.
+///
+/// In configuration files, the variant is chosen by the presence of
+/// `command` or `url`.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(untagged, deny_unknown_fields)]
+pub enum Transport {
+    /// Stdio server launched as a child process.
+    Stdio {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+        #[serde(default)]
+        env: HashMap<String, String>,
+    },
+    /// Remote streamable HTTP server.
+    Http {
+        url: String,
+        /// Extra headers sent with every request.
+        #[serde(default)]
+        headers: HashMap<String, String>,
+        /// Bearer token, without the `Bearer ` prefix.
+        #[serde(default)]
+        auth: Option<String>,
+    },
+}
+
+/// An upstream server to aggregate.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct UpstreamConfig {
+    /// Unique name of the upstream.
+    pub name: String,
+    /// Prefix for exposed names, defaulting to `name`.
+    #[serde(default)]
+    pub prefix: Option<String>,
+    /// Gets the keys not named here, so `Transport` rejects unknown ones.
+    #[serde(flatten)]
+    pub transport: Transport,
+    /// Which of the upstream's tools to expose.
+    #[serde(default)]
+    pub tools: ToolFilter,
+    /// How downstream sessions share connections to the upstream.
+    #[serde(default)]
+    pub sessions: SessionStrategy,
+    /// Connections to open to a shared upstream. Requests are spread over
+    /// them in turn, each carrying many requests at once. Defaults to 1.
+    #[serde(default = "default_pool_size")]
+    pub pool_size: usize,
+    /// Most requests in flight to the upstream at once, over all its
+    /// connections. Further requests queue. Defaults to no limit.
+    #[serde(default)]
+    pub max_concurrency: Option<usize>,
+    /// Most dedicated connections open at once to a per-principal or
+    /// per-session upstream. Further sessions queue for one to close.
+    /// Defaults to no limit.
+    #[serde(default)]
+    pub max_connections: Option<usize>,
+    /// How long a request waits in the queue, for a request slot or a
+    /// dedicated connection, before failing. Defaults to 30 seconds.
+    #[serde(default = "default_queue_timeout", deserialize_with = "secs")]
+    pub queue_timeout: Duration,
+    /// How long to wait for the answer to a forwarded request before
+    /// cancelling it and counting a failure against the circuit breaker.
+    /// Zero waits forever. Defaults to 60 seconds.
+    #[serde(default = "default_request_timeout", deserialize_with = "secs")]
+    pub request_timeout: Duration,
+    /// How long to wait for a new connection to initialize before counting
+    /// it as failed. Zero waits forever. Defaults to 30 seconds.
+    #[serde(default = "default_connect_timeout", deserialize_with = "secs")]
+    pub connect_timeout: Duration,
+}
+
+fn default_pool_size() -> usize {
+    1
+}
+
+fn default_queue_timeout() -> Duration {
+    Duration::from_secs(30)
+}
+
+fn default_request_timeout() -> Duration {
+    Duration::from_secs(60)
+}
+
+fn default_connect_timeout() -> Duration {
+    Duration::from_secs(30)
+}
+
+/// Which downstream sessions share a connection to an upstream.
+///
+/// The gateway always keeps a shared connection for listing and health
+/// checks. With the other strategies, calls go over dedicated connections,
+/// so state such as a working directory doesn't leak between users.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionStrategy {
+    /// All sessions share the pooled connections.
+    #[default]
+    Shared,
+    /// Sessions of the same authenticated principal share a connection,
+    /// closed once the last of them ends. Anonymous sessions get their
+    /// own.
+    PerPrincipal,
+    /// Every session gets its own connection, closed when it ends.
+    PerSession,
+}
+
+/// Glob patterns selecting tools by their upstream name, e.g. `read_*`.
+///
+/// A tool is exposed if it matches any `include` pattern (or `include` is
+/// empty) and no `exclude` pattern.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ToolFilter {
+    #[serde(default)]
+    pub include: Vec<Glob>,
+    #[serde(default)]
+    pub exclude: Vec<Glob>,
+}
+
+impl ToolFilter {
+    pub fn allows(&self, tool: &str) -> bool {
+        let matches = |pattern: &Glob| pattern.matches(tool);
+        (self.include.is_empty() || self.include.iter().any(matches))
+            && !self.exclude.iter().any(matches)
+    }
+}
+
+impl UpstreamConfig {
+    /// Stdio server launched with `command` and `args`.
+    pub fn stdio<I>(name: impl Into<String>, command: impl Into<String>, args: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        Self {
+            name: name.into(),
+            prefix: None,
+            transport: Transport::Stdio {
+                command: command.into(),
+                args: args.into_iter().map(Into::into).collect(),
+                env: HashMap::new(),
+            },
+            tools: ToolFilter::default(),
+            sessions: SessionStrategy::default(),
+            pool_size: default_pool_size(),
+            max_concurrency: None,
+            max_connections: None,
+            queue_timeout: default_queue_timeout(),
+            request_timeout: default_request_timeout(),
+            connect_timeout: default_connect_timeout(),
+        }
+    }
+
+    /// Remote streamable HTTP server at `url`.
+    pub fn http(name: impl Into<String>, url: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            prefix: None,
+            transport: Transport::Http {
+                url: url.into(),
+                headers: HashMap::new(),
+                auth: None,
+            },
+            tools: ToolFilter::default(),
+            sessions: SessionStrategy::default(),
+            pool_size: default_pool_size(),
+            max_concurrency: None,
+            max_connections: None,
+            queue_timeout: default_queue_timeout(),
+            request_timeout: default_request_timeout(),
+            connect_timeout: default_connect_timeout(),
+        }
+    }
+
+    /// Prefix exposed names with `prefix` instead of the upstream name.
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = Some(prefix.into());
+        self
+    }
+
+    /// Expose only the tools allowed by `tools`.
+    pub fn tools(mut self, tools: ToolFilter) -> Self {
+        self.tools = tools;
+        self
+    }
+
+    /// Share connections between sessions according to `sessions`.
+    pub fn sessions(mut self, sessions: SessionStrategy) -> Self {
+        self.sessions = sessions;
+        self
+    }
+
+    /// Open `pool_size` connections to a shared upstream.
+    pub fn pool_size(mut self, pool_size: usize) -> Self {
+        self.pool_size = pool_size;
+        self
+    }
+
+    /// Allow at most `max` requests in flight, queueing others for up to
+    /// `queue_timeout`.
+    pub fn max_concurrency(mut self, max: usize, queue_timeout: Duration) -> Self {
+        self.max_concurrency = Some(max);
+        self.queue_timeout = queue_timeout;
+        self
+    }
+
+    /// Keep at most `max` dedicated connections open, queueing sessions
+    /// that need another for up to `queue_timeout`.
+    pub fn max_connections(mut self, max: usize, queue_timeout: Duration) -> Self {
+        self.max_connections = Some(max);
+        self.queue_timeout = queue_timeout;
+        self
+    }
+
+    /// Give up on forwarded requests after `timeout`, or never if zero.
+    pub fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = timeout;
+        self
+    }
+
+    /// Give up on connections not initialized after `timeout`, or never if
+    /// zero.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+}
+
+/// A connected upstream server.
+pub struct Upstream {
+    name: String,
+    prefix: String,
+    tools: ToolFilter,
+    /// The configuration it was connected from, if any.
+    config: Option<UpstreamConfig>,
+    /// Pooled connections, used in turn.
+    services: Vec<RunningService<RoleClient, Relay>>,
+    next: AtomicUsize,
+    /// Sessions using the connections, shared by their relays.
+    routes: Arc<Routes>,
+    pub(crate) breaker: CircuitBreaker,
+    /// Request slots, if concurrency is limited.
+    slots: Option<Semaphore>,
+    /// Dedicated connection slots, if their number is limited.
+    connections: Option<Arc<Semaphore>>,
+    /// The slot a dedicated connection holds until it is dropped.
+    _connection: Option<OwnedSemaphorePermit>,
+}
+
+/// Everything an upstream exposes.
+#[derive(Default)]
+pub(crate) struct Listing {
+    pub tools: Vec<Tool>,
+    pub prompts: Vec<Prompt>,
+    pub resources: Vec<Resource>,
+    pub templates: Vec<ResourceTemplate>,
+}
+
+impl Upstream {
+    /// Connect to the upstream described by `config`.
+    ///
+    /// Shared upstreams get a pool of `pool_size` connections, others a
+    /// single one for listing and health checks.
+    pub async fn connect(config: &UpstreamConfig) -> Result<Self, Error> {
+        let pool_size = match config.sessions {
+            SessionStrategy::Shared => config.pool_size.max(1),
+            _ => 1,
+        };
+        let relay = Relay::default();
+        let services =
+            try_join_all((0..pool_size).map(|_| connect_service(config, relay.clone()))).await?;
+        Ok(Self::from_services(&config.name, services).configured(config))
+    }
+
+    /// Stand-in for an upstream that failed to connect. It counts as closed
+    /// and its breaker is open, so health checks reconnect it.
+    pub(crate) fn unavailable(config: &UpstreamConfig, health: &HealthConfig) -> Self {
+        let upstream = Self::from_services(&config.name, Vec::new()).configured(config);
+        upstream.breaker.trip(health);
+        upstream
+    }
+
+    /// Apply the settings of `config`.
+    fn configured(mut self, config: &UpstreamConfig) -> Self {
+        if let Some(prefix) = &config.prefix {
+            self.prefix = prefix.clone();
+        }
+        self.tools = config.tools.clone();
+        self.config = Some(config.clone());
+        self.slots = config.max_concurrency.map(Semaphore::new);
+        self.connections = config
+            .max_connections
+            .map(|max| Arc::new(Semaphore::new(max)));
+        self
+    }
+
+    /// A single connection for one principal or session, holding `slot`
+    /// while open. The gateway counts its requests against the breaker and
+    /// limits of the shared upstream.
+    pub(crate) async fn dedicated(
+        config: &UpstreamConfig,
+        slot: Option<OwnedSemaphorePermit>,
+    ) -> Result<Self, Error> {
+        let service = connect_service(config, Relay::default()).await?;
+        let mut upstream = Self::from_services(&config.name, vec![service]);
+        upstream.config = Some(config.clone());
+        upstream._connection = slot;
+        Ok(upstream)
+    }
+
+    /// Wrap an already running client, e.g. over a custom transport.
+    pub fn from_service(
+        name: impl Into<String>,
+        service: RunningService<RoleClient, Relay>,
+    ) -> Self {
+        Self::from_services(name, vec![service])
+    }
+
+    /// Wrap `services`, which share one relay.
+    fn from_services(
+        name: impl Into<String>,
+        services: Vec<RunningService<RoleClient, Relay>>,
+    ) -> Self {
+        let name = name.into();
+        let routes = services
+            .first()
+            .map(|service| service.service().routes().clone())
+            .unwrap_or_default();
+        routes.set_upstream(&name);
+        Self {
+            prefix: name.clone(),
+            name,
+            tools: ToolFilter::default(),
+            config: None,
+            services,
+            next: AtomicUsize::new(0),
+            routes,
+            breaker: CircuitBreaker::default(),
+            slots: None,
+            connections: None,
+            _connection: None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// The configuration the upstream was connected from, or `None` if it
+    /// was attached with [`Upstream::from_service`].
+    pub fn config(&self) -> Option<&UpstreamConfig> {
+        self.config.as_ref()
+    }
+
+    /// The next pooled connection, or `None` if the upstream failed to
+    /// connect and wasn't reconnected yet.
+    pub fn peer(&self) -> Option<&Peer<RoleClient>> {
+        if self.services.is_empty() {
+            return None;
+        }
+        let next = self.next.fetch_add(1, Ordering::Relaxed);
+        Some(self.services[next % self.services.len()].peer())
+    }
+
+    /// Send `request` over the next pooled connection for `session`, see
+    /// [`relay`](crate::relay). Times out after the configured request
+    /// timeout.
+    pub(crate) async fn request(
+        &self,
+        request: ClientRequest,
+        session: &Arc<Downstream>,
+        context: &RequestContext<RoleServer>,
+    ) -> Result<ServerResult, rmcp::ServiceError> {
+        let timeout = self
+            .config
+            .as_ref()
+            .map(|config| config.request_timeout)
+            .filter(|timeout| !timeout.is_zero());
+        let peer = self.peer().ok_or(rmcp::ServiceError::TransportClosed)?;
+        self.routes
+            .forward(peer, request, session, context, timeout)
+            .await
+    }
+
+    /// Sessions using the connections.
+    pub(crate) fn routes(&self) -> &Routes {
+        &self.routes
+    }
+
+    /// Tell every pooled connection that the roots changed.
+    pub(crate) async fn notify_roots_list_changed(&self) {
+        for service in &self.services {
+            if let Err(e) = service.peer().notify_roots_list_changed().await {
+                tracing::debug!(upstream = self.name, "failed to notify roots change: {e}");
+            }
+        }
+    }
+
+    /// Whether any pooled connection has closed, or there are none.
+    pub fn is_closed(&self) -> bool {
+        self.services.is_empty()
+            || self
+                .services
+                .iter()
+                .any(|service| service.peer().is_transport_closed())
+    }
+
+    /// Whether the upstream's circuit breaker is closed.
+    pub fn is_healthy(&self) -> bool {
+        !self.breaker.is_open()
+    }
+
+    /// Capabilities announced by the upstream during initialization, none
+    /// if it isn't connected.
+    pub fn capabilities(&self) -> ServerCapabilities {
+        self.peer()
+            .and_then(|peer| peer.peer_info())
+            .map(|info| info.capabilities.clone())
+            .unwrap_or_default()
+    }
+
+    /// List everything the upstream announced a capability for.
+    pub(crate) async fn listing(&self) -> Result<Listing, Error> {
+        let peer = self
+            .peer()
+            .ok_or_else(|| self.error(rmcp::ServiceError::TransportClosed))?;
+        let capabilities = self.capabilities();
+        let mut listing = Listing::default();
+        let result = async {
+            if capabilities.tools.is_some() {
+                listing.tools = peer.list_all_tools().await?;
+                listing.tools.retain(|tool| self.tools.allows(&tool.name));
+            }
+            if capabilities.prompts.is_some() {
+                listing.prompts = peer.list_all_prompts().await?;
+            }
+            if capabilities.resources.is_some() {
+                listing.resources = peer.list_all_resources().await?;
+                listing.templates = peer.list_all_resource_templates().await?;
+            }
+            Ok(())
+        }
+        .await;
+        result.map_err(|source| self.error(source))?;
+        Ok(listing)
+    }
+
+    /// Ping every pooled connection, giving up after `timeout`.
+    pub(crate) async fn ping(&self, timeout: Duration) -> Result<(), rmcp::ServiceError> {
+        let pings = self.services.iter().map(|service| async move {
+            let request = ClientRequest::PingRequest(PingRequest::default());
+            let options = PeerRequestOptions {
+                timeout: Some(timeout),
+                meta: None,
+            };
+            service
+                .peer()
+                .send_request_with_option(request, options)
+                .await?
+                .await_response()
+                .await?;
+            Ok(())
+        });
+        join_all(pings).await.into_iter().collect()
+    }
+
+    /// Wait for a request slot, for at most the configured queue timeout.
+    ///
+    /// Returns `Ok(None)` if concurrency isn't limited, and `Err` if the
+    /// queue timed out.
+    pub(crate) async fn slot(&self) -> Result<Option<SemaphorePermit<'_>>, ()> {
+        let (Some(slots), Some(config)) = (&self.slots, &self.config) else {
+            return Ok(None);
+        };
+        match tokio::time::timeout(config.queue_timeout, slots.acquire()).await {
+            Ok(Ok(permit)) => Ok(Some(permit)),
+            _ => Err(()),
+        }
+    }
+
+    /// Wait for a dedicated connection slot, for at most the configured
+    /// queue timeout.
+    ///
+    /// Returns `Ok(None)` if dedicated connections aren't limited, and
+    /// `Err` if the queue timed out.
+    pub(crate) async fn connection_slot(&self) -> Result<Option<OwnedSemaphorePermit>, ()> {
+        let (Some(connections), Some(config)) = (&self.connections, &self.config) else {
+            return Ok(None);
+        };
+        let slot = connections.clone().acquire_owned();
+        match tokio::time::timeout(config.queue_timeout, slot).await {
+            Ok(Ok(permit)) => Ok(Some(permit)),
+            _ => Err(()),
+        }
+    }
+
+    pub(crate) fn error(&self, source: rmcp::ServiceError) -> Error {
+        Error::Upstream {
+            upstream: self.name.clone(),
+            source,
+        }
+    }
+}
+
+/// Open one connection to the upstream described by `config`, waiting at
+/// most its connect timeout for it to initialize.
+async fn connect_service(
+    config: &UpstreamConfig,
+    relay: Relay,
+) -> Result<RunningService<RoleClient, Relay>, Error> {
+    let timeout = config.connect_timeout;
+    if timeout.is_zero() {
+        return open_service(config, relay).await;
+    }
+    tokio::time::timeout(timeout, open_service(config, relay))
+        .await
+        .map_err(|_| Error::ConnectTimeout {
+            upstream: config.name.clone(),
+            timeout,
+        })?
+}
+
+async fn open_service(
+    config: &UpstreamConfig,
+    relay: Relay,
+) -> Result<RunningService<RoleClient, Relay>, Error> {
+    let init = |source| Error::ClientInit {
+        upstream: config.name.clone(),
+        source: Box::new(source),
+    };
+    let service = match &config.transport {
+        Transport::Stdio { command, args, env } => {
+            let mut cmd = Command::new(command);
+            // Kill children abandoned by a timed-out connect right away.
+            cmd.args(args).envs(env).kill_on_drop(true);
+            let transport = TokioChildProcess::new(cmd).map_err(|source| Error::Spawn {
+                upstream: config.name.clone(),
+                source,
+            })?;
+            relay.serve(transport).await.map_err(init)?
+        }
+        Transport::Http { url, headers, auth } => {
+            let mut http = StreamableHttpClientTransportConfig::with_uri(url.as_str());
+            for (name, value) in headers {
+                let header = HeaderName::try_from(name.as_str())
+                    .ok()
+                    .zip(HeaderValue::try_from(value.as_str()).ok());
+                let Some((name, value)) = header else {
+                    return Err(Error::InvalidHeader {
+                        upstream: config.name.clone(),
+                        header: name.clone(),
+                    });
+                };
+                http.custom_headers.insert(name, value);
+            }
+            if let Some(token) = auth {
+                http = http.auth_header(token);
+            }
+            let transport = StreamableHttpClientTransport::from_config(http);
+            relay.serve(transport).await.map_err(init)?
+        }
+    };
+    Ok(service)
+}
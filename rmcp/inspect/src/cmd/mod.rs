@@ -182,21 +182,21 @@ fn print_prompts(prompts: &[rmcp::model::Prompt]) {
         if let Some(desc) = &prompt.description {
             println!("    {desc}");
         }
-        if let Some(args) = &prompt.arguments {
-            if !args.is_empty() {
-                println!("    Arguments:");
-                for arg in args {
-                    let req = if arg.required == Some(true) {
-                        " (required)"
-                    } else {
-                        ""
-                    };
-                    let desc = arg.description.as_deref().unwrap_or("");
-                    if desc.is_empty() {
-                        println!("      {}{req}", arg.name);
-                    } else {
-                        println!("      {}{req} — {desc}", arg.name);
-                    }
+        if let Some(args) = &prompt.arguments
+            && !args.is_empty()
+        {
+            println!("    Arguments:");
+            for arg in args {
+                let req = if arg.required == Some(true) {
+                    " (required)"
+                } else {
+                    ""
+                };
+                let desc = arg.description.as_deref().unwrap_or("");
+                if desc.is_empty() {
+                    println!("      {}{req}", arg.name);
+                } else {
+                    println!("      {}{req} — {desc}", arg.name);
                 }
             }
         }